@@ -1,40 +1,119 @@
 use anyhow::Result;
-use ratatui::widgets::ListState;
+use ratatui::{layout::Rect, widgets::ListState};
 use std::{
-    fs::{self, DirEntry},
-    path::PathBuf,
+    collections::HashSet,
+    fs::{self, DirEntry, Metadata},
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
-use crate::commands::Command;
+use crate::{
+    bookmarks::Bookmarks,
+    columns::{ColumnCache, ViewLayout},
+    command_line::Completion,
+    commands::{
+        BackgroundCommand, Command, CommandHistory, ConflictPolicy, ConflictResolution,
+        CopyOptions, HistoryEntry, PastePlan,
+    },
+    config::Config,
+    filter::{Filter, FilterMode},
+    find::Search,
+    frecency::Frecency,
+    git::GitStatusLoader,
+    input_history::{HistoryBrowse, HistorySearch, InputHistory},
+    jobs::{JobKind, JobManager},
+    keymap::Keymap,
+    listing::ListingFilter,
+    navigation::{Cursor, NavigationHistory},
+    opener::{Launch, Opener},
+    preview::Previewer,
+    sort::{SortMemory, SortOrder},
+    tabs::Tab,
+    theme::Theme,
+    trash::{Trash, TrashEntry},
+    tree::TreeView,
+    watcher::DirWatcher,
+};
+
+/// Shown when a delete would take the current directory with it
+pub const CANT_DELETE_CURRENT_DIR: &str =
+    "Can't delete the current directory or one of its parents";
+
+/// How often to check whether cancelled jobs have stopped, when quitting
+const JOB_WAIT_INTERVAL: Duration = Duration::from_millis(20);
 
 pub struct App {
     // Core state:
     pub should_exit: bool,
     pub mode: AppMode,
+    pub return_mode: AppMode, // mode to go back to once a confirm or input prompt closes
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub opener: Opener,
+    pub pending_launch: Option<Launch>, // terminal program waiting for the UI to be suspended
 
     // Backend State:
     pub current_path: PathBuf,
     pub file_list: StatefulList<FileItem>,
+    pub filter: Filter,
+    pub sort: SortOrder,
+    pub default_sort: SortOrder, // for directories without a remembered order
+    pub sort_memory: Option<SortMemory>, // orders picked per directory, when configured
+    pub listing: ListingFilter,  // dotfiles and ignored entries left out of the list
+    pub ignored: HashSet<PathBuf>, // listed entries matched by ignore files, shown dimmed
+    pub tree: TreeView,          // expanded directories, when showing the list as a tree
+    pub previewer: Previewer,
+    pub git: GitStatusLoader, // status of the repository the current directory is in
+    pub watcher: DirWatcher,  // notices changes made to the listed and previewed directories
+    pub navigation: NavigationHistory,
+    pub jump_list: StatefulList<PathBuf>,
+    pub bookmarks: Bookmarks,
+    pub bookmark_list: StatefulList<(String, PathBuf)>,
+    pub mark_prompt: Option<MarkPrompt>, // waiting for the key after `m` or `'`
+    pub frecency: Frecency,
+    pub jumper_list: StatefulList<PathBuf>, // candidates in the frecency picker
+    pub search: Option<Search>,             // the last `:find`, and its matches so far
+    pub tabs: Vec<Tab>,
+    pub active_tab: usize,
+    pub other_pane: Tab, // the inactive pane of the dual-pane layout
+    pub left_pane_active: bool,
 
     // UI State:
     pub error_message: Option<String>,
     pub status_message: Option<String>,
     pub selection: Vec<usize>,
+    pub show_preview: bool,
+    pub layout: ViewLayout,
+    pub columns: ColumnCache, // listings beside the file list in the columns layout
 
     // help UI:
     pub help_scroll_offset: usize,
 
     // Input handling:
     pub input_buffer: String,
-    pub cursor_position: usize,
+    pub cursor_position: usize, // byte offset into `input_buffer`
     pub input_context: Option<InputContext>,
+    pub command_error: Option<String>, // mistake in the last command, shown under the command line
+    pub completion: Option<Completion>, // tab completion being cycled through
+    pub input_history: InputHistory,
+    pub history_browse: Option<HistoryBrowse>, // stepping through the history with Up/Down
+    pub history_search: Option<HistorySearch>, // Ctrl+R search in progress
 
     // Operation State:
-    pub active_command: Option<Box<dyn Command>>,
+    pub active_command: Option<Box<dyn BackgroundCommand>>, // awaiting confirmation
+    pub confirm_quit: bool, // awaiting confirmation to cancel running jobs and quit
+    pub history: CommandHistory,
+    pub jobs: JobManager,
     pub clipboard: Clipboard,
     pub clipboard_scroll_offset: usize,
+    pub trash_list: StatefulList<TrashEntry>,
+    pub conflict_policy: ConflictPolicy, // what to do when a paste collides with an existing item
+    pub pending_paste: Option<PastePlan>, // paste waiting on conflict prompts
 
     // Mouse control features:
+    pub list_area: ListArea, // where the file list was last drawn
     pub last_scroll_time: Option<std::time::Instant>,
     pub last_click_time: Option<std::time::Instant>,
     pub last_click_index: Option<usize>,
@@ -43,19 +122,100 @@ pub struct App {
 impl App {
     pub fn new() -> Result<Self> {
         let current_path = std::env::current_dir()?;
+
+        // a broken config shouldn't stop the app from starting:
+        let (config, config_error) = match Config::load() {
+            Ok(config) => (config, None),
+            Err(e) => (Config::default(), Some(format!("Config error: {:#}", e))),
+        };
+        let (frecency, frecency_error) = match Frecency::load() {
+            Ok(frecency) => (frecency, None),
+            Err(e) => (
+                Frecency::default(),
+                Some(format!("Frecency error: {:#}", e)),
+            ),
+        };
+        let (sort_memory, sort_error) = match config.sort_per_directory.then(SortMemory::load) {
+            None => (None, None),
+            Some(Ok(memory)) => (Some(memory), None),
+            Some(Err(e)) => (
+                Some(SortMemory::default()),
+                Some(format!("Sort memory error: {:#}", e)),
+            ),
+        };
+        let (input_history, history_error) = match InputHistory::load() {
+            Ok(history) => (history, None),
+            Err(e) => (
+                InputHistory::default(),
+                Some(format!("History error: {:#}", e)),
+            ),
+        };
+        let (watcher, watcher_error) = match DirWatcher::new() {
+            Ok(watcher) => (watcher, None),
+            Err(e) => (
+                DirWatcher::default(),
+                Some(format!("Can't watch for changes: {:#}", e)),
+            ),
+        };
+        let (bookmarks, bookmarks_error) = match Bookmarks::load() {
+            Ok(bookmarks) => (bookmarks, None),
+            Err(e) => (
+                Bookmarks::default(),
+                Some(format!("Bookmarks error: {:#}", e)),
+            ),
+        };
+
+        // the second pane starts out in the same directory:
+        let other_pane = Tab::new(current_path.clone(), config.sort);
+
         let mut app = Self {
             // Core state:
             should_exit: false,
             mode: AppMode::Normal,
+            return_mode: AppMode::Normal,
+            previewer: Previewer::new(config.theme.syntax.clone()),
+            git: GitStatusLoader::new(),
+            watcher,
+            keymap: config.keymap,
+            theme: config.theme,
+            opener: config.opener,
+            pending_launch: None,
 
             // Backend State:
-            current_path: current_path,
+            current_path,
             file_list: StatefulList::new(),
+            filter: Filter::default(),
+            sort: config.sort,
+            default_sort: config.sort,
+            sort_memory,
+            listing: config.listing,
+            ignored: HashSet::new(),
+            tree: TreeView::default(),
+            navigation: NavigationHistory::default(),
+            jump_list: StatefulList::new(),
+            bookmarks,
+            bookmark_list: StatefulList::new(),
+            mark_prompt: None,
+            frecency,
+            jumper_list: StatefulList::new(),
+            search: None,
+            tabs: vec![Tab::default()],
+            active_tab: 0,
+            other_pane,
+            left_pane_active: true,
 
             // UI State:
-            error_message: None,
+            error_message: config_error
+                .or(bookmarks_error)
+                .or(frecency_error)
+                .or(history_error)
+                .or(sort_error)
+                .or(watcher_error),
             status_message: None,
             selection: Vec::new(),
+            show_preview: true,
+            layout: config.layout,
+            columns: ColumnCache::default(),
 
             // help UI:
             help_scroll_offset: 0,
@@ -64,19 +224,39 @@ impl App {
             input_buffer: String::new(),
             cursor_position: 0,
             input_context: None,
+            command_error: None,
+            completion: None,
+            input_history,
+            history_browse: None,
+            history_search: None,
 
             // Operation State:
             active_command: None,
+            confirm_quit: false,
+            history: CommandHistory::new(),
+            jobs: JobManager::new(),
             clipboard: Clipboard::new(),
             clipboard_scroll_offset: 0,
+            trash_list: StatefulList::new(),
+            conflict_policy: config.conflict_policy,
+            pending_paste: None,
 
             // Mouse control features:
             last_scroll_time: None,
             last_click_time: None,
+            list_area: ListArea::default(),
             last_click_index: None,
         };
 
+        if let Some(order) = app
+            .sort_memory
+            .as_ref()
+            .and_then(|memory| memory.get(&app.current_path))
+        {
+            app.sort = order;
+        }
         app.refresh_file_list()?;
+        app.navigation.visit(&app.current_path);
 
         Ok(app)
     }
@@ -86,20 +266,8 @@ impl App {
     }
 
     pub fn refresh_file_list(&mut self) -> Result<()> {
-        let mut entries = fs::read_dir(&self.current_path)?
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| FileItem::from_dir_entry(entry).ok())
-            .collect::<Vec<_>>();
-
-        // sort entries:
-        entries.sort_by(|a, b| {
-            match (a.is_dir, b.is_dir) {
-                // all folders appear before files
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-            }
-        });
+        let mut entries = FileItem::read_dir(&self.current_path, &self.sort)?;
+        self.ignored = self.listing.apply(&self.current_path, &mut entries);
 
         // check if root, if not, add parent directory to top of list:
         if let Some(parent) = self.current_path.parent() {
@@ -109,17 +277,171 @@ impl App {
                     name: "..".to_string(),
                     path: parent.to_path_buf(),
                     is_dir: true,
+                    kind: FileKind::Directory,
                     size: None,
                     modified: None,
+                    depth: 0,
+                    parent: None,
                 },
             );
         }
+        if self.tree.enabled {
+            entries = self
+                .tree
+                .build(entries, &self.sort, &self.listing, &mut self.ignored);
+        }
+
+        // remember the multi-selection by path, since indices change when the list is rebuilt:
+        let selected_paths = self
+            .selected_items()
+            .iter()
+            .map(|item| item.path.clone())
+            .collect::<Vec<_>>();
 
         // update self
         self.file_list = StatefulList::new_with_items(entries);
+        self.selection = self
+            .file_list
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| selected_paths.contains(&item.path))
+            .map(|(i, _)| i)
+            .collect();
+
+        self.apply_filter();
+
+        // the selected item may have changed on disk:
+        self.previewer.invalidate();
+        self.git.request(&self.current_path);
+
+        // and so may the columns beside the list, or the other pane showing the same directory:
+        self.columns.invalidate(&self.current_path);
+        if let Some(parent) = self.current_path.parent() {
+            self.columns.invalidate(parent);
+        }
+        for item in self.file_list.items.iter().filter(|item| item.is_dir) {
+            self.columns.invalidate(&item.path);
+        }
+
+        Ok(())
+    }
+
+    /// Re-read the current directory, keeping the cursor on the same item, or at about the
+    /// same position if it has gone
+    pub fn reload_file_list(&mut self) -> Result<()> {
+        let selected = self.file_list.selected().map(|item| item.path.clone());
+        let index = self.file_list.state.selected();
+        self.refresh_file_list()?;
+
+        if let Some(selected) = selected
+            && !self.select_path(&selected)
+            && let Some(index) = index
+            && !self.file_list.items.is_empty()
+        {
+            let index = index.min(self.file_list.items.len() - 1);
+            let visible = self.file_list.filtered_items.clone();
+            self.file_list.state.select(Some(index));
+            self.file_list.set_filtered_items(visible);
+        }
+        Ok(())
+    }
+
+    /// Narrow the visible file list down to the items matching the current filter
+    pub fn apply_filter(&mut self) {
+        let mut matches = self
+            .file_list
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| self.filter.matches(&item.name).map(|m| (i, m.score)))
+            .collect::<Vec<_>>();
+
+        // the tree keeps its order, and the directories leading down to each match:
+        if self.tree.enabled && !self.filter.is_empty() {
+            let mut visible = vec![false; self.file_list.items.len()];
+            for &(i, _) in &matches {
+                let mut node = Some(i);
+                while let Some(i) = node.filter(|&i| !visible[i]) {
+                    visible[i] = true;
+                    node = self.file_list.items[i].parent;
+                }
+            }
+            let visible = (0..visible.len()).filter(|&i| visible[i]).collect();
+            self.file_list.set_filtered_items(visible);
+            return;
+        }
+
+        // fuzzy matches are ranked best-first:
+        if self.filter.mode == FilterMode::Fuzzy && !self.filter.is_empty() {
+            matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        }
+
+        self.file_list
+            .set_filtered_items(matches.into_iter().map(|(i, _)| i).collect());
+    }
+
+    pub fn set_filter(&mut self, pattern: String) {
+        self.filter.pattern = pattern;
+        self.apply_filter();
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.filter.pattern.clear();
+        self.apply_filter();
+    }
+
+    /// Re-sort the file list, keeping the cursor on the same item, and remember the order
+    /// for this directory if sorting per directory
+    pub fn set_sort(&mut self, order: SortOrder) {
+        self.sort = order;
+        if let Some(memory) = &mut self.sort_memory
+            && let Err(e) = memory.set(&self.current_path, order)
+        {
+            self.set_error(format!("Couldn't remember sort order: {:#}", e));
+        }
+        if let Err(e) = self.reload_file_list() {
+            self.set_error(format!("Failed to refresh: {}", e));
+        }
+        self.set_status(format!("Sort: {}", self.sort.label()));
+    }
+
+    /// Show or hide dotfiles and ignored entries, keeping the cursor on the same item
+    /// when it's still listed
+    pub fn set_listing(&mut self, listing: ListingFilter) {
+        self.listing = listing;
+        if let Err(e) = self.reload_file_list() {
+            self.set_error(format!("Failed to refresh: {}", e));
+        }
+        self.set_status(format!(
+            "Hidden files: {}, ignored files: {}",
+            if listing.show_hidden {
+                "shown"
+            } else {
+                "hidden"
+            },
+            listing.ignore.label()
+        ));
+    }
+
+    pub fn refresh_trash_list(&mut self) -> Result<()> {
+        let entries = Trash::home()?.list()?;
+        let selected = self.trash_list.state.selected();
+        self.trash_list = StatefulList::new_with_items(entries);
+
+        // keep the cursor roughly where it was:
+        if !self.trash_list.items.is_empty() {
+            let selected = selected.unwrap_or(0).min(self.trash_list.items.len() - 1);
+            self.trash_list.state.select(Some(selected));
+        }
 
-        // todo: apply filtering
+        Ok(())
+    }
 
+    pub fn open_trash(&mut self) -> Result<()> {
+        self.trash_list = StatefulList::new();
+        self.refresh_trash_list()?;
+        self.mode = AppMode::Trash;
         Ok(())
     }
 
@@ -130,39 +452,273 @@ impl App {
 
     pub fn navigate_to(&mut self, path: PathBuf) -> Result<()> {
         if path.is_dir() {
-            // todo: keep track of browsing history here?
-            self.current_path = path;
-            self.refresh_file_list()?;
-            self.clear_messages();
+            if path != self.current_path {
+                self.navigation.push(self.current_path.clone());
+            }
+            self.change_dir(path)?;
         }
         Ok(())
     }
 
     pub fn navigate_up(&mut self) -> Result<()> {
         if let Some(parent) = self.current_path.parent() {
+            let child = self.current_path.clone();
             self.navigate_to(parent.to_path_buf())?;
+            // land on the directory we just came out of:
+            self.select_path(&child);
         }
         Ok(())
     }
 
+    pub fn go_back(&mut self) -> Result<()> {
+        match self.navigation.go_back(&self.current_path) {
+            Some(path) => self.change_dir(path),
+            None => {
+                self.set_status("No previous directory".to_string());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn go_forward(&mut self) -> Result<()> {
+        match self.navigation.go_forward(&self.current_path) {
+            Some(path) => self.change_dir(path),
+            None => {
+                self.set_status("No next directory".to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// Switch to `path` without touching the back/forward stacks, leaving the cursor
+    /// wherever it was the last time `path` was visited
+    fn change_dir(&mut self, path: PathBuf) -> Result<()> {
+        if let Some(item) = self.file_list.selected() {
+            let cursor = Cursor {
+                item: item.path.clone(),
+                index: self.file_list.state.selected().unwrap_or(0),
+            };
+            self.navigation.save_cursor(&self.current_path, cursor);
+        }
+
+        let changed = path != self.current_path;
+        self.current_path = path;
+        self.filter.pattern.clear();
+        if let Some(memory) = &self.sort_memory {
+            self.sort = memory.get(&self.current_path).unwrap_or(self.default_sort);
+        }
+        self.refresh_file_list()?;
+        self.navigation.visit(&self.current_path);
+        self.clear_messages();
+        if changed && let Err(e) = self.frecency.record(&self.current_path) {
+            self.set_error(format!("Couldn't record visit: {:#}", e));
+        }
+
+        if let Some(cursor) = self.navigation.cursor(&self.current_path).cloned()
+            && !self.select_path(&cursor.item)
+            && !self.file_list.items.is_empty()
+        {
+            // the item has gone, so stay at about the same position:
+            let index = cursor.index.min(self.file_list.items.len() - 1);
+            self.file_list.state.select(Some(index));
+        }
+        Ok(())
+    }
+
+    /// Put the cursor on the item at `path`, if it's in the list
+    pub fn select_path(&mut self, path: &Path) -> bool {
+        let index = self
+            .file_list
+            .items
+            .iter()
+            .position(|item| item.path == path);
+        match index {
+            Some(index) if self.file_list.filtered_items.contains(&index) => {
+                self.file_list.state.select(Some(index));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn open_jump_list(&mut self) {
+        let dirs = self
+            .navigation
+            .recent()
+            .iter()
+            .filter(|dir| **dir != self.current_path)
+            .cloned()
+            .collect::<Vec<_>>();
+        if dirs.is_empty() {
+            self.set_status("No other directories visited yet".to_string());
+            return;
+        }
+        self.jump_list = StatefulList::new_with_items(dirs);
+        self.jump_list.state.select(Some(0));
+        self.mode = AppMode::JumpList;
+    }
+
+    pub fn jump_to_selected(&mut self) -> Result<()> {
+        self.mode = AppMode::Normal;
+        if let Some(dir) = self.jump_list.selected().cloned() {
+            if dir.is_dir() {
+                self.navigate_to(dir)?;
+            } else {
+                self.set_error(format!("'{}' no longer exists", dir.display()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Jump to the best ranked directory matching `keywords`, like `z` from zoxide
+    pub fn jump_to_frecent(&mut self, keywords: &[&str]) -> Result<()> {
+        match self.frecency.best_match(keywords, &self.current_path) {
+            Some(dir) => self.navigate_to(dir)?,
+            None => self.set_error(format!(
+                "No visited directory matches '{}'",
+                keywords.join(" ")
+            )),
+        }
+        Ok(())
+    }
+
+    /// Open the picker of visited directories, best ranked first
+    pub fn open_jumper(&mut self) {
+        let dirs = self
+            .frecency
+            .ranked()
+            .into_iter()
+            .filter(|dir| *dir != self.current_path)
+            .collect::<Vec<_>>();
+        if dirs.is_empty() {
+            self.set_status("No other directories visited yet".to_string());
+            return;
+        }
+        self.jumper_list = StatefulList::new_with_items(dirs);
+        self.clear_input_buffer();
+        self.mode = AppMode::Jumper;
+        self.apply_jumper_filter();
+    }
+
+    /// Narrow the picker down to directories fuzzy-matching the typed query
+    pub fn apply_jumper_filter(&mut self) {
+        let filter = Filter {
+            pattern: self.input_buffer.clone(),
+            mode: FilterMode::Fuzzy,
+        };
+        let mut matches = self
+            .jumper_list
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, dir)| filter.matches(&dir.to_string_lossy()).map(|m| (i, m.score)))
+            .collect::<Vec<_>>();
+
+        // best matches first, then by rank (the sort is stable):
+        matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        self.jumper_list
+            .set_filtered_items(matches.into_iter().map(|(i, _)| i).collect());
+        let first = self.jumper_list.filtered_items.first().copied();
+        self.jumper_list.state.select(first);
+    }
+
+    pub fn jump_to_jumper_selection(&mut self) -> Result<()> {
+        self.mode = AppMode::Normal;
+        let selected = self.jumper_list.selected().cloned();
+        self.clear_input_buffer();
+        if let Some(dir) = selected {
+            self.navigate_to(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Bookmark `dir` as `name`
+    pub fn add_bookmark(&mut self, name: &str, dir: PathBuf) {
+        match self.bookmarks.add(name, dir.clone()) {
+            Ok(()) => self.set_status(format!("Bookmarked '{}' as '{}'", dir.display(), name)),
+            Err(e) => self.set_error(format!("Couldn't save bookmark: {:#}", e)),
+        }
+        self.refresh_bookmark_list();
+    }
+
+    pub fn remove_bookmark(&mut self, name: &str) {
+        match self.bookmarks.remove(name) {
+            Ok(Some(_)) => self.set_status(format!("Removed bookmark '{}'", name)),
+            Ok(None) => self.set_error(format!("No bookmark named '{}'", name)),
+            Err(e) => self.set_error(format!("Couldn't save bookmarks: {:#}", e)),
+        }
+        self.refresh_bookmark_list();
+    }
+
+    pub fn jump_to_bookmark(&mut self, name: &str) -> Result<()> {
+        match self.bookmarks.get(name).map(Path::to_path_buf) {
+            Some(dir) if dir.is_dir() => self.navigate_to(dir)?,
+            Some(dir) => self.set_error(format!(
+                "Bookmark '{}' points to '{}', which no longer exists",
+                name,
+                dir.display()
+            )),
+            None => self.set_error(format!("No bookmark named '{}'", name)),
+        }
+        Ok(())
+    }
+
+    pub fn open_bookmarks(&mut self) {
+        self.bookmark_list = StatefulList::new();
+        self.refresh_bookmark_list();
+        self.mode = AppMode::Bookmarks;
+    }
+
+    fn refresh_bookmark_list(&mut self) {
+        let entries = self
+            .bookmarks
+            .iter()
+            .map(|(name, dir)| (name.to_string(), dir.to_path_buf()))
+            .collect::<Vec<_>>();
+        let selected = self.bookmark_list.state.selected();
+        self.bookmark_list = StatefulList::new_with_items(entries);
+
+        // keep the cursor roughly where it was:
+        if !self.bookmark_list.items.is_empty() {
+            let selected = selected
+                .unwrap_or(0)
+                .min(self.bookmark_list.items.len() - 1);
+            self.bookmark_list.state.select(Some(selected));
+        }
+    }
+
     pub fn enter_selected(&mut self) -> Result<()> {
         if let Some(selected_item) = self.file_list.selected().cloned() {
-            if selected_item.is_dir {
+            // symlinks to directories are entered too:
+            if selected_item.is_dir || selected_item.path.is_dir() {
                 self.navigate_to(selected_item.path)?;
             } else {
-                // todo: open file with relevant application
-                // use system default app?
+                self.open_file(&selected_item.path)?;
             }
         }
         Ok(())
     }
 
-    pub fn scroll_help_down(&mut self, content_length: usize, viewport_height: usize) {
-        let max_scroll = if content_length > viewport_height {
-            content_length - viewport_height
-        } else {
-            0
+    /// Open a file with the program chosen by the opener rules
+    pub fn open_file(&mut self, path: &Path) -> Result<()> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let Some(launch) = self.opener.launch_for(path) else {
+            self.set_error(format!("No program configured to open '{}'", name));
+            return Ok(());
         };
+
+        if launch.terminal {
+            // the main loop hands the terminal over to it:
+            self.pending_launch = Some(launch);
+        } else {
+            launch.spawn_detached()?;
+            self.set_status(format!("Opened '{}' with {}", name, launch.program()));
+        }
+        Ok(())
+    }
+
+    pub fn scroll_help_down(&mut self, content_length: usize, viewport_height: usize) {
+        let max_scroll = content_length.saturating_sub(viewport_height);
         if self.help_scroll_offset < max_scroll {
             self.help_scroll_offset += 1;
         }
@@ -184,14 +740,17 @@ impl App {
     }
 
     pub fn move_cursor_left(&mut self) {
-        if self.cursor_position > 0 {
-            self.cursor_position -= 1;
+        if let Some(c) = self.input_buffer[..self.cursor_position]
+            .chars()
+            .next_back()
+        {
+            self.cursor_position -= c.len_utf8();
         }
     }
 
     pub fn move_cursor_right(&mut self) {
-        if self.cursor_position < self.input_buffer.len() {
-            self.cursor_position += 1;
+        if let Some(c) = self.input_buffer[self.cursor_position..].chars().next() {
+            self.cursor_position += c.len_utf8();
         }
     }
 
@@ -205,8 +764,8 @@ impl App {
 
     pub fn delete_char_before_cursor(&mut self) -> Option<char> {
         if self.cursor_position > 0 {
-            self.cursor_position -= 1;
-            Some(self.input_buffer.remove(self.cursor_position))
+            self.move_cursor_left();
+            self.delete_char_at_cursor()
         } else {
             None
         }
@@ -222,7 +781,113 @@ impl App {
 
     pub fn insert_char_at_cursor(&mut self, c: char) {
         self.input_buffer.insert(self.cursor_position, c);
-        self.cursor_position += 1;
+        self.cursor_position += c.len_utf8();
+    }
+
+    /// Replace the whole input, keeping a filter being typed in sync
+    fn replace_input(&mut self, text: String) {
+        self.input_buffer = text;
+        self.move_cursor_end();
+        if self.input_context == Some(InputContext::Filter) {
+            self.set_filter(self.input_buffer.clone());
+        }
+    }
+
+    /// Save a line entered in the current input to its history
+    pub fn record_input(&mut self, line: &str) {
+        let Some(kind) = self.input_context.as_ref().map(InputContext::history_kind) else {
+            return;
+        };
+        if let Err(e) = self.input_history.add(kind, line) {
+            self.set_error(format!("Failed to save history: {}", e));
+        }
+    }
+
+    /// Put the previous history entry starting with what was typed into the input
+    pub fn history_prev(&mut self) {
+        let Some(kind) = self.input_context.as_ref().map(InputContext::history_kind) else {
+            return;
+        };
+        let browse = self.history_browse.take().unwrap_or(HistoryBrowse {
+            index: self.input_history.entries(kind).len(),
+            draft: self.input_buffer.clone(),
+        });
+        match self
+            .input_history
+            .previous(kind, Some(browse.index), &browse.draft)
+        {
+            Some(index) => {
+                self.replace_input(self.input_history.entries(kind)[index].clone());
+                self.history_browse = Some(HistoryBrowse { index, ..browse });
+            }
+            // already at the oldest:
+            None if browse.index < self.input_history.entries(kind).len() => {
+                self.history_browse = Some(browse);
+            }
+            None => {}
+        }
+    }
+
+    /// Put the next history entry into the input, or what was typed before browsing once past the newest
+    pub fn history_next(&mut self) {
+        let Some(kind) = self.input_context.as_ref().map(InputContext::history_kind) else {
+            return;
+        };
+        let Some(browse) = self.history_browse.take() else {
+            return;
+        };
+        match self.input_history.next(kind, browse.index, &browse.draft) {
+            Some(index) => {
+                self.replace_input(self.input_history.entries(kind)[index].clone());
+                self.history_browse = Some(HistoryBrowse { index, ..browse });
+            }
+            None => self.replace_input(browse.draft),
+        }
+    }
+
+    pub fn start_history_search(&mut self) {
+        self.history_browse = None;
+        self.history_search = Some(HistorySearch::default());
+    }
+
+    /// Find the newest entry matching the search query, or with `older` the next one back
+    pub fn update_history_search(&mut self, older: bool) {
+        let Some(kind) = self.input_context.as_ref().map(InputContext::history_kind) else {
+            return;
+        };
+        let Some(search) = &mut self.history_search else {
+            return;
+        };
+        let before = if older { search.found } else { None };
+        match self.input_history.search(kind, &search.query, before) {
+            Some(found) => search.found = Some(found),
+            // keep showing the last match when there's nothing older:
+            None if older => {}
+            None => search.found = None,
+        }
+    }
+
+    /// Finish a search, putting the match (if any) into the input
+    pub fn accept_history_search(&mut self) {
+        let Some(search) = self.history_search.take() else {
+            return;
+        };
+        let Some(kind) = self.input_context.as_ref().map(InputContext::history_kind) else {
+            return;
+        };
+        if let Some(found) = search.found {
+            self.replace_input(self.input_history.entries(kind)[found].clone());
+        }
+    }
+
+    /// The entry a search has found, for display
+    pub fn history_search_match(&self) -> Option<&str> {
+        let kind = self.input_context.as_ref()?.history_kind();
+        let found = self.history_search.as_ref()?.found?;
+        self.input_history
+            .entries(kind)
+            .get(found)
+            .map(String::as_str)
     }
 
     pub fn clear_multi_selection(&mut self) {
@@ -239,6 +904,13 @@ impl App {
         }
     }
 
+    /// Whether deleting `targets` would take the current directory with it (e.g. `..`)
+    pub fn deletes_current_dir(&self, targets: &[PathBuf]) -> bool {
+        targets
+            .iter()
+            .any(|target| self.current_path.starts_with(target))
+    }
+
     pub fn selected_items(&self) -> Vec<&FileItem> {
         self.selection
             .iter()
@@ -247,11 +919,7 @@ impl App {
     }
 
     pub fn scroll_clipboard_down(&mut self, content_length: usize, viewport_height: usize) {
-        let max_scroll = if content_length > viewport_height {
-            content_length - viewport_height
-        } else {
-            0
-        };
+        let max_scroll = content_length.saturating_sub(viewport_height);
         if self.clipboard_scroll_offset < max_scroll {
             self.clipboard_scroll_offset += 1;
         }
@@ -263,33 +931,334 @@ impl App {
         }
     }
 
-    pub fn execute_command(&mut self, command: &str) -> Result<()> {
-        let parts: Vec<&str> = command.trim_end().split_whitespace().collect();
-        if parts.is_empty() {
-            return Ok(());
+    /// Execute a command, keeping it in the undo history if it can be undone
+    pub fn run_command(&mut self, mut command: Box<dyn Command>) -> Result<()> {
+        command.execute(self)?;
+        if command.can_undo() {
+            self.history.record(command);
+        }
+        Ok(())
+    }
+
+    /// Run a command on a worker thread. It is finished off in `process_job_events`
+    pub fn spawn_job(&mut self, command: Box<dyn BackgroundCommand>) {
+        self.start_job(command, JobKind::Execute);
+    }
+
+    fn start_job(&mut self, command: Box<dyn BackgroundCommand>, kind: JobKind) {
+        self.set_status(format!("Started: {}", kind.describe(command.as_ref())));
+        self.jobs.spawn(command, kind);
+    }
+
+    /// Quit, first asking whether to cancel any jobs that are still running
+    pub fn request_quit(&mut self) {
+        if self.jobs.is_empty() {
+            self.should_exit = true;
+            return;
+        }
+        self.return_mode = std::mem::replace(&mut self.mode, AppMode::Confirm);
+        self.confirm_quit = true;
+        self.set_status(format!(
+            "{} job(s) still running. Cancel them and quit? (y/n)",
+            self.jobs.jobs.len()
+        ));
+    }
+
+    /// Cancel all running jobs and wait for them to stop, so exiting doesn't cut them off
+    /// part way through a file
+    pub fn cancel_jobs(&mut self) {
+        self.jobs.cancel_all();
+        while !self.jobs.is_empty() {
+            thread::sleep(JOB_WAIT_INTERVAL);
+            self.process_job_events();
+        }
+    }
+
+    /// Paste the clipboard into the current directory (or the one picked in the tree view),
+    /// prompting for any conflicts
+    pub fn paste(&mut self, options: CopyOptions) {
+        if self.clipboard.items.is_empty() {
+            return;
         }
 
-        let command = parts[0].trim().to_lowercase();
-        match command.as_str() {
-            "q" | "quit" | "exit" => {
-                self.should_exit = true;
+        let mut plan = match PastePlan::new(
+            self.clipboard.operation.clone(),
+            self.clipboard.items.clone(),
+            self.target_dir(),
+            options,
+        ) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.set_error(format!("Couldn't paste: {:#}", e));
+                return;
             }
+        };
+        plan.from_clipboard = true;
+        self.transfer(plan);
+    }
 
-            "h" | "help" => {
-                self.mode = AppMode::Help;
-                self.clear_input_buffer();
+    /// Start a copy/move, first prompting for any conflicts the conflict policy doesn't settle
+    pub fn transfer(&mut self, mut plan: PastePlan) {
+        if let ConflictPolicy::Always(resolution) = self.conflict_policy {
+            plan.apply_to_all = true;
+            if let Err(e) = plan.resolve(resolution) {
+                self.set_error(format!("Couldn't paste: {:#}", e));
+                return;
             }
+            // anything left (conflicts that can't be merged) is asked about one at a time:
+            plan.apply_to_all = false;
+        }
+
+        if plan.current_conflict().is_some() {
+            self.pending_paste = Some(plan);
+            self.mode = AppMode::Conflict;
+        } else {
+            self.start_paste(plan);
+        }
+    }
+
+    /// Answer the current conflict prompt, starting the paste once nothing is left to ask
+    pub fn resolve_conflict(&mut self, resolution: ConflictResolution) {
+        let Some(mut plan) = self.pending_paste.take() else {
+            self.mode = AppMode::Normal;
+            return;
+        };
+        if let Err(e) = plan.resolve(resolution) {
+            self.mode = AppMode::Normal;
+            self.set_error(format!("Couldn't paste: {:#}", e));
+            return;
+        }
 
-            _ => {
-                self.error_message = Some(format!("Unknown command: {}", parts[0]));
-                self.mode = AppMode::Normal;
+        if plan.current_conflict().is_some() {
+            self.pending_paste = Some(plan);
+        } else {
+            self.mode = AppMode::Normal;
+            self.start_paste(plan);
+        }
+    }
+
+    pub fn cancel_paste(&mut self) {
+        self.pending_paste = None;
+        self.mode = AppMode::Normal;
+        self.set_status("Paste cancelled".to_string());
+    }
+
+    fn start_paste(&mut self, plan: PastePlan) {
+        let operation = plan.operation.clone();
+        let from_clipboard = plan.from_clipboard;
+        match plan.into_command() {
+            Some(command) => self.spawn_job(command),
+            None => {
+                self.set_status("Nothing to paste".to_string());
+                return;
             }
         }
+
+        // clear clipboard after pasting a cut:
+        if from_clipboard && operation == ClipboardOperation::Cut {
+            self.clipboard.items.clear();
+            self.clipboard.operation = ClipboardOperation::None;
+        }
+    }
+
+    /// Apply progress from background jobs, and report (and record for undo) any that have finished
+    pub fn process_job_events(&mut self) {
+        let finished = self.jobs.poll();
+        let any_finished = !finished.is_empty();
+        for (mut command, kind, result) in finished {
+            // report whatever was done, even if the job failed or was cancelled part way:
+            let finished = match kind {
+                JobKind::Undo => command.finish_undo(self),
+                JobKind::Execute | JobKind::Redo => command.finish(self),
+            };
+            if let Err(e) = finished {
+                self.set_error(format!("{} failed: {}", kind.describe(command.as_ref()), e));
+            }
+            let succeeded = result.is_ok();
+            if let Err(e) = result {
+                self.set_error(format!("{}: {}", kind.describe(command.as_ref()), e));
+            }
+            match kind {
+                JobKind::Execute if command.can_undo() => self.history.record(command),
+                JobKind::Redo if command.can_undo() => self.history.push_undo(command),
+                JobKind::Undo if succeeded => self.history.push_redo(command),
+                // an undo cancelled part way can carry on from where it stopped:
+                JobKind::Undo if command.can_undo() => self.history.push_undo(command),
+                _ => {}
+            }
+        }
+        // search results may have been deleted or moved:
+        if any_finished {
+            self.prune_search_results();
+            // jobs often change the other pane's directory too:
+            self.columns.invalidate(&self.other_pane.path);
+        }
+
+        // nothing left to manage:
+        if self.mode == AppMode::Jobs && self.jobs.is_empty() {
+            self.mode = AppMode::Normal;
+        }
+    }
+
+    /// Refresh the list or the preview when something else changes the directories they show
+    pub fn process_watch_events(&mut self) {
+        let mut dirs = vec![self.current_path.as_path()];
+        if self.layout == ViewLayout::Columns
+            && let Some(parent) = self.current_path.parent()
+        {
+            dirs.push(parent);
+        }
+        if self.layout == ViewLayout::Dual {
+            dirs.push(&self.other_pane.path);
+        } else if self.show_preview
+            && let Some(item) = self.file_list.selected()
+            && item.is_dir
+        {
+            dirs.push(&item.path);
+        }
+        // directories expanded in the tree view are part of the list:
+        if self.tree.enabled {
+            dirs.extend(
+                self.file_list
+                    .items
+                    .iter()
+                    .filter(|item| item.name != ".." && self.tree.is_expanded(&item.path))
+                    .map(|item| item.path.as_path()),
+            );
+        }
+        self.watcher.watch(&dirs);
+
+        for dir in self.watcher.poll() {
+            let listed = dir == self.current_path
+                || (self.tree.enabled
+                    && dir.starts_with(&self.current_path)
+                    && self.tree.is_expanded(&dir));
+            if !listed {
+                self.previewer.invalidate();
+                self.columns.invalidate(&dir);
+            } else if self.current_path.is_dir() {
+                if let Err(e) = self.reload_file_list() {
+                    self.set_error(format!("Failed to refresh: {}", e));
+                }
+            } else if let Some(parent) = self.current_path.ancestors().find(|dir| dir.is_dir()) {
+                // the directory itself was deleted or moved away:
+                let gone = self.current_path.display().to_string();
+                if let Err(e) = self.change_dir(parent.to_path_buf()) {
+                    self.set_error(format!("Failed to refresh: {}", e));
+                } else {
+                    self.set_status(format!("'{}' no longer exists", gone));
+                }
+            }
+        }
+    }
+
+    /// Switch between the split, columns and dual-pane layouts
+    pub fn set_layout(&mut self, layout: ViewLayout) {
+        self.layout = layout;
+        self.set_status(format!("Layout: {}", layout.label()));
+    }
+
+    /// Make sure the parent and selected directories are listed for the columns layout (or
+    /// the other pane for the dual-pane layout), with their cursors in the right place
+    pub fn update_columns(&mut self) {
+        if self.layout == ViewLayout::Dual {
+            let other = &self.other_pane;
+            self.columns.load(
+                &other.path,
+                other.cursor.as_deref(),
+                &other.sort,
+                &self.listing,
+            );
+            return;
+        }
+        if self.layout != ViewLayout::Columns {
+            return;
+        }
+        if let Some(parent) = self.current_path.parent() {
+            self.columns
+                .load(parent, Some(&self.current_path), &self.sort, &self.listing);
+        }
+        if self.show_preview
+            && let Some(item) = self.file_list.selected()
+            && item.is_dir
+            && item.name != ".."
+        {
+            // where the cursor will be on entering it:
+            let cursor = self
+                .navigation
+                .cursor(&item.path)
+                .map(|cursor| &cursor.item);
+            self.columns.load(
+                &item.path,
+                cursor.map(PathBuf::as_path),
+                &self.sort,
+                &self.listing,
+            );
+        }
+    }
+
+    /// Ask for a preview of the item under the cursor, and pick up any that have finished loading
+    pub fn update_preview(&mut self) {
+        if !self.show_preview {
+            return;
+        }
+        let selected = self.file_list.selected().map(|item| item.path.clone());
+        self.previewer.request(selected.as_deref());
+        self.previewer.poll();
+    }
+
+    /// Undo the last command. File operations are undone as a job, and only become redoable
+    /// once it finishes
+    pub fn undo(&mut self) -> Result<()> {
+        match self.history.pop_undo() {
+            Some(HistoryEntry::Immediate(mut command)) => {
+                // keep a command that failed to undo, so it can be tried again:
+                if let Err(e) = command.undo(self) {
+                    self.history.push_undo(command);
+                    return Err(e);
+                }
+                self.history.push_redo(command);
+            }
+            Some(HistoryEntry::Background(command)) => self.start_job(command, JobKind::Undo),
+            None => self.set_status("Nothing to undo".to_string()),
+        }
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> Result<()> {
+        match self.history.pop_redo() {
+            Some(HistoryEntry::Immediate(mut command)) => {
+                if let Err(e) = command.redo(self) {
+                    self.history.push_redo(command);
+                    return Err(e);
+                }
+                self.history.push_undo(command);
+            }
+            Some(HistoryEntry::Background(command)) => self.start_job(command, JobKind::Redo),
+            None => self.set_status("Nothing to redo".to_string()),
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Where the file list's items were drawn, to find the item under a mouse click
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListArea {
+    pub rows: Rect,    // inside the borders
+    pub offset: usize, // position of the first item shown, once scrolled
+}
+
+impl ListArea {
+    /// Position (among the visible items) of the item drawn at `column`, `row`
+    pub fn position_at(&self, column: u16, row: u16) -> Option<usize> {
+        let rows = self.rows;
+        let inside = (rows.x..rows.x + rows.width).contains(&column)
+            && (rows.y..rows.y + rows.height).contains(&row);
+        inside.then(|| self.offset + (row - rows.y) as usize)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AppMode {
     Normal,      // default mode
     MultiSelect, // when selecting multiple files
@@ -298,17 +1267,34 @@ pub enum AppMode {
     Command, // When user entering a command
     Help,    // When app is showing help modal
     Clipboard, // When app is showing the contents of the clipboard
+    Trash,   // When app is showing the trash browser
+    Jobs,    // When the background jobs panel is focused
+    Conflict, // When asking how to resolve a paste conflict
+    JumpList, // When app is showing recently visited directories
+    Bookmarks, // When app is showing the bookmarks modal
+    Jumper,  // When picking a frequently visited directory to jump to
+    Find,    // When showing the results of a `:find` search
+}
+
+/// What the next key press means after `m` or `'`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarkPrompt {
+    Set,  // bookmark the current directory under that key
+    Jump, // go to the directory bookmarked under that key
 }
 
 // File items:
 #[derive(Debug, Clone)]
 pub struct FileItem {
     pub name: String,
-    pub path: PathBuf,     // path to this item
-    pub is_dir: bool,      // whether or not is a directory
-    pub size: Option<u64>, // size in bytes
+    pub path: PathBuf, // path to this item
+    pub is_dir: bool,  // whether or not is a directory
+    pub kind: FileKind,
+    pub size: Option<u64>,                       // size in bytes
     pub modified: Option<std::time::SystemTime>, // last modified date
-                           // pub permissions: Option<String>,  // much later feature so removed for now
+    pub depth: usize, // how far below the current directory, in the tree view
+    pub parent: Option<usize>, // index of the directory it's listed under, in the tree view
+                      // pub permissions: Option<String>,  // much later feature so removed for now
 }
 
 impl FileItem {
@@ -320,6 +1306,16 @@ impl FileItem {
         }
     }
 
+    /// The entries of a directory, folders first and then in `order`
+    pub fn read_dir(path: &Path, order: &SortOrder) -> Result<Vec<Self>> {
+        let mut entries = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| FileItem::from_dir_entry(entry).ok())
+            .collect::<Vec<_>>();
+        order.sort(&mut entries);
+        Ok(entries)
+    }
+
     pub fn from_dir_entry(entry: DirEntry) -> Result<Self> {
         let metadata = entry.metadata()?;
         let path = entry.path();
@@ -328,8 +1324,9 @@ impl FileItem {
             .into_string()
             .unwrap_or_else(|_| "Invalid filename".to_string());
         Ok(Self {
-            name: name,
-            path: path,
+            name,
+            kind: FileKind::from_metadata(&metadata, &path),
+            path,
             is_dir: metadata.is_dir(),
             size: if metadata.is_file() {
                 Some(metadata.len())
@@ -337,16 +1334,61 @@ impl FileItem {
                 None
             },
             modified: metadata.modified().ok(),
+            depth: 0,
+            parent: None,
         })
     }
 }
 
+/// What sort of filesystem entry an item is (used to colour it)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FileKind {
+    Directory,
+    #[default]
+    File,
+    Executable,
+    Symlink,
+    BrokenSymlink, // symlink whose target doesn't exist
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+impl FileKind {
+    /// Classify an entry from its (not followed) metadata
+    pub fn from_metadata(metadata: &Metadata, path: &Path) -> Self {
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            FileKind::Directory
+        } else if file_type.is_symlink() {
+            if path.exists() {
+                FileKind::Symlink
+            } else {
+                FileKind::BrokenSymlink
+            }
+        } else if file_type.is_fifo() {
+            FileKind::Fifo
+        } else if file_type.is_socket() {
+            FileKind::Socket
+        } else if file_type.is_block_device() {
+            FileKind::BlockDevice
+        } else if file_type.is_char_device() {
+            FileKind::CharDevice
+        } else if metadata.permissions().mode() & 0o111 != 0 {
+            FileKind::Executable
+        } else {
+            FileKind::File
+        }
+    }
+}
+
 // Stateful List:
 #[derive(Debug, Clone)]
 pub struct StatefulList<T> {
     pub state: ListState,
     pub items: Vec<T>,
-    pub filtered_items: Vec<usize>, // indices of selected items
+    pub filtered_items: Vec<usize>, // indices of visible items (after filtering)
 }
 
 impl<T> StatefulList<T> {
@@ -362,16 +1404,26 @@ impl<T> StatefulList<T> {
         let selected = (0..items.len()).collect();
         Self {
             state: ListState::default(),
-            items: items,
+            items,
             filtered_items: selected,
         }
     }
 
-    pub fn filtered_items(&self) -> Vec<&T> {
-        self.filtered_items
-            .iter()
-            .filter_map(|&i| self.items.get(i))
-            .collect()
+    /// Replace the visible items, keeping the cursor on a visible item
+    pub fn set_filtered_items(&mut self, filtered_items: Vec<usize>) {
+        self.filtered_items = filtered_items;
+        if let Some(selected) = self.state.selected()
+            && !self.filtered_items.contains(&selected)
+        {
+            self.state.select(self.filtered_items.first().copied());
+        }
+    }
+
+    /// Position of the selected item within the visible (filtered) items
+    pub fn selected_position(&self) -> Option<usize> {
+        self.state
+            .selected()
+            .and_then(|i| self.filtered_items.iter().position(|&x| x == i))
     }
 
     pub fn selected(&self) -> Option<&T> {
@@ -429,6 +1481,18 @@ pub enum InputContext {
     Command,
 }
 
+impl InputContext {
+    /// Which history lines entered here are kept in (new files and directories share one)
+    pub fn history_kind(&self) -> &'static str {
+        match self {
+            InputContext::Rename => "rename",
+            InputContext::CreateFile | InputContext::CreateDir => "create",
+            InputContext::Filter => "filter",
+            InputContext::Command => "command",
+        }
+    }
+}
+
 // Clipboard:
 #[derive(Debug, Clone, PartialEq)]
 pub enum ClipboardOperation {
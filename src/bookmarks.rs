@@ -0,0 +1,69 @@
+use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::utils::{data_home, load_toml, save_toml};
+
+/// Named directories, saved to `$XDG_DATA_HOME/clexp/bookmarks.toml` as `name = "/path"`
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    file: Option<PathBuf>, // `None` when there's no data directory to save to
+    entries: BTreeMap<String, PathBuf>,
+}
+
+impl Bookmarks {
+    pub fn load() -> Result<Self> {
+        let mut bookmarks = Self {
+            file: data_home().map(|dir| dir.join("clexp").join("bookmarks.toml")),
+            entries: BTreeMap::new(),
+        };
+        bookmarks.reload()?;
+        Ok(bookmarks)
+    }
+
+    /// Read the saved bookmarks again, picking up changes made by other instances
+    fn reload(&mut self) -> Result<()> {
+        if let Some(entries) = load_toml(self.file.as_deref())? {
+            self.entries = entries;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        save_toml(self.file.as_deref(), &self.entries)
+    }
+
+    /// Add (or move) the bookmark `name`, and save
+    pub fn add(&mut self, name: &str, dir: PathBuf) -> Result<()> {
+        self.reload()?;
+        self.entries.insert(name.to_string(), dir);
+        self.save()
+    }
+
+    /// Remove the bookmark `name` and save, returning where it pointed
+    pub fn remove(&mut self, name: &str) -> Result<Option<PathBuf>> {
+        self.reload()?;
+        let removed = self.entries.remove(name);
+        if removed.is_some() {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Path> {
+        self.entries.get(name).map(PathBuf::as_path)
+    }
+
+    /// All bookmarks, sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.entries
+            .iter()
+            .map(|(name, dir)| (name.as_str(), dir.as_path()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    app::{FileItem, StatefulList},
+    listing::ListingFilter,
+    sort::SortOrder,
+};
+
+/// How many directory listings are kept around for the side columns
+const MAX_CACHED_COLUMNS: usize = 32;
+
+/// How the main area is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ViewLayout {
+    #[default]
+    Split, // file list with the preview beside it
+    Columns, // parent directory, file list, then the selected directory or preview (like ranger)
+    Dual,    // two file lists side by side, copying and moving between them (like mc)
+}
+
+impl ViewLayout {
+    pub fn next(self) -> Self {
+        match self {
+            ViewLayout::Split => ViewLayout::Columns,
+            ViewLayout::Columns => ViewLayout::Dual,
+            ViewLayout::Dual => ViewLayout::Split,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "split" => Some(ViewLayout::Split),
+            "columns" | "miller" => Some(ViewLayout::Columns),
+            "dual" | "commander" => Some(ViewLayout::Dual),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ViewLayout::Split => "split",
+            ViewLayout::Columns => "columns",
+            ViewLayout::Dual => "dual",
+        }
+    }
+}
+
+/// A directory shown beside the file list
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub list: StatefulList<FileItem>,
+    pub ignored: HashSet<PathBuf>, // entries to dim, as in the file list
+}
+
+/// Listings of the directories shown beside the file list (or in the other pane), kept so
+/// moving the cursor back and forth doesn't read them again
+#[derive(Debug, Default)]
+pub struct ColumnCache {
+    columns: HashMap<PathBuf, Column>,
+    built_with: Option<(SortOrder, ListingFilter)>, // how the cached listings were sorted and filtered
+}
+
+impl ColumnCache {
+    /// Read `dir` unless it's already cached, then put its cursor on `selected`
+    pub fn load(
+        &mut self,
+        dir: &Path,
+        selected: Option<&Path>,
+        sort: &SortOrder,
+        listing: &ListingFilter,
+    ) {
+        // listings sorted or filtered another way are no use:
+        if self.built_with != Some((*sort, *listing)) {
+            self.columns.clear();
+            self.built_with = Some((*sort, *listing));
+        }
+
+        if !self.columns.contains_key(dir) {
+            // unreadable directories are cached as empty, rather than tried again every frame:
+            let mut items = FileItem::read_dir(dir, sort).unwrap_or_default();
+            let ignored = listing.apply(dir, &mut items);
+            if self.columns.len() >= MAX_CACHED_COLUMNS {
+                self.columns.clear();
+            }
+            self.columns.insert(
+                dir.to_path_buf(),
+                Column {
+                    list: StatefulList::new_with_items(items),
+                    ignored,
+                },
+            );
+        }
+
+        if let Some(column) = self.columns.get_mut(dir) {
+            let index = selected.and_then(|selected| {
+                column
+                    .list
+                    .items
+                    .iter()
+                    .position(|item| item.path == selected)
+            });
+            column.list.state.select(index);
+        }
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<&Column> {
+        self.columns.get(dir)
+    }
+
+    /// Forget the listing of `dir`, so it's read again next time
+    pub fn invalidate(&mut self, dir: &Path) {
+        self.columns.remove(dir);
+    }
+}
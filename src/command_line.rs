@@ -0,0 +1,1050 @@
+use anyhow::Result;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    app::{App, AppMode, CANT_DELETE_CURRENT_DIR, ClipboardOperation},
+    columns::ViewLayout,
+    commands::{
+        ConflictPolicy, CopyOptions, CreateDirCommand, CreateFileCommand, DeleteCommand, PastePlan,
+        RenameCommand,
+    },
+    filter::FilterMode,
+    find::FindMode,
+    listing::{IgnoreMode, ListingFilter},
+    sort::{SortKey, SortOrder},
+    utils::{expand_tilde, normalize_path},
+};
+
+/// A command understood in command mode
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str, // the arguments, e.g. `<src>... <dst>`
+    pub description: &'static str,
+}
+
+pub static COMMANDS: [CommandSpec; 21] = [
+    CommandSpec {
+        name: "cd",
+        aliases: &[],
+        usage: "[dir]",
+        description: "Go to a directory (~ when empty, - goes back)",
+    },
+    CommandSpec {
+        name: "mkdir",
+        aliases: &[],
+        usage: "<dir>...",
+        description: "Create directories, and any missing parents",
+    },
+    CommandSpec {
+        name: "touch",
+        aliases: &[],
+        usage: "<file>...",
+        description: "Create empty files, or update their modified time",
+    },
+    CommandSpec {
+        name: "rename",
+        aliases: &[],
+        usage: "[path] <new name>",
+        description: "Rename the selected item (or path)",
+    },
+    CommandSpec {
+        name: "rm",
+        aliases: &[],
+        usage: "[-p] <path>...",
+        description: "Move to the trash (-p deletes permanently)",
+    },
+    CommandSpec {
+        name: "cp",
+        aliases: &[],
+        usage: "[-a] <src>... <dst>",
+        description: "Copy into a directory, or to a new name (dual-pane: <src> alone)",
+    },
+    CommandSpec {
+        name: "mv",
+        aliases: &[],
+        usage: "<src>... <dst>",
+        description: "Move into a directory, or to a new name (dual-pane: <src> alone)",
+    },
+    CommandSpec {
+        name: "sort",
+        aliases: &[],
+        usage: "[key] [asc|desc] [dirsfirst|nodirsfirst]",
+        description: "Sort the file list (alone: next key)",
+    },
+    CommandSpec {
+        name: "filter",
+        aliases: &["s"],
+        usage: "[-s|-g|-f] [pattern]",
+        description: "Filter the view by substring/glob/fuzzy (empty clears)",
+    },
+    CommandSpec {
+        name: "find",
+        aliases: &[],
+        usage: "[-g|-r|-f] <pattern>",
+        description: "Search below this dir by glob/regex/fuzzy (alone: last results)",
+    },
+    CommandSpec {
+        name: "set",
+        aliases: &[],
+        usage: "[option[=value]]...",
+        description: "Change options (alone: show them all)",
+    },
+    CommandSpec {
+        name: "paste",
+        aliases: &[],
+        usage: "[-a]",
+        description: "Paste clipboard (-a keeps ownership and xattrs)",
+    },
+    CommandSpec {
+        name: "conflict",
+        aliases: &[],
+        usage: "[policy]",
+        description: "Paste conflict default: ask/overwrite/skip/rename/merge",
+    },
+    CommandSpec {
+        name: "undo",
+        aliases: &["u"],
+        usage: "",
+        description: "Undo last operation",
+    },
+    CommandSpec {
+        name: "redo",
+        aliases: &[],
+        usage: "",
+        description: "Redo last undone operation",
+    },
+    CommandSpec {
+        name: "trash",
+        aliases: &[],
+        usage: "",
+        description: "Show trash",
+    },
+    CommandSpec {
+        name: "bookmark",
+        aliases: &["bm"],
+        usage: "add <name> [dir] | rm <name> | list",
+        description: "Manage bookmarks",
+    },
+    CommandSpec {
+        name: "tab",
+        aliases: &[],
+        usage: "new [dir] | close | next | prev | <number>",
+        description: "Open, close or switch tabs",
+    },
+    CommandSpec {
+        name: "z",
+        aliases: &[],
+        usage: "[keywords]",
+        description: "Jump to the best ranked visited dir (alone: picker)",
+    },
+    CommandSpec {
+        name: "help",
+        aliases: &["h"],
+        usage: "",
+        description: "Show this help",
+    },
+    CommandSpec {
+        name: "quit",
+        aliases: &["q", "exit"],
+        usage: "",
+        description: "Quit",
+    },
+];
+
+/// An option changed with `:set`
+#[derive(Debug)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub values: &'static [&'static str], // `BOOLEAN` for on/off options
+    pub description: &'static str,
+}
+
+const BOOLEAN: &[&str] = &["true", "false"];
+const SORT_KEYS: &[&str] = &["name", "natural", "size", "mtime", "extension", "type"];
+
+pub static OPTIONS: [OptionSpec; 10] = [
+    OptionSpec {
+        name: "conflict",
+        values: &["ask", "overwrite", "skip", "rename", "merge"],
+        description: "What to do when a paste collides with an existing item",
+    },
+    OptionSpec {
+        name: "dirsfirst",
+        values: BOOLEAN,
+        description: "List directories before files",
+    },
+    OptionSpec {
+        name: "filtermode",
+        values: &["substring", "glob", "fuzzy"],
+        description: "How the filter matches names",
+    },
+    OptionSpec {
+        name: "hidden",
+        values: BOOLEAN,
+        description: "List dotfiles",
+    },
+    OptionSpec {
+        name: "ignore",
+        values: &["off", "dim", "hide"],
+        description: "What happens to entries matched by .gitignore/.ignore",
+    },
+    OptionSpec {
+        name: "layout",
+        values: &["split", "columns", "dual"],
+        description: "List and preview, parent/list/preview columns, or two panes",
+    },
+    OptionSpec {
+        name: "preview",
+        values: BOOLEAN,
+        description: "Show the preview pane",
+    },
+    OptionSpec {
+        name: "reverse",
+        values: BOOLEAN,
+        description: "Sort in descending order",
+    },
+    OptionSpec {
+        name: "sort",
+        values: SORT_KEYS,
+        description: "What the file list is sorted by",
+    },
+    OptionSpec {
+        name: "tree",
+        values: BOOLEAN,
+        description: "Show the file list as a tree, with expandable directories",
+    },
+];
+
+impl OptionSpec {
+    fn is_boolean(&self) -> bool {
+        self.values == BOOLEAN
+    }
+}
+
+/// Tab completion being cycled through on the command line
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub candidates: Vec<String>,
+    pub selected: Option<usize>, // the candidate currently in the input, once Tab is pressed again
+    start: usize,                // where the completed word starts in the input
+    end: usize,                  // and where it ends
+}
+
+/// A word of a command line, and where it starts
+#[derive(Debug)]
+struct Word {
+    start: usize,
+    text: String, // with quotes and escapes removed
+}
+
+pub fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    let name = name.to_lowercase();
+    COMMANDS
+        .iter()
+        .find(|spec| spec.name == name || spec.aliases.contains(&name.as_str()))
+}
+
+fn find_option(name: &str) -> Result<&'static OptionSpec, String> {
+    OPTIONS
+        .iter()
+        .find(|option| option.name == name)
+        .ok_or_else(|| format!("Unknown option: {}", name))
+}
+
+/// Commands for the suggestions popup: those starting with the first word while it's being
+/// typed, then just the one being used (as a reminder of its arguments)
+pub fn suggestions(line: &str) -> Vec<&'static CommandSpec> {
+    let (words, in_word) = lex(line);
+    match words.as_slice() {
+        [] => COMMANDS.iter().collect(),
+        [word] if in_word => {
+            let prefix = word.text.to_lowercase();
+            COMMANDS
+                .iter()
+                .filter(|spec| {
+                    spec.name.starts_with(&prefix)
+                        || spec.aliases.iter().any(|alias| alias.starts_with(&prefix))
+                })
+                .collect()
+        }
+        [command, ..] => find_command(&command.text).into_iter().collect(),
+    }
+}
+
+/// Split a command line into words like a shell: quotes group words and `\` escapes the next character
+pub fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let (words, _) = lex(line);
+    if open_quote(line).is_some() {
+        return Err("Unterminated quote".to_string());
+    }
+    Ok(words.into_iter().map(|word| word.text).collect())
+}
+
+/// The words of a line, and whether it ends part way through one
+fn lex(line: &str) -> (Vec<Word>, bool) {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    let mut quote = None;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if quote.is_none() && c.is_whitespace() {
+            words.extend(current.take());
+            continue;
+        }
+        let word = current.get_or_insert_with(|| Word {
+            start: i,
+            text: String::new(),
+        });
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (Some('\''), c) => word.text.push(c),
+            (_, '\\') => word.text.extend(chars.next().map(|(_, next)| next)),
+            (_, c) => word.text.push(c),
+        }
+    }
+    let in_word = current.is_some();
+    words.extend(current);
+    (words, in_word)
+}
+
+/// The quote left open at the end of `line`, if any
+fn open_quote(line: &str) -> Option<char> {
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    quote
+}
+
+/// Escape a word so it reads back as itself
+fn quote_arg(word: &str) -> String {
+    let mut quoted = String::with_capacity(word.len());
+    for c in word.chars() {
+        if c.is_whitespace() || matches!(c, '\'' | '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let Some((first, rest)) = candidates.split_first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for candidate in rest {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+/// The command closest to a mistyped name, for "did you mean" hints
+fn closest_command(name: &str) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .flat_map(|spec| std::iter::once(&spec.name).chain(spec.aliases))
+        .filter(|candidate| candidate.len() > 1)
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|&(distance, _)| distance <= 2)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl App {
+    /// Run a line typed in command mode. Mistakes in the line itself go in `command_error`, so
+    /// the command line can stay open to fix them, while failures are reported as usual
+    pub fn execute_command(&mut self, line: &str) -> Result<()> {
+        self.mode = AppMode::Normal;
+        self.command_error = None;
+
+        let words = match split_args(line) {
+            Ok(words) => words,
+            Err(e) => return self.reject_command(e),
+        };
+        let Some((name, args)) = words.split_first() else {
+            return Ok(());
+        };
+        let Some(spec) = find_command(name) else {
+            return self.reject_command(match closest_command(name) {
+                Some(closest) => format!("Unknown command: {} (did you mean '{}'?)", name, closest),
+                None => format!("Unknown command: {}", name),
+            });
+        };
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+        match spec.name {
+            "quit" => {
+                self.request_quit();
+            }
+
+            "help" => {
+                self.mode = AppMode::Help;
+            }
+
+            "cd" => {
+                let dir = match args.as_slice() {
+                    [] => expand_tilde("~"),
+                    ["-"] => return self.go_back(),
+                    [dir] => self.resolve_path(dir),
+                    _ => return self.reject_usage(spec),
+                };
+                if !dir.is_dir() {
+                    return self.reject_command(format!("Not a directory: {}", dir.display()));
+                }
+                self.navigate_to(dir)?;
+            }
+
+            "mkdir" => {
+                if args.is_empty() {
+                    return self.reject_usage(spec);
+                }
+                let dirs = args
+                    .iter()
+                    .map(|dir| self.resolve_path(dir))
+                    .collect::<Vec<_>>();
+                if let Some(existing) = dirs.iter().find(|dir| dir.exists()) {
+                    return self.reject_command(format!("Already exists: {}", existing.display()));
+                }
+                for dir in dirs {
+                    if let Err(e) = self.run_command(Box::new(CreateDirCommand::new(dir))) {
+                        self.set_error(format!("Directory creation failed: {}", e));
+                        break;
+                    }
+                }
+            }
+
+            "touch" => {
+                if args.is_empty() {
+                    return self.reject_usage(spec);
+                }
+                let files = args
+                    .iter()
+                    .map(|file| self.resolve_path(file))
+                    .collect::<Vec<_>>();
+                for file in files {
+                    let result = if file.exists() {
+                        File::open(&file)
+                            .and_then(|handle| handle.set_modified(SystemTime::now()))
+                            .map_err(anyhow::Error::from)
+                    } else {
+                        self.run_command(Box::new(CreateFileCommand::new(file.clone())))
+                    };
+                    if let Err(e) = result {
+                        self.set_error(format!("Touching '{}' failed: {}", file.display(), e));
+                        break;
+                    }
+                }
+                self.refresh_file_list()?;
+            }
+
+            "rename" => {
+                let (source, new_name) = match args.as_slice() {
+                    [new_name] => match self.file_list.selected() {
+                        Some(item) if item.name != ".." => (item.path.clone(), *new_name),
+                        _ => return self.reject_command("Nothing selected to rename"),
+                    },
+                    [path, new_name] => (self.resolve_path(path), *new_name),
+                    _ => return self.reject_usage(spec),
+                };
+                if new_name.contains('/') {
+                    return self.reject_command("New names can't contain '/' (use mv to move)");
+                }
+                if !source.exists() && !source.is_symlink() {
+                    return self.reject_command(format!("No such item: {}", source.display()));
+                }
+                let renamed = source.with_file_name(new_name);
+                if renamed.exists() {
+                    return self.reject_command(format!("Already exists: {}", new_name));
+                }
+                let command = RenameCommand::new(source, new_name.to_string());
+                match self.run_command(Box::new(command)) {
+                    Ok(()) => {
+                        self.select_path(&renamed);
+                    }
+                    Err(e) => self.set_error(format!("Rename failed: {}", e)),
+                }
+            }
+
+            "rm" => {
+                let (permanent, paths) = match args.split_first() {
+                    Some((&"-p" | &"--permanent", paths)) => (true, paths),
+                    _ => (false, args.as_slice()),
+                };
+                if paths.is_empty() {
+                    return self.reject_usage(spec);
+                }
+                let targets = paths
+                    .iter()
+                    .map(|path| self.resolve_path(path))
+                    .collect::<Vec<_>>();
+                if let Some(missing) = targets
+                    .iter()
+                    .find(|target| !target.exists() && !target.is_symlink())
+                {
+                    return self.reject_command(format!("No such item: {}", missing.display()));
+                }
+                if self.deletes_current_dir(&targets) {
+                    return self.reject_command(CANT_DELETE_CURRENT_DIR);
+                }
+
+                let what = match targets.as_slice() {
+                    [target] => format!("'{}'", target.display()),
+                    _ => format!("{} items", targets.len()),
+                };
+                self.mode = AppMode::Confirm;
+                if permanent {
+                    self.set_status(format!(
+                        "Permanently delete {}? This cannot be undone (y/n)",
+                        what
+                    ));
+                    self.active_command = Some(Box::new(DeleteCommand::new_permanent(targets)));
+                } else {
+                    self.set_status(format!("Delete {}? (y/n)", what));
+                    self.active_command = Some(Box::new(DeleteCommand::new(targets)));
+                }
+            }
+
+            "cp" | "mv" => {
+                let (operation, options, paths) = match (spec.name, args.split_first()) {
+                    ("cp", Some((&"-a" | &"--archive", paths))) => {
+                        (ClipboardOperation::Copy, CopyOptions::archive(), paths)
+                    }
+                    ("cp", _) => (
+                        ClipboardOperation::Copy,
+                        CopyOptions::default(),
+                        args.as_slice(),
+                    ),
+                    _ => (
+                        ClipboardOperation::Cut,
+                        CopyOptions::default(),
+                        args.as_slice(),
+                    ),
+                };
+                // with two panes, a lone source goes into the other one:
+                if let [source] = paths
+                    && self.layout == ViewLayout::Dual
+                {
+                    let source = self.resolve_path(source);
+                    if !source.exists() && !source.is_symlink() {
+                        return self.reject_command(format!("No such item: {}", source.display()));
+                    }
+                    self.transfer_to_other_pane(operation, vec![source], options);
+                    return Ok(());
+                }
+                let [sources @ .., target] = paths else {
+                    return self.reject_usage(spec);
+                };
+                if sources.is_empty() {
+                    return self.reject_usage(spec);
+                }
+                let sources = sources
+                    .iter()
+                    .map(|source| self.resolve_path(source))
+                    .collect::<Vec<_>>();
+                let destination = self.resolve_path(target);
+                if let Some(missing) = sources
+                    .iter()
+                    .find(|source| !source.exists() && !source.is_symlink())
+                {
+                    return self.reject_command(format!("No such item: {}", missing.display()));
+                }
+                if let Some(source) = sources
+                    .iter()
+                    .find(|source| source.is_dir() && destination.starts_with(source))
+                {
+                    return self
+                        .reject_command(format!("Can't put '{}' inside itself", source.display()));
+                }
+
+                // into an existing directory, otherwise to exactly the path given:
+                let plan = if destination.is_dir() {
+                    match PastePlan::new(operation, sources, destination, options) {
+                        Ok(plan) => plan,
+                        Err(e) => return self.reject_command(format!("{:#}", e)),
+                    }
+                } else if target.ends_with('/') || sources.len() > 1 {
+                    return self
+                        .reject_command(format!("Not a directory: {}", destination.display()));
+                } else if !destination.parent().is_some_and(Path::is_dir) {
+                    return self.reject_command(format!(
+                        "No such directory: {}",
+                        destination.parent().unwrap_or(&destination).display()
+                    ));
+                } else {
+                    let source = sources.into_iter().next().unwrap_or_default();
+                    PastePlan::to_path(operation, source, destination, options)
+                };
+                self.transfer(plan);
+            }
+
+            "sort" => {
+                let mut order = self.sort;
+                if args.is_empty() {
+                    order.key = order.key.next();
+                }
+                for arg in &args {
+                    match *arg {
+                        "asc" => order.reverse = false,
+                        "desc" => order.reverse = true,
+                        "dirsfirst" => order.dirs_first = true,
+                        "nodirsfirst" => order.dirs_first = false,
+                        key => match SortKey::parse(key) {
+                            Some(key) => order.key = key,
+                            None => {
+                                return self.reject_command(format!(
+                                    "Unknown sort: {} ({}, asc, desc, dirsfirst, nodirsfirst)",
+                                    key,
+                                    SORT_KEYS.join(", ")
+                                ));
+                            }
+                        },
+                    }
+                }
+                self.set_sort(order);
+            }
+
+            "filter" => {
+                let (mode, pattern) = match args.split_first() {
+                    Some((&"-s", pattern)) => (Some(FilterMode::Substring), pattern),
+                    Some((&"-g", pattern)) => (Some(FilterMode::Glob), pattern),
+                    Some((&"-f", pattern)) => (Some(FilterMode::Fuzzy), pattern),
+                    _ => (None, args.as_slice()),
+                };
+                if let Some(mode) = mode {
+                    self.filter.mode = mode;
+                }
+                self.set_filter(pattern.join(" "));
+            }
+
+            "find" => {
+                if args.is_empty() {
+                    self.open_search_results();
+                    return Ok(());
+                }
+                let (mode, pattern) = match args.split_first() {
+                    Some((&"-g", pattern)) => (Some(FindMode::Glob), pattern),
+                    Some((&"-r", pattern)) => (Some(FindMode::Regex), pattern),
+                    Some((&"-f", pattern)) => (Some(FindMode::Fuzzy), pattern),
+                    _ => (None, args.as_slice()),
+                };
+                if pattern.is_empty() {
+                    return self.reject_usage(spec);
+                }
+                let pattern = pattern.join(" ");
+                let mode = mode.unwrap_or_else(|| FindMode::guess(&pattern));
+                if let Err(e) = self.start_search(mode, pattern) {
+                    return self.reject_command(e);
+                }
+            }
+
+            "set" => {
+                if args.is_empty() {
+                    let values = OPTIONS
+                        .iter()
+                        .map(|option| format!("{}={}", option.name, self.option_value(option)))
+                        .collect::<Vec<_>>();
+                    self.set_status(values.join("  "));
+                }
+                for arg in args {
+                    if let Err(e) = self.set_option(arg) {
+                        return self.reject_command(e);
+                    }
+                }
+            }
+
+            "undo" => {
+                if let Err(e) = self.undo() {
+                    self.set_error(format!("Undo failed: {}", e));
+                }
+            }
+
+            "redo" => {
+                if let Err(e) = self.redo() {
+                    self.set_error(format!("Redo failed: {}", e));
+                }
+            }
+
+            "trash" => {
+                self.open_trash()?;
+            }
+
+            "paste" => match args.as_slice() {
+                [] => self.paste(CopyOptions::default()),
+                ["-a" | "--archive"] => self.paste(CopyOptions::archive()),
+                [flag, ..] => {
+                    return self.reject_command(format!("Unknown paste option: {}", flag));
+                }
+            },
+
+            "z" => {
+                if args.is_empty() {
+                    self.open_jumper();
+                } else {
+                    self.jump_to_frecent(&args)?;
+                }
+            }
+
+            "bookmark" => match args.as_slice() {
+                ["add", name] | ["add", name, _] => {
+                    let dir = match args.get(2) {
+                        Some(dir) => self.resolve_path(dir),
+                        None => self.current_path.clone(),
+                    };
+                    if !dir.is_dir() {
+                        return self.reject_command(format!("Not a directory: {}", dir.display()));
+                    }
+                    self.add_bookmark(name, dir);
+                }
+                ["rm", name] => self.remove_bookmark(name),
+                [] | ["list"] => self.open_bookmarks(),
+                _ => return self.reject_usage(spec),
+            },
+
+            "tab" => match args.as_slice() {
+                ["new"] => self.new_tab(self.current_path.clone()),
+                ["new", dir] => {
+                    let dir = self.resolve_path(dir);
+                    if !dir.is_dir() {
+                        return self.reject_command(format!("Not a directory: {}", dir.display()));
+                    }
+                    self.new_tab(dir);
+                }
+                ["close"] => self.close_tab(),
+                ["next"] => self.next_tab(),
+                ["prev"] => self.prev_tab(),
+                [number] => match number.parse::<usize>() {
+                    Ok(number) if (1..=self.tabs.len()).contains(&number) => {
+                        self.switch_tab(number - 1)
+                    }
+                    _ => {
+                        return self.reject_command(format!(
+                            "No tab {} (there are {})",
+                            number,
+                            self.tabs.len()
+                        ));
+                    }
+                },
+                _ => return self.reject_usage(spec),
+            },
+
+            "conflict" => match args.as_slice() {
+                [] => self.set_status(format!("Paste conflicts: {}", self.conflict_policy.label())),
+                [policy] => match ConflictPolicy::parse(policy) {
+                    Some(policy) => {
+                        self.conflict_policy = policy;
+                        self.set_status(format!("Paste conflicts: {}", policy.label()));
+                    }
+                    None => {
+                        return self.reject_command(format!(
+                            "Unknown conflict policy: {} (ask, overwrite, skip, rename, merge)",
+                            policy
+                        ));
+                    }
+                },
+                _ => return self.reject_usage(spec),
+            },
+
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Leave the command line open, showing what was wrong with it
+    fn reject_command(&mut self, message: impl Into<String>) -> Result<()> {
+        self.command_error = Some(message.into());
+        Ok(())
+    }
+
+    fn reject_usage(&mut self, spec: &CommandSpec) -> Result<()> {
+        self.reject_command(format!("Usage: {} {}", spec.name, spec.usage))
+    }
+
+    /// A path typed on the command line, relative to the current directory
+    fn resolve_path(&self, path: &str) -> PathBuf {
+        normalize_path(&self.current_path.join(expand_tilde(path)))
+    }
+
+    fn option_value(&self, option: &OptionSpec) -> String {
+        match option.name {
+            "conflict" => self.conflict_policy.label().to_string(),
+            "dirsfirst" => self.sort.dirs_first.to_string(),
+            "filtermode" => self.filter.mode.label().to_string(),
+            "hidden" => self.listing.show_hidden.to_string(),
+            "ignore" => self.listing.ignore.label().to_string(),
+            "layout" => self.layout.label().to_string(),
+            "preview" => self.show_preview.to_string(),
+            "reverse" => self.sort.reverse.to_string(),
+            "sort" => self.sort.key.label().to_string(),
+            "tree" => self.tree.enabled.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Apply one `:set` argument: `name=value`, `name?` to show it, or for on/off options
+    /// vim-style `name`, `noname` and `name!` to toggle
+    fn set_option(&mut self, arg: &str) -> Result<(), String> {
+        if let Some(name) = arg.strip_suffix('?') {
+            let option = find_option(name)?;
+            self.set_status(format!("{}={}", option.name, self.option_value(option)));
+            return Ok(());
+        }
+
+        let (option, value) = match arg.split_once('=') {
+            Some((name, value)) => (find_option(name)?, value.to_lowercase()),
+            None => match (arg.strip_suffix('!'), arg.strip_prefix("no")) {
+                (Some(name), _) => {
+                    let option = find_option(name)?;
+                    let toggled = self.option_value(option) != "true";
+                    (option, toggled.to_string())
+                }
+                (None, Some(name)) if find_option(arg).is_err() => {
+                    (find_option(name)?, "false".to_string())
+                }
+                _ => {
+                    let option = find_option(arg)?;
+                    if !option.is_boolean() {
+                        self.set_status(format!("{}={}", option.name, self.option_value(option)));
+                        return Ok(());
+                    }
+                    (option, "true".to_string())
+                }
+            },
+        };
+        if !option.values.contains(&value.as_str()) {
+            return Err(format!(
+                "Invalid value for {}: {} ({})",
+                option.name,
+                value,
+                option.values.join(", ")
+            ));
+        }
+
+        match option.name {
+            "conflict" => {
+                self.conflict_policy = ConflictPolicy::parse(&value).unwrap_or_default();
+            }
+            "filtermode" => {
+                self.filter.mode = FilterMode::parse(&value).unwrap_or_default();
+                self.apply_filter();
+            }
+            "preview" => {
+                self.show_preview = value == "true";
+            }
+            "hidden" => self.set_listing(ListingFilter {
+                show_hidden: value == "true",
+                ..self.listing
+            }),
+            "ignore" => self.set_listing(ListingFilter {
+                ignore: IgnoreMode::parse(&value).unwrap_or_default(),
+                ..self.listing
+            }),
+            "layout" => self.set_layout(ViewLayout::parse(&value).unwrap_or_default()),
+            "dirsfirst" => self.set_sort(SortOrder {
+                dirs_first: value == "true",
+                ..self.sort
+            }),
+            "reverse" => self.set_sort(SortOrder {
+                reverse: value == "true",
+                ..self.sort
+            }),
+            "sort" => self.set_sort(SortOrder {
+                key: SortKey::parse(&value).unwrap_or_default(),
+                ..self.sort
+            }),
+            "tree" if (value == "true") != self.tree.enabled => self.toggle_tree(),
+            _ => {}
+        }
+        self.set_status(format!("{}={}", option.name, self.option_value(option)));
+        Ok(())
+    }
+
+    /// Complete the word before the cursor. When there are several candidates the popup lists
+    /// them, and pressing Tab again (Shift+Tab backwards) cycles through them
+    pub fn complete_command(&mut self, backwards: bool) {
+        if let Some(completion) = &mut self.completion
+            && completion.end == self.cursor_position
+        {
+            let count = completion.candidates.len();
+            let next = match (completion.selected, backwards) {
+                (None, false) => 0,
+                (None, true) => count - 1,
+                (Some(i), false) => (i + 1) % count,
+                (Some(i), true) => (i + count - 1) % count,
+            };
+            let replacement = quote_arg(&completion.candidates[next]);
+            self.input_buffer
+                .replace_range(completion.start..completion.end, &replacement);
+            completion.selected = Some(next);
+            completion.end = completion.start + replacement.len();
+            self.cursor_position = completion.end;
+            return;
+        }
+
+        let (words, in_word) = lex(&self.input_buffer[..self.cursor_position]);
+        let (index, start, word) = match (words.last(), in_word) {
+            (Some(word), true) => (words.len() - 1, word.start, word.text.as_str()),
+            _ => (words.len(), self.cursor_position, ""),
+        };
+        let mut candidates = if index == 0 {
+            COMMANDS
+                .iter()
+                .map(|spec| spec.name.to_string())
+                .filter(|name| name.starts_with(word))
+                .collect()
+        } else {
+            let args = words[1..index]
+                .iter()
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>();
+            self.argument_candidates(&words[0].text, &args, word)
+        };
+        candidates.sort();
+        candidates.dedup();
+
+        let replacement = match candidates.as_slice() {
+            [] => return,
+            // a finished word is followed by a space, but directories and `option=` can go on:
+            [only] if only.ends_with('/') || only.ends_with('=') => quote_arg(only),
+            [only] => format!("{} ", quote_arg(only)),
+            _ => quote_arg(&common_prefix(&candidates)),
+        };
+        self.input_buffer
+            .replace_range(start..self.cursor_position, &replacement);
+        self.cursor_position = start + replacement.len();
+        if candidates.len() > 1 {
+            self.completion = Some(Completion {
+                candidates,
+                selected: None,
+                start,
+                end: self.cursor_position,
+            });
+        }
+    }
+
+    /// What the next argument of `command` could be, given the ones before it
+    fn argument_candidates(&self, command: &str, args: &[&str], word: &str) -> Vec<String> {
+        let choices = |choices: &[&str]| {
+            choices
+                .iter()
+                .filter(|choice| choice.starts_with(word))
+                .map(|choice| choice.to_string())
+                .collect()
+        };
+        match (find_command(command).map(|spec| spec.name), args) {
+            (Some("cd"), []) => self.path_candidates(word, true),
+            (Some("mkdir" | "touch" | "rm" | "cp" | "mv"), _) => self.path_candidates(word, false),
+            (Some("rename"), []) => self.path_candidates(word, false),
+            (Some("sort"), _) => {
+                let mut words = SORT_KEYS.to_vec();
+                words.extend(["asc", "desc", "dirsfirst", "nodirsfirst"]);
+                choices(&words)
+            }
+            (Some("filter"), []) => choices(&["-s", "-g", "-f"]),
+            (Some("paste"), []) => choices(&["-a"]),
+            (Some("conflict"), []) => choices(&["ask", "overwrite", "skip", "rename", "merge"]),
+            (Some("bookmark"), []) => choices(&["add", "rm", "list"]),
+            (Some("bookmark"), ["rm"]) => {
+                let names = self
+                    .bookmarks
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>();
+                choices(&names)
+            }
+            (Some("bookmark"), ["add", _]) => self.path_candidates(word, true),
+            (Some("tab"), []) => choices(&["new", "close", "next", "prev"]),
+            (Some("tab"), ["new"]) => self.path_candidates(word, true),
+            (Some("set"), _) => match word.split_once('=') {
+                Some((name, _)) => match find_option(name) {
+                    Ok(option) => option
+                        .values
+                        .iter()
+                        .map(|value| format!("{}={}", option.name, value))
+                        .filter(|candidate| candidate.starts_with(word))
+                        .collect(),
+                    Err(_) => Vec::new(),
+                },
+                None => OPTIONS
+                    .iter()
+                    .map(|option| {
+                        if option.is_boolean() {
+                            option.name.to_string()
+                        } else {
+                            format!("{}=", option.name)
+                        }
+                    })
+                    .filter(|candidate| candidate.starts_with(word))
+                    .collect(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Entries whose path starts with `word`, with a `/` after directories. Hidden entries are
+    /// only offered once a `.` is typed
+    fn path_candidates(&self, word: &str, dirs_only: bool) -> Vec<String> {
+        if word == "~" {
+            return vec!["~/".to_string()];
+        }
+        let (dir, prefix) = match word.rfind('/') {
+            Some(i) => (&word[..=i], &word[i + 1..]),
+            None => ("", word),
+        };
+        let Ok(entries) = fs::read_dir(self.resolve_path(dir)) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.'))
+                {
+                    return None;
+                }
+                let is_dir = entry.path().is_dir();
+                if dirs_only && !is_dir {
+                    return None;
+                }
+                Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+            })
+            .collect()
+    }
+}
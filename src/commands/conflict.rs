@@ -0,0 +1,247 @@
+use crate::app::ClipboardOperation;
+use crate::commands::{BackgroundCommand, CopyCommand, CopyOptions, MoveCommand};
+use crate::utils::{copy_name, numbered_name};
+use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+
+/// How to deal with a pasted item whose destination already exists
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictResolution {
+    Overwrite, // replace the existing item (which is moved to the trash first)
+    Skip,      // leave the existing item alone and don't paste this one
+    Rename,    // paste under a new, unused name
+    Merge,     // merge directory contents (only offered when both sides are directories)
+}
+
+/// Default behaviour when a paste collides with an existing item
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConflictPolicy {
+    #[default]
+    Ask, // prompt for each conflict
+    Always(ConflictResolution),
+}
+
+impl ConflictPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "ask" => Some(ConflictPolicy::Ask),
+            "overwrite" => Some(ConflictPolicy::Always(ConflictResolution::Overwrite)),
+            "skip" => Some(ConflictPolicy::Always(ConflictResolution::Skip)),
+            "rename" => Some(ConflictPolicy::Always(ConflictResolution::Rename)),
+            "merge" => Some(ConflictPolicy::Always(ConflictResolution::Merge)),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Ask => "ask",
+            ConflictPolicy::Always(ConflictResolution::Overwrite) => "overwrite",
+            ConflictPolicy::Always(ConflictResolution::Skip) => "skip",
+            ConflictPolicy::Always(ConflictResolution::Rename) => "rename",
+            ConflictPolicy::Always(ConflictResolution::Merge) => "merge",
+        }
+    }
+}
+
+/// A single item to be copied/moved, and where it ends up
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub conflict: bool, // destination already exists
+    pub resolution: Option<ConflictResolution>,
+}
+
+impl Transfer {
+    /// Both sides are directories, so their contents can be merged
+    pub fn can_merge(&self) -> bool {
+        self.source.is_dir() && !self.source.is_symlink() && self.destination.is_dir()
+    }
+}
+
+/// A paste waiting for its conflicts to be resolved
+#[derive(Debug, Clone)]
+pub struct PastePlan {
+    pub operation: ClipboardOperation,
+    pub destination: PathBuf,
+    pub transfers: Vec<Transfer>,
+    pub apply_to_all: bool, // use the next resolution for every remaining conflict
+    pub options: CopyOptions, // what copies keep besides contents, mode and timestamps
+    pub from_clipboard: bool, // a pasted cut empties the clipboard once it starts
+}
+
+impl PastePlan {
+    pub fn new(
+        operation: ClipboardOperation,
+        sources: Vec<PathBuf>,
+        destination: PathBuf,
+        options: CopyOptions,
+    ) -> anyhow::Result<Self> {
+        let mut transfers = Vec::new();
+        for source in sources {
+            let name = source
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let same_dir = source.parent() == Some(destination.as_path());
+            match operation {
+                // cutting into the same directory is a no-op:
+                ClipboardOperation::Cut if same_dir => continue,
+
+                // copying into the same directory creates "name (copy)":
+                ClipboardOperation::Copy if same_dir => {
+                    let destination = unique_destination(&destination, &name, copy_name)?;
+                    transfers.push(Transfer {
+                        source,
+                        destination,
+                        conflict: false,
+                        resolution: None,
+                    });
+                }
+
+                _ => {
+                    let target = destination.join(&name);
+                    transfers.push(Transfer {
+                        source,
+                        conflict: target.exists() || target.is_symlink(),
+                        destination: target,
+                        resolution: None,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            operation,
+            destination,
+            transfers,
+            apply_to_all: false,
+            options,
+            from_clipboard: false,
+        })
+    }
+
+    /// Copy/move a single item to exactly `target`, like `cp a.txt b.txt`
+    pub fn to_path(
+        operation: ClipboardOperation,
+        source: PathBuf,
+        target: PathBuf,
+        options: CopyOptions,
+    ) -> Self {
+        let destination = target.parent().map(Path::to_path_buf).unwrap_or_default();
+        let transfer = Transfer {
+            source,
+            conflict: target.exists() || target.is_symlink(),
+            destination: target,
+            resolution: None,
+        };
+        Self {
+            operation,
+            destination,
+            transfers: vec![transfer],
+            apply_to_all: false,
+            options,
+            from_clipboard: false,
+        }
+    }
+
+    /// The first conflict that still needs a decision
+    pub fn current_conflict(&self) -> Option<&Transfer> {
+        self.transfers
+            .iter()
+            .find(|transfer| transfer.conflict && transfer.resolution.is_none())
+    }
+
+    pub fn remaining_conflicts(&self) -> usize {
+        self.transfers
+            .iter()
+            .filter(|transfer| transfer.conflict && transfer.resolution.is_none())
+            .count()
+    }
+
+    /// Resolve the current conflict (or all remaining ones when `apply_to_all` is set).
+    /// Merging leaves conflicts that can't be merged unresolved
+    pub fn resolve(&mut self, resolution: ConflictResolution) -> anyhow::Result<()> {
+        let apply_to_all = self.apply_to_all;
+        for transfer in self
+            .transfers
+            .iter_mut()
+            .filter(|transfer| transfer.conflict && transfer.resolution.is_none())
+        {
+            resolve_transfer(transfer, resolution)?;
+            if !apply_to_all {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the copy/move command for the resolved transfers
+    pub fn into_command(self) -> Option<Box<dyn BackgroundCommand>> {
+        let transfers = self
+            .transfers
+            .into_iter()
+            .filter(|transfer| transfer.resolution != Some(ConflictResolution::Skip))
+            .collect::<Vec<_>>();
+        if transfers.is_empty() {
+            return None;
+        }
+
+        match self.operation {
+            ClipboardOperation::Copy => Some(Box::new(CopyCommand::new(
+                transfers,
+                self.destination,
+                self.options,
+            ))),
+            ClipboardOperation::Cut => {
+                Some(Box::new(MoveCommand::new(transfers, self.destination)))
+            }
+            ClipboardOperation::None => None,
+        }
+    }
+}
+
+fn resolve_transfer(transfer: &mut Transfer, resolution: ConflictResolution) -> anyhow::Result<()> {
+    let resolution = match resolution {
+        ConflictResolution::Merge if !transfer.can_merge() => return Ok(()),
+        ConflictResolution::Rename => {
+            let name = transfer
+                .destination
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let parent = transfer
+                .destination
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            transfer.destination =
+                unique_destination(&parent, &name, |name, n| numbered_name(name, n + 1))?;
+            ConflictResolution::Rename
+        }
+        resolution => resolution,
+    };
+    transfer.resolution = Some(resolution);
+    Ok(())
+}
+
+/// First `directory/label(name, n)` (for n = 1, 2, ...) that doesn't exist yet
+fn unique_destination(
+    directory: &Path,
+    name: &str,
+    label: fn(&str, usize) -> String,
+) -> anyhow::Result<PathBuf> {
+    (1..)
+        .map(|n| directory.join(label(name, n)))
+        .find(|path| !path.exists() && !path.is_symlink())
+        .ok_or_else(|| {
+            anyhow!(
+                "No unused name left for '{}' in {}",
+                name,
+                directory.display()
+            )
+        })
+}
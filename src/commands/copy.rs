@@ -1,51 +1,184 @@
 use crate::app::App;
-use crate::commands::Command;
-use std::path::PathBuf;
+use crate::commands::{BackgroundCommand, Command, ConflictResolution, Transfer};
+use crate::jobs::{JobCancelled, JobContext, scan_totals};
+use crate::trash::{Trash, TrashEntry};
+use crate::utils::remove_path;
+use anyhow::anyhow;
+use std::{
+    ffi::CString,
+    fs::{self, File, Metadata, OpenOptions},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{MetadataExt, OpenOptionsExt, lchown, symlink},
+    },
+    path::{Path, PathBuf},
+};
+
+/// Size of the buffer used when copying file contents (progress is reported per chunk)
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// What to keep from the source, besides contents, mode and timestamps
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CopyOptions {
+    pub preserve_ownership: bool,
+    pub preserve_xattrs: bool,
+}
+
+impl CopyOptions {
+    /// Keep everything, like `cp -a`
+    pub fn archive() -> Self {
+        Self {
+            preserve_ownership: true,
+            preserve_xattrs: true,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct CopyCommand {
-    sources: Vec<PathBuf>,
-    destination: PathBuf,
+    transfers: Vec<Transfer>,
+    destination: PathBuf, // directory being pasted into
+    options: CopyOptions,
+    copied_items: Vec<PathBuf>, // newly created paths (for undo function)
+    replaced: Vec<TrashEntry>,  // existing items that were overwritten (for undo function)
+    copy_count: usize,
+    removed_count: usize, // copies removed by undo
+    errors: Vec<String>,
+    warnings: Vec<String>, // things that couldn't be kept, like extended attributes
 }
 
 impl CopyCommand {
-    pub fn new(sources: Vec<PathBuf>, destination: PathBuf) -> Self {
+    pub fn new(transfers: Vec<Transfer>, destination: PathBuf, options: CopyOptions) -> Self {
         Self {
-            sources,
+            transfers,
             destination,
+            options,
+            copied_items: Vec::new(),
+            replaced: Vec::new(),
+            copy_count: 0,
+            removed_count: 0,
+            errors: Vec::new(),
+            warnings: Vec::new(),
         }
     }
-}
 
-impl Command for CopyCommand {
-    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
-        let mut copy_count = 0;
-        let mut errors = Vec::new();
-
-        // copy all sources to the destination:
-        for source in &self.sources {
-            let dest_path = if self.destination.is_dir() {
-                self.destination
-                    .join(source.file_name().unwrap_or_default())
+    fn copy_transfer(&mut self, transfer: &Transfer, context: &JobContext) -> anyhow::Result<()> {
+        match transfer.resolution {
+            Some(ConflictResolution::Merge) => {
+                self.merge_recursively(&transfer.source, &transfer.destination, context)
+            }
+            Some(ConflictResolution::Overwrite) => {
+                self.replace_and_copy(&transfer.source, &transfer.destination, context)
+            }
+            _ => self.copy_new(&transfer.source, &transfer.destination, context),
+        }
+    }
+
+    /// Copy to a destination that doesn't exist yet, remembering it for undo
+    fn copy_new(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        context: &JobContext,
+    ) -> anyhow::Result<()> {
+        // only remember paths that this copy created, so undo never removes existing data:
+        let existed = destination.symlink_metadata().is_ok();
+        let result = copy_recursively(source, destination, self.options, context);
+        if !existed && destination.symlink_metadata().is_ok() {
+            self.copied_items.push(destination.to_path_buf());
+        }
+        result
+    }
+
+    /// Move the existing destination to the trash, then copy over it
+    fn replace_and_copy(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        context: &JobContext,
+    ) -> anyhow::Result<()> {
+        self.replaced
+            .push(Trash::home()?.trash(destination, context)?);
+        self.copy_new(source, destination, context)
+    }
+
+    /// Copy the contents of `source` into the existing directory `destination`
+    fn merge_recursively(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        context: &JobContext,
+    ) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(source)? {
+            let source_path = entry?.path();
+            let dest_path = destination.join(source_path.file_name().unwrap_or_default());
+            if !dest_path.exists() && !dest_path.is_symlink() {
+                self.copy_new(&source_path, &dest_path, context)?;
+            } else if source_path.is_dir() && !source_path.is_symlink() && dest_path.is_dir() {
+                self.merge_recursively(&source_path, &dest_path, context)?;
             } else {
-                self.destination.clone()
-            };
+                self.replace_and_copy(&source_path, &dest_path, context)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BackgroundCommand for CopyCommand {
+    fn run(&mut self, context: &JobContext) -> anyhow::Result<()> {
+        self.copy_count = 0;
+        self.errors.clear();
+        self.copied_items.clear();
+        self.replaced.clear();
+
+        let sources = self
+            .transfers
+            .iter()
+            .map(|transfer| transfer.source.clone())
+            .collect::<Vec<_>>();
+        let (files, bytes) = scan_totals(&sources);
+        context.set_totals(files, bytes);
 
-            match copy_recursively(source, &dest_path) {
-                Ok(()) => copy_count += 1,
-                Err(e) => errors.push(format!("{}: {}", source.display(), e)),
+        // copy all sources to their destinations:
+        for transfer in self.transfers.clone() {
+            match self.copy_transfer(&transfer, context) {
+                Ok(()) => self.copy_count += 1,
+                Err(e) if e.is::<JobCancelled>() => return Err(e),
+                Err(e) => self
+                    .errors
+                    .push(format!("{}: {}", transfer.source.display(), e)),
             }
         }
+        self.warnings = context.take_warnings();
 
+        Ok(())
+    }
+
+    fn finish(&mut self, app: &mut App) -> anyhow::Result<()> {
         // check if any errors occurred in copying
-        if errors.is_empty() {
-            app.set_status(format!("Copied {} item(s)", copy_count));
+        let mut problems = Vec::new();
+        if !self.errors.is_empty() {
+            problems.push(format!(
+                "{} error(s): {}",
+                self.errors.len(),
+                self.errors.join(", ")
+            ));
+        }
+        if !self.warnings.is_empty() {
+            problems.push(format!(
+                "{} warning(s): {}",
+                self.warnings.len(),
+                self.warnings.join(", ")
+            ));
+        }
+        if problems.is_empty() {
+            app.set_status(format!("Copied {} item(s)", self.copy_count));
         } else {
             app.set_error(format!(
-                "Copied {} item(s), {} error(s): {}",
-                copy_count,
-                errors.len(),
-                errors.join(", ")
+                "Copied {} item(s), {}",
+                self.copy_count,
+                problems.join("; ")
             ));
         }
 
@@ -55,43 +188,319 @@ impl Command for CopyCommand {
         Ok(())
     }
 
+    fn run_undo(&mut self, context: &JobContext) -> anyhow::Result<()> {
+        self.removed_count = 0;
+        self.errors.clear();
+        context.set_totals(self.copied_items.len() as u64, 0);
+
+        // remove all copies (newest first), keeping any not reached yet if cancelled:
+        while let Some(copied) = self.copied_items.last().cloned() {
+            context.checkpoint()?;
+            self.copied_items.pop();
+            match remove_path(&copied) {
+                Ok(()) => self.removed_count += 1,
+                Err(e) => self.errors.push(format!("{}: {}", copied.display(), e)),
+            }
+            context.file_done();
+        }
+
+        // put back anything that was overwritten:
+        for entry in self.replaced.drain(..).rev() {
+            if let Err(e) = entry.restore() {
+                self.errors
+                    .push(format!("{}: {}", entry.original_path.display(), e));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish_undo(&mut self, app: &mut App) -> anyhow::Result<()> {
+        // check for errors:
+        if self.errors.is_empty() {
+            app.set_status(format!(
+                "Undid copy: removed {} item(s)",
+                self.removed_count
+            ));
+        } else {
+            app.set_error(format!(
+                "Removed {} item(s), {} error(s): {}",
+                self.removed_count,
+                self.errors.len(),
+                self.errors.join(", ")
+            ));
+        }
+
+        // update display:
+        app.refresh_file_list()?;
+
+        Ok(())
+    }
+}
+
+impl Command for CopyCommand {
+    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
+        self.run(&JobContext::detached())?;
+        self.finish(app)
+    }
+
     fn description(&self) -> String {
-        if self.sources.len() == 1 {
+        if self.transfers.len() == 1 {
             format!(
                 "Copy '{}' to '{}'",
-                self.sources[0].display(),
-                self.destination.display()
+                self.transfers[0].source.display(),
+                self.transfers[0].destination.display()
             )
         } else {
             format!(
                 "Copy {} items to '{}'",
-                self.sources.len(),
+                self.transfers.len(),
                 self.destination.display()
             )
         }
     }
+
+    fn undo(&mut self, app: &mut App) -> anyhow::Result<()> {
+        self.run_undo(&JobContext::detached())?;
+        self.finish_undo(app)
+    }
+
+    fn can_undo(&self) -> bool {
+        !self.copied_items.is_empty()
+    }
+}
+
+/// Copy `source` to `destination` as faithfully as possible: symlinks are recreated as
+/// symlinks, special files are recreated rather than read, and mode and timestamps are kept
+pub fn copy_recursively(
+    source: &Path,
+    destination: &Path,
+    options: CopyOptions,
+    context: &JobContext,
+) -> anyhow::Result<()> {
+    // copying a directory into itself would never finish:
+    if fs::symlink_metadata(source)?.is_dir() {
+        let source = source.canonicalize()?;
+        let existing_parent = destination
+            .ancestors()
+            .skip(1)
+            .find_map(|ancestor| ancestor.canonicalize().ok());
+        if existing_parent.is_some_and(|parent| parent.starts_with(&source)) {
+            return Err(anyhow!("cannot copy '{}' into itself", source.display()));
+        }
+    }
+
+    copy_entry(source, destination, options, &mut Vec::new(), context)
 }
 
-fn copy_recursively(source: &PathBuf, destination: &PathBuf) -> anyhow::Result<()> {
-    if source.is_dir() {
+/// Copy a single entry (and its contents, for directories). `ancestors` holds the
+/// (device, inode) of each directory being copied above this one, to detect cycles
+fn copy_entry(
+    source: &Path,
+    destination: &Path,
+    options: CopyOptions,
+    ancestors: &mut Vec<(u64, u64)>,
+    context: &JobContext,
+) -> anyhow::Result<()> {
+    context.checkpoint()?;
+
+    let metadata = fs::symlink_metadata(source)?;
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        // a directory that contains itself (e.g. through a bind mount) would recurse forever:
+        let id = (metadata.dev(), metadata.ino());
+        if ancestors.contains(&id) {
+            return Err(anyhow!("'{}' contains itself", source.display()));
+        }
+
         // create destination directory:
-        std::fs::create_dir_all(destination)?;
+        fs::create_dir_all(destination)?;
 
         // copy each entry in the source dir:
-        for entry in std::fs::read_dir(source)? {
+        ancestors.push(id);
+        for entry in fs::read_dir(source)? {
             let entry = entry?;
-            let source_path = entry.path();
             let dest_path = destination.join(entry.file_name());
-            copy_recursively(&source_path, &dest_path)?;
+            copy_entry(&entry.path(), &dest_path, options, ancestors, context)?;
         }
+        ancestors.pop();
     } else {
         // Ensure destination's parent directory exists:
         if let Some(parent) = destination.parent() {
-            std::fs::create_dir_all(parent)?;
+            fs::create_dir_all(parent)?;
+        }
+
+        // copy the entry, removing any partial copy if interrupted:
+        let result = if file_type.is_symlink() {
+            symlink(fs::read_link(source)?, destination).map_err(Into::into)
+        } else if file_type.is_file() {
+            copy_file(source, destination, &metadata, context)
+        } else {
+            make_node(destination, &metadata)
+        };
+        if let Err(e) = result {
+            // something already there isn't a partial copy, and isn't ours to remove:
+            let existed = e
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == ErrorKind::AlreadyExists);
+            if !existed {
+                let _ = fs::remove_file(destination);
+            }
+            return Err(e);
         }
-        // copy file:
-        std::fs::copy(source, destination)?;
+        context.file_done();
     }
 
+    // metadata goes last, so a read-only directory can still be filled and times aren't touched:
+    copy_metadata(source, destination, &metadata, options, context)
+}
+
+/// Copy a regular file's contents in chunks, reporting progress as it goes. Holes in sparse
+/// files are skipped over rather than written out as zeros
+fn copy_file(
+    source: &Path,
+    destination: &Path,
+    metadata: &Metadata,
+    context: &JobContext,
+) -> anyhow::Result<()> {
+    let sparse = metadata.blocks() * 512 < metadata.len();
+
+    let mut reader = File::open(source)?;
+    // only the owner can get at the contents until the real mode is set at the end:
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(destination)?;
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        context.checkpoint()?;
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        if sparse && buffer[..read].iter().all(|&b| b == 0) {
+            writer.seek(SeekFrom::Current(read as i64))?;
+        } else {
+            writer.write_all(&buffer[..read])?;
+        }
+        context.add_bytes(read as u64);
+    }
+
+    // a trailing hole was seeked over, so the length has to be set explicitly:
+    if sparse {
+        writer.set_len(metadata.len())?;
+    }
+    Ok(())
+}
+
+/// Recreate a FIFO, socket or device node (reading from these would block or never end)
+fn make_node(destination: &Path, metadata: &Metadata) -> anyhow::Result<()> {
+    let path = CString::new(destination.as_os_str().as_bytes())?;
+    // safety: `path` is a valid nul-terminated string that outlives the call
+    let result = unsafe {
+        libc::mknod(
+            path.as_ptr(),
+            metadata.mode() as libc::mode_t,
+            metadata.rdev() as libc::dev_t,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Apply ownership, extended attributes, mode and timestamps from `source` to `destination`
+fn copy_metadata(
+    source: &Path,
+    destination: &Path,
+    metadata: &Metadata,
+    options: CopyOptions,
+    context: &JobContext,
+) -> anyhow::Result<()> {
+    let is_symlink = metadata.file_type().is_symlink();
+
+    // ownership first, as changing the owner can clear setuid/setgid bits:
+    if options.preserve_ownership {
+        match lchown(destination, Some(metadata.uid()), Some(metadata.gid())) {
+            // only root can give files away, so (like `cp -a`) don't treat that as an error:
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {}
+            result => result?,
+        }
+    }
+
+    if options.preserve_xattrs && !is_symlink {
+        copy_xattrs(source, destination, context);
+    }
+
+    // symlink permissions can't be changed (and are ignored anyway):
+    if !is_symlink {
+        fs::set_permissions(destination, metadata.permissions())?;
+    }
+
+    set_times(destination, metadata)?;
+    Ok(())
+}
+
+/// Copy extended attributes as far as possible: like `cp -a`, any that can't be read or set
+/// (e.g. `security.*` ones for regular users) are skipped with a warning
+fn copy_xattrs(source: &Path, destination: &Path, context: &JobContext) {
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        Err(e) if e.kind() == ErrorKind::Unsupported => return,
+        Err(e) => {
+            context.warn(format!(
+                "{}: extended attributes not copied: {}",
+                destination.display(),
+                e
+            ));
+            return;
+        }
+    };
+    for name in names {
+        let result = xattr::get(source, &name).and_then(|value| match value {
+            Some(value) => xattr::set(destination, &name, &value),
+            None => Ok(()),
+        });
+        match result {
+            Ok(()) => {}
+            // the destination filesystem may not support them at all:
+            Err(e) if e.kind() == ErrorKind::Unsupported => break,
+            Err(e) => context.warn(format!(
+                "{}: extended attribute '{}' not copied: {}",
+                destination.display(),
+                name.to_string_lossy(),
+                e
+            )),
+        }
+    }
+}
+
+/// Give `destination` the access and modification times from `metadata`, without following symlinks
+fn set_times(destination: &Path, metadata: &Metadata) -> anyhow::Result<()> {
+    let path = CString::new(destination.as_os_str().as_bytes())?;
+    let times = [
+        libc::timespec {
+            tv_sec: metadata.atime() as libc::time_t,
+            tv_nsec: metadata.atime_nsec() as _,
+        },
+        libc::timespec {
+            tv_sec: metadata.mtime() as libc::time_t,
+            tv_nsec: metadata.mtime_nsec() as _,
+        },
+    ];
+    // safety: `path` is a valid nul-terminated string and `times` has the two entries utimensat reads
+    let result = unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
     Ok(())
 }
@@ -45,6 +45,7 @@ impl Command for CreateFileCommand {
         if self.created {
             // remove the file:
             std::fs::remove_file(&self.path)?;
+            self.created = false;
 
             // update display:
             app.refresh_file_list()?;
@@ -52,6 +53,10 @@ impl Command for CreateFileCommand {
         }
         Ok(())
     }
+
+    fn can_undo(&self) -> bool {
+        self.created
+    }
 }
 
 #[derive(Debug)]
@@ -90,6 +95,7 @@ impl Command for CreateDirCommand {
         if self.created {
             // remove the directory:
             std::fs::remove_dir(&self.path)?;
+            self.created = false;
 
             // update display:
             app.refresh_file_list()?;
@@ -98,4 +104,8 @@ impl Command for CreateDirCommand {
 
         Ok(())
     }
+
+    fn can_undo(&self) -> bool {
+        self.created
+    }
 }
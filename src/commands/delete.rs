@@ -1,50 +1,93 @@
 use crate::app::App;
-use crate::commands::Command;
+use crate::commands::{BackgroundCommand, Command};
+use crate::jobs::{JobCancelled, JobContext};
+use crate::trash::{Trash, TrashEntry};
+use crate::utils::remove_path;
 use std::path::PathBuf;
 
 #[derive(Debug)]
 pub struct DeleteCommand {
-    targets: Vec<PathBuf>, // support deleting many at once
+    targets: Vec<PathBuf>,    // support deleting many at once
+    permanent: bool,          // bypass the trash and delete for good
+    trashed: Vec<TrashEntry>, // items moved to the trash (for undo function)
+    delete_count: usize,
+    restored_count: usize, // items put back by undo
+    errors: Vec<String>,
 }
 
 impl DeleteCommand {
     pub fn new(targets: Vec<PathBuf>) -> Self {
-        Self { targets }
+        Self {
+            targets,
+            permanent: false,
+            trashed: Vec::new(),
+            delete_count: 0,
+            restored_count: 0,
+            errors: Vec::new(),
+        }
     }
 
     pub fn new_single(target: PathBuf) -> Self {
+        Self::new(vec![target])
+    }
+
+    pub fn new_permanent(targets: Vec<PathBuf>) -> Self {
         Self {
-            targets: vec![target],
+            permanent: true,
+            ..Self::new(targets)
         }
     }
 }
 
-impl Command for DeleteCommand {
-    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
-        let mut delete_count = 0;
-        let mut errors = Vec::new();
+impl BackgroundCommand for DeleteCommand {
+    fn run(&mut self, context: &JobContext) -> anyhow::Result<()> {
+        self.delete_count = 0;
+        self.errors.clear();
+        self.trashed.clear();
+        context.set_totals(self.targets.len() as u64, 0);
+
+        let trash = if self.permanent {
+            None
+        } else {
+            Some(Trash::home()?)
+        };
 
         // try delete each file/directory:
         for target in &self.targets {
-            match if target.is_dir() {
-                std::fs::remove_dir_all(target)
-            } else {
-                std::fs::remove_file(target)
-            } {
-                Ok(()) => delete_count += 1,
-                Err(e) => errors.push(format!("{} {}", target.display(), e)),
+            context.checkpoint()?;
+            let result = match &trash {
+                Some(trash) => trash
+                    .trash(target, context)
+                    .map(|entry| self.trashed.push(entry)),
+                None => remove_path(target).map_err(anyhow::Error::from),
+            };
+            match result {
+                Ok(()) => self.delete_count += 1,
+                Err(e) if e.is::<JobCancelled>() => return Err(e),
+                Err(e) => self.errors.push(format!("{} {}", target.display(), e)),
             }
+            context.file_done();
         }
 
+        Ok(())
+    }
+
+    fn finish(&mut self, app: &mut App) -> anyhow::Result<()> {
         // handle errors:
-        if errors.is_empty() {
-            app.set_status(format!("Deleted {} item(s)", delete_count));
+        let verb = if self.permanent {
+            "Permanently deleted"
+        } else {
+            "Moved to trash"
+        };
+        if self.errors.is_empty() {
+            app.set_status(format!("{} {} item(s)", verb, self.delete_count));
         } else {
             app.set_error(format!(
-                "Deleted {} item(s), with {} error(s): {}",
-                delete_count,
-                errors.len(),
-                errors.join(", ")
+                "{} {} item(s), with {} error(s): {}",
+                verb,
+                self.delete_count,
+                self.errors.len(),
+                self.errors.join(", ")
             ));
         }
 
@@ -54,11 +97,75 @@ impl Command for DeleteCommand {
         Ok(())
     }
 
+    fn run_undo(&mut self, context: &JobContext) -> anyhow::Result<()> {
+        self.restored_count = 0;
+        self.errors.clear();
+        context.set_totals(self.trashed.len() as u64, 0);
+
+        // restore everything from the trash, keeping any not reached yet if cancelled:
+        while let Some(entry) = self.trashed.first().cloned() {
+            context.checkpoint()?;
+            self.trashed.remove(0);
+            match entry.restore() {
+                Ok(_) => self.restored_count += 1,
+                Err(e) => self
+                    .errors
+                    .push(format!("{}: {}", entry.original_path.display(), e)),
+            }
+            context.file_done();
+        }
+
+        Ok(())
+    }
+
+    fn finish_undo(&mut self, app: &mut App) -> anyhow::Result<()> {
+        // check for errors:
+        if self.errors.is_empty() {
+            app.set_status(format!(
+                "Undid delete: restored {} item(s)",
+                self.restored_count
+            ));
+        } else {
+            app.set_error(format!(
+                "Restored {} item(s), {} error(s): {}",
+                self.restored_count,
+                self.errors.len(),
+                self.errors.join(", ")
+            ));
+        }
+
+        // update display:
+        app.refresh_file_list()?;
+
+        Ok(())
+    }
+}
+
+impl Command for DeleteCommand {
+    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
+        self.run(&JobContext::detached())?;
+        self.finish(app)
+    }
+
     fn description(&self) -> String {
+        let verb = if self.permanent {
+            "Permanently delete"
+        } else {
+            "Delete"
+        };
         if self.targets.len() == 1 {
-            format!("Delete '{}'", self.targets[0].display())
+            format!("{} '{}'", verb, self.targets[0].display())
         } else {
-            format!("Delete {} items", self.targets.len())
+            format!("{} {} items", verb, self.targets.len())
         }
     }
+
+    fn undo(&mut self, app: &mut App) -> anyhow::Result<()> {
+        self.run_undo(&JobContext::detached())?;
+        self.finish_undo(app)
+    }
+
+    fn can_undo(&self) -> bool {
+        !self.trashed.is_empty()
+    }
 }
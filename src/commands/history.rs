@@ -0,0 +1,62 @@
+use crate::commands::{BackgroundCommand, Command};
+
+/// Maximum number of commands kept in the undo history
+const MAX_HISTORY_SIZE: usize = 100;
+
+/// A command in the history. Background commands are undone and redone on a worker thread
+#[derive(Debug)]
+pub enum HistoryEntry {
+    Immediate(Box<dyn Command>),
+    Background(Box<dyn BackgroundCommand>),
+}
+
+impl From<Box<dyn Command>> for HistoryEntry {
+    fn from(command: Box<dyn Command>) -> Self {
+        HistoryEntry::Immediate(command)
+    }
+}
+
+impl From<Box<dyn BackgroundCommand>> for HistoryEntry {
+    fn from(command: Box<dyn BackgroundCommand>) -> Self {
+        HistoryEntry::Background(command)
+    }
+}
+
+/// Undo/redo stacks of executed commands
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a freshly executed command. This invalidates anything that could be redone
+    pub fn record(&mut self, command: impl Into<HistoryEntry>) {
+        self.redo_stack.clear();
+        self.push_undo(command);
+    }
+
+    /// Push a command back onto the undo stack (e.g. after it has been redone)
+    pub fn push_undo(&mut self, command: impl Into<HistoryEntry>) {
+        self.undo_stack.push(command.into());
+        if self.undo_stack.len() > MAX_HISTORY_SIZE {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    pub fn push_redo(&mut self, command: impl Into<HistoryEntry>) {
+        self.redo_stack.push(command.into());
+    }
+
+    pub fn pop_undo(&mut self) -> Option<HistoryEntry> {
+        self.undo_stack.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<HistoryEntry> {
+        self.redo_stack.pop()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::app::App;
+use crate::{app::App, jobs::JobContext};
 use anyhow::Result;
 
 /// Command trait defines the interface for all file operations
@@ -13,8 +13,40 @@ pub trait Command: std::fmt::Debug {
     fn undo(&mut self, _app: &mut App) -> Result<()> {
         Err(anyhow::anyhow!("Undo not implemented for this command!"))
     }
+
+    /// Redo the command after it has been undone. By default the command is simply executed again
+    fn redo(&mut self, app: &mut App) -> Result<()> {
+        self.execute(app)
+    }
+
+    /// Whether this command supports undo (only undoable commands are kept in the history)
+    fn can_undo(&self) -> bool {
+        false
+    }
 }
 
+/// Commands whose filesystem work can run on a worker thread (see `crate::jobs`)
+pub trait BackgroundCommand: Command + Send {
+    /// Do the work, reporting progress through `context`. Runs off the UI thread, so has no app access
+    fn run(&mut self, context: &JobContext) -> Result<()>;
+
+    /// Report the outcome and update the display once the work is done (on the UI thread)
+    fn finish(&mut self, app: &mut App) -> Result<()>;
+
+    /// Undo the work, like `run`. Whatever is left undone when cancelled can be undone later
+    fn run_undo(&mut self, _context: &JobContext) -> Result<()> {
+        Err(anyhow::anyhow!("Undo not implemented for this command!"))
+    }
+
+    /// Report the outcome of `run_undo` and update the display (on the UI thread)
+    fn finish_undo(&mut self, _app: &mut App) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub mod history;
+pub use history::{CommandHistory, HistoryEntry};
+
 pub mod rename;
 pub use rename::RenameCommand;
 
@@ -24,8 +56,14 @@ pub use create::{CreateDirCommand, CreateFileCommand};
 pub mod delete;
 pub use delete::DeleteCommand;
 
+pub mod conflict;
+pub use conflict::{ConflictPolicy, ConflictResolution, PastePlan, Transfer};
+
 pub mod copy;
-pub use copy::CopyCommand;
+pub use copy::{CopyCommand, CopyOptions};
 
 pub mod move_cmd;
 pub use move_cmd::MoveCommand;
+
+pub mod trash;
+pub use trash::{PurgeTrashCommand, RestoreCommand};
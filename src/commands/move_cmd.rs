@@ -1,55 +1,176 @@
 use crate::app::App;
-use crate::commands::Command;
-use std::path::PathBuf;
+use crate::commands::copy::{CopyOptions, copy_recursively};
+use crate::commands::{BackgroundCommand, Command, ConflictResolution, Transfer};
+use crate::jobs::{JobCancelled, JobContext, scan_each};
+use crate::trash::{Trash, TrashEntry};
+use crate::utils::remove_path;
+use anyhow::anyhow;
+use std::{
+    ffi::CString,
+    fs,
+    io::ErrorKind,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug)]
 pub struct MoveCommand {
-    sources: Vec<PathBuf>,
-    destination: PathBuf,
+    transfers: Vec<Transfer>,
+    destination: PathBuf,                 // directory being pasted into
     moved_items: Vec<(PathBuf, PathBuf)>, // tuples of original location, new location (for undo function)
+    merged_dirs: Vec<PathBuf>, // source directories emptied and removed by a merge (for undo function)
+    replaced: Vec<TrashEntry>, // existing items that were overwritten (for undo function)
+    moved_count: usize,
+    restored_count: usize, // items moved back by undo
+    errors: Vec<String>,
+    warnings: Vec<String>, // things that didn't stop an item moving, like an original left behind
 }
 
 impl MoveCommand {
-    pub fn new(sources: Vec<PathBuf>, destination: PathBuf) -> Self {
+    pub fn new(transfers: Vec<Transfer>, destination: PathBuf) -> Self {
         Self {
-            sources,
+            transfers,
             destination,
             moved_items: Vec::new(),
+            merged_dirs: Vec::new(),
+            replaced: Vec::new(),
+            moved_count: 0,
+            restored_count: 0,
+            errors: Vec::new(),
+            warnings: Vec::new(),
         }
     }
-}
 
-impl Command for MoveCommand {
-    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
-        let mut moved_count = 0;
-        let mut errors = Vec::new();
+    fn move_transfer(&mut self, transfer: &Transfer, context: &JobContext) -> anyhow::Result<()> {
+        match transfer.resolution {
+            Some(ConflictResolution::Merge) => {
+                self.merge_recursively(&transfer.source, &transfer.destination, context)
+            }
+            Some(ConflictResolution::Overwrite) => {
+                self.replace_and_move(&transfer.source, &transfer.destination, context)
+            }
+            _ => self.move_item(&transfer.source, &transfer.destination, context),
+        }
+    }
+
+    fn move_item(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        context: &JobContext,
+    ) -> anyhow::Result<()> {
+        match move_path(source, destination, context) {
+            Ok(()) => {}
+            // the item is at the destination, so undo needs to know about it all the same:
+            Err(e) if e.is::<SourceNotRemoved>() => {
+                self.warnings.push(format!("{}: {}", source.display(), e));
+            }
+            Err(e) => return Err(e),
+        }
+        self.moved_items
+            .push((source.to_path_buf(), destination.to_path_buf()));
+        Ok(())
+    }
 
-        for source in &self.sources {
-            let dest_path = if self.destination.is_dir() {
-                self.destination
-                    .join(source.file_name().unwrap_or_default())
+    /// Move the existing destination to the trash, then move over it
+    fn replace_and_move(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        context: &JobContext,
+    ) -> anyhow::Result<()> {
+        self.replaced
+            .push(Trash::home()?.trash(destination, context)?);
+        self.move_item(source, destination, context)
+    }
+
+    /// Move the contents of `source` into the existing directory `destination`, then remove `source`
+    fn merge_recursively(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        context: &JobContext,
+    ) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(source)? {
+            context.checkpoint()?;
+            let source_path = entry?.path();
+            let dest_path = destination.join(source_path.file_name().unwrap_or_default());
+            if !dest_path.exists() && !dest_path.is_symlink() {
+                self.move_item(&source_path, &dest_path, context)?;
+            } else if source_path.is_dir() && !source_path.is_symlink() && dest_path.is_dir() {
+                self.merge_recursively(&source_path, &dest_path, context)?;
             } else {
-                self.destination.clone()
-            };
-
-            match std::fs::rename(source, &dest_path) {
-                Ok(()) => {
-                    self.moved_items.push((source.clone(), dest_path));
-                    moved_count += 1;
-                }
-                Err(e) => errors.push(format!("{}: {}", source.display(), e)),
+                self.replace_and_move(&source_path, &dest_path, context)?;
+            }
+        }
+
+        // everything has been moved out, so the source directory goes too:
+        std::fs::remove_dir(source)?;
+        self.merged_dirs.push(source.to_path_buf());
+        Ok(())
+    }
+}
+
+impl BackgroundCommand for MoveCommand {
+    fn run(&mut self, context: &JobContext) -> anyhow::Result<()> {
+        self.moved_count = 0;
+        self.errors.clear();
+        self.warnings.clear();
+        self.moved_items.clear();
+        self.merged_dirs.clear();
+        self.replaced.clear();
+        let sources = self
+            .transfers
+            .iter()
+            .map(|transfer| transfer.source.clone())
+            .collect::<Vec<_>>();
+        let totals = scan_each(&sources, context);
+
+        let (mut files_done, mut bytes_done) = (0, 0);
+        for (transfer, (files, bytes)) in self.transfers.clone().into_iter().zip(totals) {
+            context.checkpoint()?;
+            match self.move_transfer(&transfer, context) {
+                Ok(()) => self.moved_count += 1,
+                Err(e) if e.is::<JobCancelled>() => return Err(e),
+                Err(e) => self
+                    .errors
+                    .push(format!("{}: {}", transfer.source.display(), e)),
             }
+            // renames only count once the whole item is done:
+            files_done += files;
+            bytes_done += bytes;
+            context.catch_up(files_done, bytes_done);
         }
+        // e.g. attributes that couldn't be kept when moving to another filesystem:
+        self.warnings.extend(context.take_warnings());
+
+        Ok(())
+    }
 
+    fn finish(&mut self, app: &mut App) -> anyhow::Result<()> {
         // check if any errors occurred:
-        if errors.is_empty() {
-            app.set_status(format!("Moved {} item(s)", moved_count));
+        let mut problems = Vec::new();
+        if !self.errors.is_empty() {
+            problems.push(format!(
+                "{} error(s): {}",
+                self.errors.len(),
+                self.errors.join(", ")
+            ));
+        }
+        if !self.warnings.is_empty() {
+            problems.push(format!(
+                "{} warning(s): {}",
+                self.warnings.len(),
+                self.warnings.join(", ")
+            ));
+        }
+        if problems.is_empty() {
+            app.set_status(format!("Moved {} item(s)", self.moved_count));
         } else {
             app.set_error(format!(
-                "Moved {} item(s), {} error(s): {}",
-                moved_count,
-                errors.len(),
-                errors.join(", ")
+                "Moved {} item(s), {}",
+                self.moved_count,
+                problems.join("; ")
             ));
         }
 
@@ -59,49 +180,212 @@ impl Command for MoveCommand {
         Ok(())
     }
 
+    fn run_undo(&mut self, context: &JobContext) -> anyhow::Result<()> {
+        self.restored_count = 0;
+        self.errors.clear();
+        // (newest first, the order they're moved back in)
+        let moved = self
+            .moved_items
+            .iter()
+            .rev()
+            .map(|(_, moved)| moved.clone())
+            .collect::<Vec<_>>();
+        let mut totals = scan_each(&moved, context).into_iter();
+
+        // recreate directories that were emptied by a merge:
+        for dir in &self.merged_dirs {
+            if let Err(e) = fs::create_dir_all(dir) {
+                self.errors.push(format!("{}: {}", dir.display(), e));
+            }
+        }
+
+        // move all files back (which may also cross filesystems), keeping any not reached
+        // yet if cancelled:
+        let (mut files_done, mut bytes_done) = (0, 0);
+        while let Some((original, moved)) = self.moved_items.last().cloned() {
+            context.checkpoint()?;
+            match move_path(&moved, &original, context) {
+                Ok(()) => self.restored_count += 1,
+                Err(e) if e.is::<JobCancelled>() => return Err(e),
+                Err(e) => self.errors.push(format!("{}: {}", moved.display(), e)),
+            }
+            self.moved_items.pop();
+            let (files, bytes) = totals.next().unwrap_or_default();
+            files_done += files;
+            bytes_done += bytes;
+            context.catch_up(files_done, bytes_done);
+        }
+
+        // put back anything that was overwritten:
+        for entry in self.replaced.drain(..).rev() {
+            if let Err(e) = entry.restore() {
+                self.errors
+                    .push(format!("{}: {}", entry.original_path.display(), e));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish_undo(&mut self, app: &mut App) -> anyhow::Result<()> {
+        // check for errors:
+        if self.errors.is_empty() {
+            app.set_status(format!(
+                "Undid move: restored {} item(s)",
+                self.restored_count
+            ));
+        } else {
+            app.set_error(format!(
+                "Restored {} item(s), {} error(s): {}",
+                self.restored_count,
+                self.errors.len(),
+                self.errors.join(", ")
+            ));
+        }
+
+        // update display:
+        app.refresh_file_list()?;
+
+        Ok(())
+    }
+}
+
+impl Command for MoveCommand {
+    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
+        self.run(&JobContext::detached())?;
+        self.finish(app)
+    }
+
     fn description(&self) -> String {
-        if self.sources.len() == 1 {
+        if self.transfers.len() == 1 {
             format!(
                 "Move '{}' to '{}'",
-                self.sources[0].display(),
-                self.destination.display()
+                self.transfers[0].source.display(),
+                self.transfers[0].destination.display()
             )
         } else {
             format!(
                 "Move {} items to '{}'",
-                self.sources.len(),
+                self.transfers.len(),
                 self.destination.display()
             )
         }
     }
 
     fn undo(&mut self, app: &mut App) -> anyhow::Result<()> {
-        let mut restored_count = 0;
-        let mut errors = Vec::new();
-
-        // move all files back
-        for (original, moved) in &self.moved_items {
-            match std::fs::rename(moved, original) {
-                Ok(()) => restored_count += 1,
-                Err(e) => errors.push(format!("{}: {}", moved.display(), e)),
+        self.run_undo(&JobContext::detached())?;
+        self.finish_undo(app)
+    }
+
+    fn can_undo(&self) -> bool {
+        !self.moved_items.is_empty()
+    }
+}
+
+/// Rename `source` to `destination`. When they are on different filesystems, fall back to
+/// copying (keeping permissions and timestamps), checking the copy, then deleting the source.
+/// Never replaces an item that has appeared at `destination` since the move was planned
+pub fn move_path(source: &Path, destination: &Path, context: &JobContext) -> anyhow::Result<()> {
+    match rename_no_replace(source, destination) {
+        // progress is counted by the caller, which already knows how much was below `source`:
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            Err(anyhow!("'{}' already exists", destination.display()))
+        }
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            // (the cleanup below would otherwise delete whatever is there)
+            if fs::symlink_metadata(destination).is_ok() {
+                return Err(anyhow!("'{}' already exists", destination.display()));
+            }
+            let copied = copy_recursively(source, destination, CopyOptions::archive(), context)
+                .and_then(|()| verify_copy(source, destination));
+            if let Err(e) = copied {
+                // never leave a half-finished copy behind:
+                let _ = remove_path(destination);
+                return Err(e);
             }
+            // the copy is complete by now, so failing here leaves the item in both places:
+            remove_path(source).map_err(SourceNotRemoved)?;
+            Ok(())
         }
+        Err(e) => Err(e.into()),
+    }
+}
 
-        // check for errors:
-        if errors.is_empty() {
-            app.set_status(format!("Undid move: restored {} item(s)", restored_count));
-        } else {
-            app.set_error(format!(
-                "Restored {} item(s), {} error(s): {}",
-                restored_count,
-                errors.len(),
-                errors.join(", ")
-            ));
+/// Rename without replacing an existing `destination`, atomically where the filesystem supports it
+fn rename_no_replace(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let from = CString::new(source.as_os_str().as_bytes())?;
+    let to = CString::new(destination.as_os_str().as_bytes())?;
+    // safety: `from` and `to` are valid nul-terminated strings that outlive the call
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            from.as_ptr(),
+            libc::AT_FDCWD,
+            to.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if result == 0 {
+        return Ok(());
+    }
+    let error = std::io::Error::last_os_error();
+    match error.raw_os_error() {
+        // the filesystem (or kernel) doesn't support the flag, so check just before renaming:
+        Some(libc::EINVAL | libc::ENOSYS | libc::EOPNOTSUPP) => {
+            if fs::symlink_metadata(destination).is_ok() {
+                return Err(ErrorKind::AlreadyExists.into());
+            }
+            fs::rename(source, destination)
         }
+        _ => Err(error),
+    }
+}
 
-        // update display:
-        app.refresh_file_list()?;
+/// Error from `move_path` when an item was copied across filesystems but the original
+/// couldn't be removed afterwards (perhaps only partly), so the move has still happened
+#[derive(Debug)]
+pub struct SourceNotRemoved(pub std::io::Error);
 
-        Ok(())
+impl std::fmt::Display for SourceNotRemoved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "moved, but couldn't remove the original: {}", self.0)
     }
 }
+
+impl std::error::Error for SourceNotRemoved {}
+
+/// Check that `copy` has the same structure, file types, link targets and file sizes as `original`
+fn verify_copy(original: &Path, copy: &Path) -> anyhow::Result<()> {
+    let original_meta = fs::symlink_metadata(original)?;
+    let copy_meta = fs::symlink_metadata(copy)?;
+    if original_meta.file_type() != copy_meta.file_type() {
+        return Err(anyhow!(
+            "copy of '{}' has the wrong type",
+            original.display()
+        ));
+    }
+
+    if original_meta.is_dir() {
+        let mut count = 0;
+        for entry in fs::read_dir(original)? {
+            let entry = entry?;
+            verify_copy(&entry.path(), &copy.join(entry.file_name()))?;
+            count += 1;
+        }
+        if fs::read_dir(copy)?.count() != count {
+            return Err(anyhow!(
+                "copy of '{}' has extra entries",
+                original.display()
+            ));
+        }
+    } else if original_meta.is_symlink() {
+        if fs::read_link(original)? != fs::read_link(copy)? {
+            return Err(anyhow!("copy of '{}' points elsewhere", original.display()));
+        }
+    } else if original_meta.len() != copy_meta.len() {
+        return Err(anyhow!("copy of '{}' is incomplete", original.display()));
+    }
+
+    Ok(())
+}
@@ -6,7 +6,7 @@ use std::path::PathBuf;
 pub struct RenameCommand {
     source: PathBuf,
     new_name: String,
-    old_name: Option<String>, // For potential undo functionality later
+    old_name: Option<String>, // set once executed, used for undo
 }
 
 impl RenameCommand {
@@ -57,4 +57,8 @@ impl Command for RenameCommand {
         }
         Ok(())
     }
+
+    fn can_undo(&self) -> bool {
+        self.old_name.is_some()
+    }
 }
@@ -0,0 +1,192 @@
+use crate::app::App;
+use crate::commands::{BackgroundCommand, Command};
+use crate::jobs::JobContext;
+use crate::trash::{Trash, TrashEntry};
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub struct RestoreCommand {
+    entries: Vec<TrashEntry>,
+    restored: Vec<PathBuf>, // restored locations (for undo function)
+}
+
+impl RestoreCommand {
+    pub fn new(entries: Vec<TrashEntry>) -> Self {
+        Self {
+            entries,
+            restored: Vec::new(),
+        }
+    }
+}
+
+impl Command for RestoreCommand {
+    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
+        let mut restored_count = 0;
+        let mut errors = Vec::new();
+        self.restored.clear();
+
+        for entry in &self.entries {
+            match entry.restore() {
+                Ok(path) => {
+                    self.restored.push(path);
+                    restored_count += 1;
+                }
+                Err(e) => errors.push(format!("{}: {}", entry.name, e)),
+            }
+        }
+
+        // check if any errors occurred:
+        if errors.is_empty() {
+            app.set_status(format!("Restored {} item(s)", restored_count));
+        } else {
+            app.set_error(format!(
+                "Restored {} item(s), {} error(s): {}",
+                restored_count,
+                errors.len(),
+                errors.join(", ")
+            ));
+        }
+
+        // update display:
+        app.refresh_file_list()?;
+        app.refresh_trash_list()?;
+
+        Ok(())
+    }
+
+    fn description(&self) -> String {
+        if self.entries.len() == 1 {
+            format!("Restore '{}'", self.entries[0].original_path.display())
+        } else {
+            format!("Restore {} items", self.entries.len())
+        }
+    }
+
+    fn undo(&mut self, app: &mut App) -> anyhow::Result<()> {
+        let trash = Trash::home()?;
+        let mut trashed_count = 0;
+        let mut errors = Vec::new();
+
+        // move everything back into the trash:
+        for path in self.restored.drain(..) {
+            match trash.trash(&path, &JobContext::detached()) {
+                Ok(_) => trashed_count += 1,
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+
+        // check for errors:
+        if errors.is_empty() {
+            app.set_status(format!(
+                "Undid restore: moved {} item(s) to trash",
+                trashed_count
+            ));
+        } else {
+            app.set_error(format!(
+                "Moved {} item(s) to trash, {} error(s): {}",
+                trashed_count,
+                errors.len(),
+                errors.join(", ")
+            ));
+        }
+
+        // update display:
+        app.refresh_file_list()?;
+        app.refresh_trash_list()?;
+
+        Ok(())
+    }
+
+    fn can_undo(&self) -> bool {
+        !self.restored.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct PurgeTrashCommand {
+    entries: Option<Vec<TrashEntry>>, // `None` empties the whole trash
+    purged_count: usize,
+    errors: Vec<String>,
+}
+
+impl PurgeTrashCommand {
+    pub fn new(entries: Vec<TrashEntry>) -> Self {
+        Self {
+            entries: Some(entries),
+            purged_count: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            entries: None,
+            purged_count: 0,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl BackgroundCommand for PurgeTrashCommand {
+    fn run(&mut self, context: &JobContext) -> anyhow::Result<()> {
+        self.purged_count = 0;
+        self.errors.clear();
+
+        let entries = match &self.entries {
+            Some(entries) => entries.clone(),
+            None => Trash::home()?.list()?,
+        };
+        context.set_totals(entries.len() as u64, 0);
+
+        for entry in &entries {
+            context.checkpoint()?;
+            match entry.purge() {
+                Ok(()) => self.purged_count += 1,
+                Err(e) => self.errors.push(format!("{}: {}", entry.name, e)),
+            }
+            context.file_done();
+        }
+
+        // also clear out anything the listing skipped (e.g. items without valid trash info):
+        if self.entries.is_none() {
+            self.purged_count += Trash::home()?.empty()?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, app: &mut App) -> anyhow::Result<()> {
+        if self.errors.is_empty() {
+            app.set_status(format!("Permanently deleted {} item(s)", self.purged_count));
+        } else {
+            app.set_error(format!(
+                "Permanently deleted {} item(s), {} error(s): {}",
+                self.purged_count,
+                self.errors.len(),
+                self.errors.join(", ")
+            ));
+        }
+
+        // update display:
+        app.refresh_trash_list()?;
+
+        Ok(())
+    }
+}
+
+impl Command for PurgeTrashCommand {
+    fn execute(&mut self, app: &mut App) -> anyhow::Result<()> {
+        self.run(&JobContext::detached())?;
+        self.finish(app)
+    }
+
+    fn description(&self) -> String {
+        match &self.entries {
+            Some(entries) if entries.len() == 1 => {
+                format!("Permanently delete '{}' from trash", entries[0].name)
+            }
+            Some(entries) => format!("Permanently delete {} items from trash", entries.len()),
+            None => "Empty trash".to_string(),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::PathBuf};
+
+use crate::{
+    columns::ViewLayout,
+    commands::ConflictPolicy,
+    keymap::{Action, KEYMAP_MODES, KeyChord, Keymap},
+    listing::{IgnoreMode, ListingFilter},
+    opener::{Opener, OpenerRule},
+    sort::{SortKey, SortOrder},
+    theme::{LsColors, Theme},
+    utils::config_home,
+};
+
+/// Settings read from `$XDG_CONFIG_HOME/clexp/config.toml`
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub opener: Opener,
+    pub sort: SortOrder,
+    pub sort_per_directory: bool, // remember the sort order picked in each directory
+    pub listing: ListingFilter,
+    pub layout: ViewLayout,
+    pub conflict_policy: ConflictPolicy, // what to do when a pasted item already exists
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // still picks up `NO_COLOR` and `LS_COLORS` from the environment:
+        Self::parse("").expect("an empty config is valid")
+    }
+}
+
+/// The config file as written by the user, e.g.
+///
+/// ```toml
+/// keymap = "vim"              # preset to start from: "default" or "vim"
+/// layout = "columns"          # split (list and preview), columns (parent, list, preview) or dual
+/// conflict = "ask"            # when a pasted item exists: ask, overwrite, skip, rename or merge
+///
+/// [keys.normal]
+/// "ctrl+d" = "delete"         # chord = action
+/// "x" = "none"                # unbind a default
+///
+/// [theme]
+/// name = "light"              # dark, light, high-contrast or no-color
+/// ls_colors = true            # use $LS_COLORS (or give a string in the same format)
+/// syntax = "Solarized (dark)" # highlighting for previews, or "none"
+///
+/// [theme.styles]
+/// directory = "bold blue"
+///
+/// [theme.file_types]
+/// "*.rs" = "38;5;208"         # LS_COLORS-style entries
+///
+/// [[opener]]                  # tried before the default openers, see `OpenerRule`
+/// ext = ["pdf"]
+/// command = "zathura"
+///
+/// [sort]
+/// by = "natural"              # name, natural, size, mtime, extension or type
+/// reverse = false
+/// dirs_first = true
+/// per_directory = true        # remember the order picked in each directory
+///
+/// [listing]
+/// show_hidden = false         # list dotfiles
+/// ignore = "dim"              # entries matched by .gitignore/.ignore: off, dim or hide
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    keymap: Option<String>,
+    layout: Option<String>,
+    conflict: Option<String>,
+    keys: BTreeMap<String, BTreeMap<String, String>>, // mode -> chord -> action
+    theme: ThemeConfig,
+    opener: Vec<OpenerRule>,
+    sort: SortConfig,
+    listing: ListingConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeConfig {
+    name: Option<String>,
+    ls_colors: Option<LsColorsSetting>,
+    syntax: Option<String>,
+    styles: BTreeMap<String, String>, // theme element -> style, e.g. "bold blue"
+    file_types: BTreeMap<String, String>, // LS_COLORS key -> SGR codes
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SortConfig {
+    by: String,
+    reverse: bool,
+    dirs_first: bool,
+    per_directory: bool,
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        let order = SortOrder::default();
+        Self {
+            by: order.key.label().to_string(),
+            reverse: order.reverse,
+            dirs_first: order.dirs_first,
+            per_directory: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ListingConfig {
+    show_hidden: bool,
+    ignore: String,
+}
+
+impl Default for ListingConfig {
+    fn default() -> Self {
+        let listing = ListingFilter::default();
+        Self {
+            show_hidden: listing.show_hidden,
+            ignore: listing.ignore.label().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum LsColorsSetting {
+    Enabled(bool),
+    Spec(String),
+}
+
+impl ThemeConfig {
+    fn build(&self) -> Result<Theme> {
+        // `NO_COLOR` beats anything in the config:
+        if Theme::no_color_requested() {
+            return Ok(Theme::no_color());
+        }
+
+        let mut theme = Theme::named(self.name.as_deref().unwrap_or("dark"))?;
+        if theme.name == Theme::no_color().name {
+            return Ok(theme);
+        }
+
+        theme.file_types = match &self.ls_colors {
+            None | Some(LsColorsSetting::Enabled(true)) => LsColors::from_env(),
+            Some(LsColorsSetting::Enabled(false)) => LsColors::default(),
+            Some(LsColorsSetting::Spec(spec)) => {
+                let mut colors = LsColors::default();
+                colors.extend(spec);
+                colors
+            }
+        };
+        if let Some(syntax) = &self.syntax {
+            theme.syntax = (syntax != "none").then(|| syntax.clone());
+        }
+        for (key, codes) in &self.file_types {
+            theme.file_types.set(key, codes);
+        }
+        for (element, style) in &self.styles {
+            theme
+                .set_style(element, style)
+                .with_context(|| "[theme.styles]")?;
+        }
+
+        Ok(theme)
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        config_home().map(|dir| dir.join("clexp").join("config.toml"))
+    }
+
+    /// Load the user's config, or the defaults if there isn't one
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("reading '{}'", path.display())),
+        };
+        Self::parse(&contents).with_context(|| format!("in '{}'", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(contents)?;
+
+        let mut keymap = Keymap::preset(file.keymap.as_deref().unwrap_or("default"))?;
+        for (section, bindings) in &file.keys {
+            let (mode, _, _) = KEYMAP_MODES
+                .iter()
+                .find(|(_, name, _)| name == section)
+                .ok_or_else(|| anyhow!("unknown key section [keys.{}]", section))?;
+            for (chord, action) in bindings {
+                let chord =
+                    KeyChord::parse(chord).with_context(|| format!("[keys.{}]", section))?;
+                let action = if action == "none" {
+                    None
+                } else {
+                    let parsed = Action::parse(action);
+                    Some(parsed.ok_or_else(|| {
+                        anyhow!("[keys.{}]: unknown action '{}'", section, action)
+                    })?)
+                };
+                keymap.bind(mode.clone(), chord, action);
+            }
+        }
+
+        let theme = file.theme.build()?;
+
+        let opener = Opener::with_rules(file.opener)?;
+
+        let sort = SortOrder {
+            key: SortKey::parse(&file.sort.by)
+                .ok_or_else(|| anyhow!("[sort]: unknown sort key '{}'", file.sort.by))?,
+            reverse: file.sort.reverse,
+            dirs_first: file.sort.dirs_first,
+        };
+
+        let listing = ListingFilter {
+            show_hidden: file.listing.show_hidden,
+            ignore: IgnoreMode::parse(&file.listing.ignore).ok_or_else(|| {
+                anyhow!(
+                    "[listing]: unknown ignore mode '{}' (expected off, dim or hide)",
+                    file.listing.ignore
+                )
+            })?,
+        };
+
+        let layout = match &file.layout {
+            Some(layout) => ViewLayout::parse(layout).ok_or_else(|| {
+                anyhow!(
+                    "unknown layout '{}' (expected split, columns or dual)",
+                    layout
+                )
+            })?,
+            None => ViewLayout::default(),
+        };
+
+        let conflict_policy = match &file.conflict {
+            Some(conflict) => ConflictPolicy::parse(conflict).ok_or_else(|| {
+                anyhow!(
+                    "unknown conflict policy '{}' (expected ask, overwrite, skip, rename or merge)",
+                    conflict
+                )
+            })?,
+            None => ConflictPolicy::default(),
+        };
+
+        Ok(Self {
+            keymap,
+            theme,
+            opener,
+            sort,
+            sort_per_directory: file.sort.per_directory,
+            listing,
+            layout,
+            conflict_policy,
+        })
+    }
+}
@@ -1,11 +1,15 @@
 use crate::utils::DOUBLE_CLICK_DURATION;
 use crate::{
-    app::{App, AppMode, ClipboardOperation, InputContext},
+    app::{App, AppMode, CANT_DELETE_CURRENT_DIR, ClipboardOperation, InputContext, MarkPrompt},
+    columns::ViewLayout,
     commands::{
-        Command, CopyCommand, CreateDirCommand, CreateFileCommand, DeleteCommand, MoveCommand,
-        RenameCommand,
+        ConflictResolution, CopyOptions, CreateDirCommand, CreateFileCommand, DeleteCommand,
+        PurgeTrashCommand, RenameCommand, RestoreCommand,
     },
-    ui::HELP_DIALOG,
+    keymap::Action,
+    listing::ListingFilter,
+    sort::SortOrder,
+    ui::help_lines,
 };
 use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
@@ -15,29 +19,51 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKi
 // !---------------------
 
 pub fn handle_key_event(key: KeyEvent, app: &mut App) -> Result<()> {
+    // text entry isn't remappable, as every printable key types itself:
     match app.mode {
-        AppMode::Normal => handle_key_event_normal(key, app),
-        AppMode::Help => handle_key_event_help(key, app),
-        AppMode::Input => handle_key_event_input(key, app),
-        AppMode::Confirm => handle_key_event_confirm(key, app),
-        AppMode::MultiSelect => handle_key_event_multi_select(key, app),
-        AppMode::Clipboard => handle_key_event_clipboard(key, app),
-        AppMode::Command => handle_key_event_command(key, app),
+        AppMode::Input => return handle_key_event_input(key, app),
+        AppMode::Command => return handle_key_event_command(key, app),
+        AppMode::Jumper => return handle_key_event_jumper(key, app),
+        _ => {}
+    }
+
+    // the key after `m` or `'` names a bookmark:
+    if let Some(prompt) = app.mark_prompt.take() {
+        return handle_key_event_mark(prompt, key, app);
+    }
+
+    // everything else goes through the keymap:
+    let Some(action) = app.keymap.action(&app.mode, &key) else {
+        return Ok(());
+    };
+    match app.mode {
+        AppMode::Normal => handle_action_normal(action, app),
+        AppMode::Help => handle_action_help(action, app),
+        AppMode::Confirm => handle_action_confirm(action, app),
+        AppMode::MultiSelect => handle_action_multi_select(action, app),
+        AppMode::Clipboard => handle_action_clipboard(action, app),
+        AppMode::Trash => handle_action_trash(action, app),
+        AppMode::Jobs => handle_action_jobs(action, app),
+        AppMode::Conflict => handle_action_conflict(action, app),
+        AppMode::JumpList => handle_action_jump_list(action, app),
+        AppMode::Bookmarks => handle_action_bookmarks(action, app),
+        AppMode::Find => handle_action_find(action, app),
+        AppMode::Input | AppMode::Command | AppMode::Jumper => Ok(()),
     }
 }
 
-pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
+pub fn handle_action_normal(action: Action, app: &mut App) -> Result<()> {
+    match action {
         // Navigation within current directory:
-        KeyCode::Down => {
+        Action::MoveDown => {
             app.file_list.next();
         }
-        KeyCode::Up => {
+        Action::MoveUp => {
             app.file_list.prev();
         }
 
         // Command mode:
-        KeyCode::Char('/') => {
+        Action::Command => {
             app.mode = AppMode::Command;
             app.input_context = Some(InputContext::Command);
             app.input_buffer.clear();
@@ -45,31 +71,153 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // Navigating into/out of directories:
-        KeyCode::Left => {
+        // in the tree view, fold the directory away (or move out to its parent) first:
+        Action::GoUp if app.can_collapse() => {
+            app.collapse_selected();
+        }
+        Action::GoUp => {
             app.navigate_up()?;
         }
-        KeyCode::Right | KeyCode::Enter => {
+        Action::Open => {
             app.enter_selected()?;
         }
 
+        // Browsing history:
+        Action::Back => {
+            app.go_back()?;
+        }
+        Action::Forward => {
+            app.go_forward()?;
+        }
+        Action::JumpList => {
+            app.open_jump_list();
+        }
+
+        // Bookmarks:
+        Action::SetMark => {
+            app.mark_prompt = Some(MarkPrompt::Set);
+            app.set_status("Bookmark this directory as: (press a key)".to_string());
+        }
+        Action::JumpToMark => {
+            if app.bookmarks.is_empty() {
+                app.set_status("No bookmarks yet".to_string());
+            } else {
+                let names = app
+                    .bookmarks
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                app.mark_prompt = Some(MarkPrompt::Jump);
+                app.set_status(format!("Jump to bookmark: {}", names));
+            }
+        }
+        Action::Bookmarks => {
+            app.open_bookmarks();
+        }
+
+        // Frecency picker:
+        Action::Jumper => {
+            app.open_jumper();
+        }
+
         // Display help:
-        KeyCode::Char('?') => {
+        Action::Help => {
             app.mode = AppMode::Help;
         }
 
         // Clear messages:
-        KeyCode::Esc => {
+        Action::ClearMessages => {
             app.clear_messages();
         }
 
+        // Filter files:
+        Action::Filter => {
+            app.mode = AppMode::Input;
+            app.input_context = Some(InputContext::Filter);
+            app.input_buffer = app.filter.pattern.clone();
+            app.move_cursor_end();
+        }
+
+        // Sort order:
+        Action::CycleSort => {
+            app.set_sort(SortOrder {
+                key: app.sort.key.next(),
+                ..app.sort
+            });
+        }
+        Action::ReverseSort => {
+            app.set_sort(SortOrder {
+                reverse: !app.sort.reverse,
+                ..app.sort
+            });
+        }
+        Action::ToggleDirsFirst => {
+            app.set_sort(SortOrder {
+                dirs_first: !app.sort.dirs_first,
+                ..app.sort
+            });
+        }
+        Action::ToggleHidden => {
+            app.set_listing(ListingFilter {
+                show_hidden: !app.listing.show_hidden,
+                ..app.listing
+            });
+        }
+        Action::CycleIgnored => {
+            app.set_listing(ListingFilter {
+                ignore: app.listing.ignore.next(),
+                ..app.listing
+            });
+        }
+        Action::CycleLayout => {
+            app.set_layout(app.layout.next());
+        }
+        Action::NewTab => {
+            app.new_tab(app.current_path.clone());
+        }
+        Action::CloseTab => {
+            app.close_tab();
+        }
+        Action::NextTab => {
+            app.next_tab();
+        }
+        Action::PrevTab => {
+            app.prev_tab();
+        }
+        Action::Tab1
+        | Action::Tab2
+        | Action::Tab3
+        | Action::Tab4
+        | Action::Tab5
+        | Action::Tab6
+        | Action::Tab7
+        | Action::Tab8
+        | Action::Tab9 => {
+            if let Some(number) = action.tab_number() {
+                app.go_to_tab(number);
+            }
+        }
+        Action::SwitchPane => {
+            app.switch_pane();
+        }
+
+        // Tree view:
+        Action::ToggleTree => {
+            app.toggle_tree();
+        }
+        Action::ToggleExpand => {
+            app.toggle_expanded();
+        }
+
         // Multi-select mode:
-        KeyCode::Char('s') => {
+        Action::SelectMode => {
             app.mode = AppMode::MultiSelect;
             app.clear_multi_selection();
         }
 
         // Create new file:
-        KeyCode::Char('n') => {
+        Action::NewFile => {
             app.mode = AppMode::Input;
             app.input_context = Some(InputContext::CreateFile);
             app.clear_input_buffer();
@@ -77,7 +225,7 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // Create new directory:
-        KeyCode::Char('N') => {
+        Action::NewDir => {
             app.mode = AppMode::Input;
             app.input_context = Some(InputContext::CreateDir);
             app.clear_input_buffer();
@@ -85,7 +233,7 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // View clipboard:
-        KeyCode::Char('C') => {
+        Action::Clipboard => {
             if !app.clipboard.items.is_empty() {
                 app.mode = AppMode::Clipboard;
                 app.clipboard_scroll_offset = 0;
@@ -95,17 +243,25 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // Quit:
-        KeyCode::Char('q') => {
-            app.should_exit = true;
+        Action::Quit => {
+            app.request_quit();
+        }
+
+        // Undo / redo:
+        Action::Undo => {
+            if let Err(e) = app.undo() {
+                app.set_error(format!("Undo failed: {}", e));
+            }
         }
-        // allow ctrl+C to exit application too:
-        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            app.should_exit = true;
+        Action::Redo => {
+            if let Err(e) = app.redo() {
+                app.set_error(format!("Redo failed: {}", e));
+            }
         }
 
         // Quick actions:
         // rename:
-        KeyCode::Char('r') => {
+        Action::Rename => {
             if let Some(selected) = app.file_list.selected() {
                 app.mode = AppMode::Input;
                 app.input_buffer.clear();
@@ -117,9 +273,13 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // delete:
-        KeyCode::Char('d') => {
+        Action::Delete => {
             if let Some(selected) = app.file_list.selected() {
                 let selected_path = selected.path.clone();
+                if app.deletes_current_dir(std::slice::from_ref(&selected_path)) {
+                    app.set_error(CANT_DELETE_CURRENT_DIR.to_string());
+                    return Ok(());
+                }
                 app.mode = AppMode::Confirm;
                 app.set_status(format!("Delete '{}'? (y/n)", selected.name));
                 // store delete command:
@@ -127,8 +287,54 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
             }
         }
 
+        // permanent delete (bypasses the trash):
+        Action::DeletePermanent => {
+            if let Some(selected) = app.file_list.selected() {
+                let selected_path = selected.path.clone();
+                if app.deletes_current_dir(std::slice::from_ref(&selected_path)) {
+                    app.set_error(CANT_DELETE_CURRENT_DIR.to_string());
+                    return Ok(());
+                }
+                app.mode = AppMode::Confirm;
+                app.set_status(format!(
+                    "Permanently delete '{}'? This cannot be undone (y/n)",
+                    selected.name
+                ));
+                app.active_command =
+                    Some(Box::new(DeleteCommand::new_permanent(vec![selected_path])));
+            }
+        }
+
+        // View trash:
+        Action::Trash => {
+            app.open_trash()?;
+        }
+
+        // View background jobs:
+        Action::Jobs => {
+            if !app.jobs.is_empty() {
+                app.mode = AppMode::Jobs;
+            } else {
+                app.set_status("No jobs running".to_string());
+            }
+        }
+
         // cut:
-        KeyCode::Char('x') => {
+        // with two panes, copy/move straight into the other one:
+        Action::Cut | Action::Copy if app.layout == ViewLayout::Dual => {
+            if let Some(selected) = app.file_list.selected()
+                && selected.name != ".."
+            {
+                let operation = if action == Action::Cut {
+                    ClipboardOperation::Cut
+                } else {
+                    ClipboardOperation::Copy
+                };
+                let sources = vec![selected.path.clone()];
+                app.transfer_to_other_pane(operation, sources, CopyOptions::default());
+            }
+        }
+        Action::Cut => {
             if let Some(selected) = app.file_list.selected() {
                 app.clipboard.items = vec![selected.path.clone()];
                 app.clipboard.operation = ClipboardOperation::Cut;
@@ -137,7 +343,7 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // copy:
-        KeyCode::Char('c') => {
+        Action::Copy => {
             if let Some(selected) = app.file_list.selected() {
                 app.clipboard.items = vec![selected.path.clone()];
                 app.clipboard.operation = ClipboardOperation::Copy;
@@ -146,37 +352,12 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // paste:
-        KeyCode::Char('v') => {
-            if !app.clipboard.items.is_empty() {
-                let dest_path = app.current_path.clone();
-                let clipboard_items = app.clipboard.items.clone();
-                let op = app.clipboard.operation.clone();
-
-                // handle clipboard operation types:
-                match op {
-                    // copys:
-                    ClipboardOperation::Copy => {
-                        let mut copy_command = CopyCommand::new(clipboard_items, dest_path);
-                        if let Err(e) = copy_command.execute(app) {
-                            app.set_error(format!("Copy failed: {}", e));
-                        }
-                    }
-
-                    // cut:
-                    ClipboardOperation::Cut => {
-                        let mut move_command = MoveCommand::new(clipboard_items, dest_path);
-                        if let Err(e) = move_command.execute(app) {
-                            app.set_error(format!("Move failed: {}", e));
-                        }
-
-                        // clear clipboard after pasting a cut:
-                        app.clipboard.items.clear();
-                        app.clipboard.operation = ClipboardOperation::None;
-                    }
-
-                    _ => {}
-                }
-            }
+        Action::Paste if !app.clipboard.items.is_empty() => {
+            app.paste(CopyOptions::default());
+        }
+        // paste keeping ownership and extended attributes too (like `cp -a`):
+        Action::PasteArchive if !app.clipboard.items.is_empty() => {
+            app.paste(CopyOptions::archive());
         }
         _ => {}
     }
@@ -184,20 +365,19 @@ pub fn handle_key_event_normal(key: KeyEvent, app: &mut App) -> Result<()> {
     Ok(())
 }
 
-pub fn handle_key_event_help(key: KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
-        // Quit:
-        KeyCode::Char('q') | KeyCode::Esc => {
+pub fn handle_action_help(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        // Close:
+        Action::Close => {
             app.mode = AppMode::Normal;
         }
-        // allow ctrl+C to exit application too:
-        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            app.should_exit = true;
+        Action::Quit => {
+            app.request_quit();
         }
 
         // Scroll down:
-        KeyCode::Down => {
-            let content_length = HELP_DIALOG.len();
+        Action::MoveDown => {
+            let content_length = help_lines(&app.keymap).len();
             if let Ok((_, terminal_height)) = crossterm::terminal::size() {
                 let modal_height = (terminal_height as f32 * 0.8) as usize;
                 let viewport_height = modal_height.saturating_sub(2); // account for borders
@@ -206,7 +386,7 @@ pub fn handle_key_event_help(key: KeyEvent, app: &mut App) -> Result<()> {
         }
 
         // Scroll up:
-        KeyCode::Up => {
+        Action::MoveUp => {
             app.scroll_help_up();
         }
 
@@ -216,20 +396,38 @@ pub fn handle_key_event_help(key: KeyEvent, app: &mut App) -> Result<()> {
 }
 
 pub fn handle_key_event_input(key: KeyEvent, app: &mut App) -> Result<()> {
+    // the filter bar is applied incrementally, so has its own handling:
+    if app.input_context == Some(InputContext::Filter) {
+        return handle_key_event_filter(key, app);
+    }
+
+    // Up/Down and Ctrl+R bring back earlier names:
+    if handle_history_key(key, app, true) {
+        return Ok(());
+    }
+
     match key.code {
-        KeyCode::Enter => {
+        KeyCode::Enter
             // handle execution based on input context
-            if !app.input_buffer.is_empty() {
+            if !app.input_buffer.is_empty() => {
                 let input_text = app.input_buffer.clone();
+                app.record_input(&input_text);
                 match app.input_context {
                     // rename:
                     Some(InputContext::Rename) => {
-                        if let Some(selected) = app.file_list.selected() {
-                            let mut rename_command =
-                                RenameCommand::new(selected.path.clone(), input_text);
-                            if let Err(e) = rename_command.execute(app) {
+                        // renaming a `:find` match rather than the item under the cursor:
+                        let source = if app.return_mode == AppMode::Find {
+                            app.selected_match().map(|found| found.path.clone())
+                        } else {
+                            app.file_list.selected().map(|item| item.path.clone())
+                        };
+                        if let Some(source) = source {
+                            let renamed = source.with_file_name(&input_text);
+                            let rename_command = RenameCommand::new(source.clone(), input_text);
+                            if let Err(e) = app.run_command(Box::new(rename_command)) {
                                 app.set_error(format!("Rename failed: {}", e));
                             } else {
+                                app.rename_match(&source, renamed);
                                 if let Err(e) = app.refresh_file_list() {
                                     app.set_error(format!("Failed to refresh after rename: {}", e));
                                 }
@@ -239,32 +437,31 @@ pub fn handle_key_event_input(key: KeyEvent, app: &mut App) -> Result<()> {
 
                     // create file:
                     Some(InputContext::CreateFile) => {
-                        let new_file_path = app.current_path.join(&input_text);
-                        let mut create_command = CreateFileCommand::new(new_file_path);
-                        if let Err(e) = create_command.execute(app) {
+                        let new_file_path = app.target_dir().join(&input_text);
+                        let create_command = CreateFileCommand::new(new_file_path);
+                        if let Err(e) = app.run_command(Box::new(create_command)) {
                             app.set_error(format!("File creation failed: {}", e));
                         }
                     }
 
                     // create directory:
                     Some(InputContext::CreateDir) => {
-                        let new_dir_path = app.current_path.join(&input_text);
-                        let mut create_command = CreateDirCommand::new(new_dir_path);
-                        if let Err(e) = create_command.execute(app) {
+                        let new_dir_path = app.target_dir().join(&input_text);
+                        let create_command = CreateDirCommand::new(new_dir_path);
+                        if let Err(e) = app.run_command(Box::new(create_command)) {
                             app.set_error(format!("Directory creation failed: {}", e));
                         }
                     }
                     //todo: implement the rest of the commands:
                     _ => {}
                 }
-                app.mode = AppMode::Normal;
+                app.mode = std::mem::replace(&mut app.return_mode, AppMode::Normal);
                 app.input_context = None;
                 app.clear_input_buffer();
             }
-        }
 
         KeyCode::Esc => {
-            app.mode = AppMode::Normal;
+            app.mode = std::mem::replace(&mut app.return_mode, AppMode::Normal);
             app.input_context = None;
             app.clear_input_buffer();
             app.clear_messages();
@@ -277,10 +474,10 @@ pub fn handle_key_event_input(key: KeyEvent, app: &mut App) -> Result<()> {
         KeyCode::Right => {
             app.move_cursor_right();
         }
-        KeyCode::Up | KeyCode::Home => {
+        KeyCode::Home => {
             app.move_cursor_home();
         }
-        KeyCode::Down | KeyCode::End => {
+        KeyCode::End => {
             app.move_cursor_end();
         }
 
@@ -302,29 +499,156 @@ pub fn handle_key_event_input(key: KeyEvent, app: &mut App) -> Result<()> {
     Ok(())
 }
 
-pub fn handle_key_event_confirm(key: KeyEvent, app: &mut App) -> Result<()> {
+/// History keys shared by the text inputs: Up/Down (when `arrows`) or Ctrl+P/Ctrl+N step through
+/// earlier entries starting with what's typed, and Ctrl+R searches them. Returns whether the key was used
+fn handle_history_key(key: KeyEvent, app: &mut App, arrows: bool) -> bool {
+    if app.history_search.is_some() {
+        return handle_key_event_history_search(key, app);
+    }
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Up if arrows => app.history_prev(),
+        KeyCode::Char('p') if ctrl => app.history_prev(),
+        KeyCode::Down if arrows => app.history_next(),
+        KeyCode::Char('n') if ctrl => app.history_next(),
+        KeyCode::Char('r') if ctrl => app.start_history_search(),
+
+        // anything else starts browsing afresh from whatever is typed next:
+        _ => {
+            app.history_browse = None;
+            return false;
+        }
+    }
+    true
+}
+
+/// Keys during a Ctrl+R search. Other keys accept the match and then act as usual, so Enter runs it
+fn handle_key_event_history_search(key: KeyEvent, app: &mut App) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
     match key.code {
+        // next older match:
+        KeyCode::Char('r') if ctrl => app.update_history_search(true),
+
+        // give up, leaving the input as it was:
+        KeyCode::Esc => app.history_search = None,
+        KeyCode::Char('g') if ctrl => app.history_search = None,
+
+        // edit the query:
+        KeyCode::Backspace => {
+            if let Some(search) = &mut app.history_search {
+                search.query.pop();
+            }
+            app.update_history_search(false);
+        }
+        KeyCode::Char(c) if !ctrl => {
+            if let Some(search) = &mut app.history_search {
+                search.query.push(c);
+            }
+            app.update_history_search(false);
+        }
+
+        _ => {
+            app.accept_history_search();
+            return false;
+        }
+    }
+    true
+}
+
+pub fn handle_key_event_filter(key: KeyEvent, app: &mut App) -> Result<()> {
+    // Up/Down move through the list here, so only Ctrl+P/Ctrl+N/Ctrl+R bring back earlier filters:
+    if handle_history_key(key, app, false) {
+        return Ok(());
+    }
+
+    match key.code {
+        // keep the filter and return to the (filtered) list:
+        KeyCode::Enter => {
+            let pattern = app.input_buffer.clone();
+            app.record_input(&pattern);
+            app.mode = AppMode::Normal;
+            app.input_context = None;
+            app.clear_input_buffer();
+        }
+
+        // drop the filter:
+        KeyCode::Esc => {
+            app.mode = AppMode::Normal;
+            app.input_context = None;
+            app.clear_input_buffer();
+            app.clear_filter();
+        }
+
+        // cycle substring / glob / fuzzy matching:
+        KeyCode::Tab => {
+            app.filter.mode = app.filter.mode.next();
+            app.apply_filter();
+        }
+
+        // move through the filtered list while typing:
+        KeyCode::Down => {
+            app.file_list.next();
+        }
+        KeyCode::Up => {
+            app.file_list.prev();
+        }
+
+        // Cursor movement:
+        KeyCode::Left => {
+            app.move_cursor_left();
+        }
+        KeyCode::Right => {
+            app.move_cursor_right();
+        }
+        KeyCode::Home => {
+            app.move_cursor_home();
+        }
+        KeyCode::End => {
+            app.move_cursor_end();
+        }
+
+        // Text editing:
+        KeyCode::Backspace => {
+            app.delete_char_before_cursor();
+            app.set_filter(app.input_buffer.clone());
+        }
+        KeyCode::Delete => {
+            app.delete_char_at_cursor();
+            app.set_filter(app.input_buffer.clone());
+        }
+        // Text input:
+        KeyCode::Char(c) => {
+            app.insert_char_at_cursor(c);
+            app.set_filter(app.input_buffer.clone());
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_action_confirm(action: Action, app: &mut App) -> Result<()> {
+    match action {
         // confirm yes:
-        KeyCode::Char('y') | KeyCode::Char('Y') => {
+        Action::Confirm => {
             // Execute the stored action:
-            if let Some(mut command) = app.active_command.take() {
-                if let Err(e) = command.execute(app) {
-                    app.set_error(format!("Command failed: {}", e));
-                }
-                app.set_status(format!("Executed Action: {}", command.description()));
-                app.mode = AppMode::Normal;
+            if let Some(command) = app.active_command.take() {
+                app.spawn_job(command);
+                app.mode = std::mem::replace(&mut app.return_mode, AppMode::Normal);
+            } else if app.confirm_quit {
+                app.confirm_quit = false;
+                app.should_exit = true;
             }
         }
 
         // confirm no:
-        KeyCode::Char('q')
-        | KeyCode::Char('Q')
-        | KeyCode::Char('n')
-        | KeyCode::Char('N')
-        | KeyCode::Esc => {
+        Action::Cancel => {
             app.set_status("Cancelled Action".to_string());
             app.active_command = None; // clear stored command
-            app.mode = AppMode::Normal;
+            app.confirm_quit = false;
+            app.mode = std::mem::replace(&mut app.return_mode, AppMode::Normal);
         }
 
         _ => {}
@@ -333,53 +657,111 @@ pub fn handle_key_event_confirm(key: KeyEvent, app: &mut App) -> Result<()> {
     Ok(())
 }
 
-pub fn handle_key_event_multi_select(key: KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
+pub fn handle_action_multi_select(action: Action, app: &mut App) -> Result<()> {
+    match action {
         // Movement up/down
-        KeyCode::Down => {
+        Action::MoveDown => {
             app.file_list.next();
         }
-        KeyCode::Up => {
+        Action::MoveUp => {
             app.file_list.prev();
         }
 
         // Navigating into/out of directories:
-        KeyCode::Left => {
+        Action::GoUp if app.can_collapse() => {
+            app.collapse_selected();
+        }
+        Action::GoUp => {
             app.clear_multi_selection();
             app.navigate_up()?;
         }
-        KeyCode::Right | KeyCode::Enter => {
+        Action::Open => {
             app.clear_multi_selection();
             app.enter_selected()?;
         }
 
         // Toggle selection for current item:
-        KeyCode::Char(' ') => {
-            if let Some(selected) = app.file_list.state.selected() {
-                if let Some(item) = app.file_list.items.get(selected) {
-                    if item.name != ".." {
-                        app.toggle_selection();
-                    }
-                }
+        Action::ToggleSelection => {
+            if let Some(selected) = app.file_list.state.selected()
+                && let Some(item) = app.file_list.items.get(selected)
+                && item.name != ".."
+            {
+                app.toggle_selection();
             }
         }
 
         // Delete selection:
-        KeyCode::Char('d') => {
+        Action::Delete => {
             let targets = app
                 .selected_items()
                 .iter()
                 .map(|f| f.path.clone())
                 .collect::<Vec<_>>();
-            if !targets.is_empty() {
+            if app.deletes_current_dir(&targets) {
+                app.set_error(CANT_DELETE_CURRENT_DIR.to_string());
+            } else if !targets.is_empty() {
                 app.mode = AppMode::Confirm;
                 app.set_status(format!("Delete {} selected item(s)? (y/n)", targets.len()));
                 app.active_command = Some(Box::new(DeleteCommand::new(targets)));
             }
         }
 
+        // Permanently delete selection:
+        Action::DeletePermanent => {
+            let targets = app
+                .selected_items()
+                .iter()
+                .map(|f| f.path.clone())
+                .collect::<Vec<_>>();
+            if app.deletes_current_dir(&targets) {
+                app.set_error(CANT_DELETE_CURRENT_DIR.to_string());
+            } else if !targets.is_empty() {
+                app.mode = AppMode::Confirm;
+                app.set_status(format!(
+                    "Permanently delete {} selected item(s)? This cannot be undone (y/n)",
+                    targets.len()
+                ));
+                app.active_command = Some(Box::new(DeleteCommand::new_permanent(targets)));
+            }
+        }
+
         // Copy selection:
-        KeyCode::Char('c') => {
+        // with two panes, copy/move straight into the other one:
+        Action::Copy | Action::Cut if app.layout == ViewLayout::Dual => {
+            let targets = app
+                .selected_items()
+                .iter()
+                .map(|f| f.path.clone())
+                .collect::<Vec<_>>();
+            let operation = if action == Action::Cut {
+                ClipboardOperation::Cut
+            } else {
+                ClipboardOperation::Copy
+            };
+            if !targets.is_empty() {
+                app.clear_multi_selection();
+                app.mode = AppMode::Normal;
+                app.transfer_to_other_pane(operation, targets, CopyOptions::default());
+            }
+        }
+        Action::Tab1
+        | Action::Tab2
+        | Action::Tab3
+        | Action::Tab4
+        | Action::Tab5
+        | Action::Tab6
+        | Action::Tab7
+        | Action::Tab8
+        | Action::Tab9 => {
+            if let Some(number) = action.tab_number() {
+                app.go_to_tab(number);
+            }
+        }
+        Action::SwitchPane => {
+            app.switch_pane();
+        }
+
+        Action::Copy => {
             let targets = app
                 .selected_items()
                 .iter()
@@ -393,7 +775,7 @@ pub fn handle_key_event_multi_select(key: KeyEvent, app: &mut App) -> Result<()>
         }
 
         // Cut selection:
-        KeyCode::Char('x') => {
+        Action::Cut => {
             let targets = app
                 .selected_items()
                 .iter()
@@ -407,7 +789,7 @@ pub fn handle_key_event_multi_select(key: KeyEvent, app: &mut App) -> Result<()>
         }
 
         // Exit selection mode:
-        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('s') => {
+        Action::Close => {
             app.mode = AppMode::Normal;
             app.clear_multi_selection();
         }
@@ -418,13 +800,13 @@ pub fn handle_key_event_multi_select(key: KeyEvent, app: &mut App) -> Result<()>
     Ok(())
 }
 
-pub fn handle_key_event_clipboard(key: KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
-        KeyCode::Esc | KeyCode::Char('q') => {
+pub fn handle_action_clipboard(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        Action::Close => {
             app.mode = AppMode::Normal; // todo: allow viewing clipboard from select mode as well
         }
 
-        KeyCode::Down => {
+        Action::MoveDown => {
             let content_length = app.clipboard.items.len() + 3; // add lines for header
 
             if let Ok((_, terminal_height)) = crossterm::terminal::size() {
@@ -434,7 +816,7 @@ pub fn handle_key_event_clipboard(key: KeyEvent, app: &mut App) -> Result<()> {
             }
         }
 
-        KeyCode::Up => {
+        Action::MoveUp => {
             app.scroll_clipboard_up();
         }
 
@@ -444,17 +826,348 @@ pub fn handle_key_event_clipboard(key: KeyEvent, app: &mut App) -> Result<()> {
     Ok(())
 }
 
+pub fn handle_action_trash(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        Action::Close => {
+            app.mode = AppMode::Normal;
+        }
+
+        Action::MoveDown => {
+            app.trash_list.next();
+        }
+        Action::MoveUp => {
+            app.trash_list.prev();
+        }
+
+        // Restore selected item:
+        Action::Restore => {
+            if let Some(entry) = app.trash_list.selected().cloned() {
+                let restore_command = RestoreCommand::new(vec![entry]);
+                if let Err(e) = app.run_command(Box::new(restore_command)) {
+                    app.set_error(format!("Restore failed: {}", e));
+                }
+            }
+        }
+
+        // Permanently delete selected item:
+        Action::Purge => {
+            if let Some(entry) = app.trash_list.selected().cloned() {
+                app.set_status(format!(
+                    "Permanently delete '{}'? This cannot be undone (y/n)",
+                    entry.name
+                ));
+                app.active_command = Some(Box::new(PurgeTrashCommand::new(vec![entry])));
+                app.return_mode = AppMode::Trash;
+                app.mode = AppMode::Confirm;
+            }
+        }
+
+        // Empty the trash:
+        Action::EmptyTrash if !app.trash_list.items.is_empty() => {
+            app.set_status(format!(
+                "Permanently delete all {} item(s) in the trash? (y/n)",
+                app.trash_list.items.len()
+            ));
+            app.active_command = Some(Box::new(PurgeTrashCommand::empty()));
+            app.return_mode = AppMode::Trash;
+            app.mode = AppMode::Confirm;
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_action_jobs(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        Action::Close => {
+            app.mode = AppMode::Normal;
+        }
+
+        Action::MoveDown => {
+            app.jobs.select_next();
+        }
+        Action::MoveUp => {
+            app.jobs.select_prev();
+        }
+
+        // Pause / resume selected job:
+        Action::PauseJob => {
+            app.jobs.toggle_pause_selected();
+        }
+
+        // Cancel selected job:
+        Action::CancelJob => {
+            app.jobs.cancel_selected();
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_action_conflict(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        Action::Overwrite => app.resolve_conflict(ConflictResolution::Overwrite),
+        Action::Skip => app.resolve_conflict(ConflictResolution::Skip),
+        Action::KeepBoth => app.resolve_conflict(ConflictResolution::Rename),
+        // only directories onto directories can be merged:
+        Action::Merge => {
+            let can_merge = app
+                .pending_paste
+                .as_ref()
+                .and_then(|plan| plan.current_conflict())
+                .is_some_and(|conflict| conflict.can_merge());
+            if can_merge {
+                app.resolve_conflict(ConflictResolution::Merge);
+            }
+        }
+
+        // Toggle applying the next choice to every remaining conflict:
+        Action::ApplyToAll => {
+            if let Some(plan) = app.pending_paste.as_mut() {
+                plan.apply_to_all = !plan.apply_to_all;
+            }
+        }
+
+        Action::Cancel => {
+            app.cancel_paste();
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_action_jump_list(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        Action::Close => {
+            app.mode = AppMode::Normal;
+        }
+
+        Action::MoveDown => {
+            app.jump_list.next();
+        }
+        Action::MoveUp => {
+            app.jump_list.prev();
+        }
+
+        // Go to the selected directory:
+        Action::Open => {
+            app.jump_to_selected()?;
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_action_find(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        // the first Esc stops a search that's still running, the next one closes the results:
+        Action::Close if app.search.as_ref().is_some_and(|search| search.running) => {
+            app.cancel_search();
+        }
+        Action::Close => {
+            app.mode = AppMode::Normal;
+        }
+
+        Action::MoveDown => {
+            if let Some(search) = &mut app.search {
+                search.results.next();
+            }
+        }
+        Action::MoveUp => {
+            if let Some(search) = &mut app.search {
+                search.results.prev();
+            }
+        }
+
+        // Go to the directory the match is in:
+        Action::Open => {
+            app.jump_to_match()?;
+        }
+
+        // File operations on the match itself:
+        Action::Cut | Action::Copy => {
+            if let Some(path) = app.selected_match().map(|found| found.path.clone()) {
+                app.clipboard.items = vec![path];
+                if action == Action::Cut {
+                    app.clipboard.operation = ClipboardOperation::Cut;
+                    app.set_status("Cut to clipboard".to_string());
+                } else {
+                    app.clipboard.operation = ClipboardOperation::Copy;
+                    app.set_status("Copied to clipboard".to_string());
+                }
+            }
+        }
+        Action::Rename => {
+            if let Some(name) = app
+                .selected_match()
+                .and_then(|found| found.path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+            {
+                app.mode = AppMode::Input;
+                app.return_mode = AppMode::Find;
+                app.cursor_position = name.len(); // position cursor at the end
+                app.input_buffer = name;
+                app.input_context = Some(InputContext::Rename);
+                app.set_status("Rename to: ".to_string());
+            }
+        }
+        Action::Delete | Action::DeletePermanent => {
+            if let Some(path) = app.selected_match().map(|found| found.path.clone()) {
+                if action == Action::Delete {
+                    app.set_status(format!("Delete '{}'? (y/n)", path.display()));
+                    app.active_command = Some(Box::new(DeleteCommand::new_single(path)));
+                } else {
+                    app.set_status(format!(
+                        "Permanently delete '{}'? This cannot be undone (y/n)",
+                        path.display()
+                    ));
+                    app.active_command = Some(Box::new(DeleteCommand::new_permanent(vec![path])));
+                }
+                app.return_mode = AppMode::Find;
+                app.mode = AppMode::Confirm;
+            }
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_key_event_mark(prompt: MarkPrompt, key: KeyEvent, app: &mut App) -> Result<()> {
+    let KeyCode::Char(c) = key.code else {
+        // any other key (like Esc) cancels:
+        app.clear_messages();
+        return Ok(());
+    };
+    match prompt {
+        MarkPrompt::Set => app.add_bookmark(&c.to_string(), app.current_path.clone()),
+        MarkPrompt::Jump => app.jump_to_bookmark(&c.to_string())?,
+    }
+    Ok(())
+}
+
+pub fn handle_action_bookmarks(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        Action::Close => {
+            app.mode = AppMode::Normal;
+        }
+
+        Action::MoveDown => {
+            app.bookmark_list.next();
+        }
+        Action::MoveUp => {
+            app.bookmark_list.prev();
+        }
+
+        // Go to the selected bookmark:
+        Action::Open => {
+            if let Some((name, _)) = app.bookmark_list.selected().cloned() {
+                app.mode = AppMode::Normal;
+                app.jump_to_bookmark(&name)?;
+            }
+        }
+
+        Action::RemoveBookmark => {
+            if let Some((name, _)) = app.bookmark_list.selected().cloned() {
+                app.remove_bookmark(&name);
+            }
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_key_event_jumper(key: KeyEvent, app: &mut App) -> Result<()> {
+    match key.code {
+        KeyCode::Enter => {
+            app.jump_to_jumper_selection()?;
+        }
+
+        // Close the picker:
+        KeyCode::Esc => {
+            app.mode = AppMode::Normal;
+            app.clear_input_buffer();
+        }
+
+        // Pick a candidate:
+        KeyCode::Down => {
+            app.jumper_list.next();
+        }
+        KeyCode::Up => {
+            app.jumper_list.prev();
+        }
+
+        // Cursor movement:
+        KeyCode::Left => {
+            app.move_cursor_left();
+        }
+        KeyCode::Right => {
+            app.move_cursor_right();
+        }
+        KeyCode::Home => {
+            app.move_cursor_home();
+        }
+        KeyCode::End => {
+            app.move_cursor_end();
+        }
+
+        // Editing the query narrows the candidates:
+        KeyCode::Backspace => {
+            app.delete_char_before_cursor();
+            app.apply_jumper_filter();
+        }
+        KeyCode::Delete => {
+            app.delete_char_at_cursor();
+            app.apply_jumper_filter();
+        }
+        KeyCode::Char(c) => {
+            app.insert_char_at_cursor(c);
+            app.apply_jumper_filter();
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
 pub fn handle_key_event_command(key: KeyEvent, app: &mut App) -> Result<()> {
+    // any other key ends tab completion, and the last error goes once the line is touched:
+    if !matches!(key.code, KeyCode::Tab | KeyCode::BackTab) {
+        app.completion = None;
+    }
+    app.command_error = None;
+
+    // Up/Down and Ctrl+R bring back earlier commands:
+    if handle_history_key(key, app, true) {
+        return Ok(());
+    }
+
     match key.code {
         KeyCode::Enter => {
-            let command = app.input_buffer.chars().collect::<String>();
-            if command.trim() == "help" {
-                app.mode = AppMode::Help;
-                app.clear_input_buffer();
-                return Ok(());
-            }
+            let command = app.input_buffer.clone();
             app.clear_input_buffer();
             app.execute_command(&command)?;
+
+            // a mistake in the command keeps the command line open to fix it:
+            if app.command_error.is_some() {
+                app.mode = AppMode::Command;
+                app.input_buffer = command;
+                app.move_cursor_end();
+            } else {
+                app.record_input(&command);
+            }
         }
 
         // Exit the command view:
@@ -463,6 +1176,14 @@ pub fn handle_key_event_command(key: KeyEvent, app: &mut App) -> Result<()> {
             app.clear_input_buffer();
         }
 
+        // Complete commands, paths and arguments:
+        KeyCode::Tab => {
+            app.complete_command(false);
+        }
+        KeyCode::BackTab => {
+            app.complete_command(true);
+        }
+
         // Cursor movement:
         KeyCode::Left => {
             app.move_cursor_left();
@@ -470,10 +1191,10 @@ pub fn handle_key_event_command(key: KeyEvent, app: &mut App) -> Result<()> {
         KeyCode::Right => {
             app.move_cursor_right();
         }
-        KeyCode::Up | KeyCode::Home => {
+        KeyCode::Home => {
             app.move_cursor_home();
         }
-        KeyCode::Down | KeyCode::End => {
+        KeyCode::End => {
             app.move_cursor_end();
         }
 
@@ -503,10 +1224,10 @@ pub fn handle_mouse_event(mouse: MouseEvent, app: &mut App) -> Result<()> {
     let now = std::time::Instant::now();
     match mouse.kind {
         MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
-            if let Some(last) = app.last_scroll_time {
-                if now.duration_since(last).as_millis() < debounce_ms {
-                    return Ok(());
-                }
+            if let Some(last) = app.last_scroll_time
+                && now.duration_since(last).as_millis() < debounce_ms
+            {
+                return Ok(());
             }
             app.last_scroll_time = Some(now);
         }
@@ -518,7 +1239,7 @@ pub fn handle_mouse_event(mouse: MouseEvent, app: &mut App) -> Result<()> {
         MouseEventKind::ScrollDown => {
             match app.mode {
                 AppMode::Help => {
-                    let content_length = HELP_DIALOG.len();
+                    let content_length = help_lines(&app.keymap).len();
                     if let Ok((_, terminal_height)) = crossterm::terminal::size() {
                         let modal_height = (terminal_height as f32 * 0.8) as usize;
                         let viewport_height = modal_height.saturating_sub(2); // account for borders
@@ -535,9 +1256,16 @@ pub fn handle_mouse_event(mouse: MouseEvent, app: &mut App) -> Result<()> {
                     }
                 }
 
-                _ => {
+                AppMode::Trash => {
+                    app.trash_list.prev();
+                }
+
+                AppMode::Normal | AppMode::MultiSelect => {
                     app.file_list.prev();
                 }
+
+                // anything else has a prompt or modal over the list:
+                _ => {}
             }
         }
 
@@ -551,23 +1279,26 @@ pub fn handle_mouse_event(mouse: MouseEvent, app: &mut App) -> Result<()> {
                     app.scroll_clipboard_up();
                 }
 
-                _ => {
+                AppMode::Trash => {
+                    app.trash_list.next();
+                }
+
+                AppMode::Normal | AppMode::MultiSelect => {
                     app.file_list.next();
                 }
+
+                _ => {}
             }
             return Ok(());
         }
 
         MouseEventKind::Down(crossterm::event::MouseButton::Left) => {
-            let y = mouse.row.saturating_sub(4) as usize;
-            if y < app.file_list.filtered_items.len() {
-                let idx = app.file_list.filtered_items[y];
-
+            if let Some(idx) = clicked_item(&mouse, app) {
                 // Double click detection:
                 let double_click = app.last_click_index == Some(idx)
-                    && app.last_click_time.map_or(false, |t| {
-                        now.duration_since(t).as_millis() < DOUBLE_CLICK_DURATION
-                    });
+                    && app
+                        .last_click_time
+                        .is_some_and(|t| now.duration_since(t).as_millis() < DOUBLE_CLICK_DURATION);
 
                 if double_click {
                     if let Some(item) = app.file_list.items.get(idx) {
@@ -589,20 +1320,17 @@ pub fn handle_mouse_event(mouse: MouseEvent, app: &mut App) -> Result<()> {
         }
 
         MouseEventKind::Up(crossterm::event::MouseButton::Left) => {
-            let y = mouse.row.saturating_sub(4) as usize;
-            if y >= app.file_list.filtered_items.len() {
+            let Some(idx) = clicked_item(&mouse, app) else {
                 return Ok(());
-            }
-
-            let idx = app.file_list.filtered_items[y];
+            };
             if app.file_list.state.selected() != Some(idx) || app.mode != AppMode::MultiSelect {
                 return Ok(());
             }
 
-            if let Some(item) = app.file_list.items.get(idx) {
-                if item.name != ".." {
-                    app.toggle_selection();
-                }
+            if let Some(item) = app.file_list.items.get(idx)
+                && item.name != ".."
+            {
+                app.toggle_selection();
             }
         }
 
@@ -611,3 +1339,13 @@ pub fn handle_mouse_event(mouse: MouseEvent, app: &mut App) -> Result<()> {
 
     Ok(())
 }
+
+/// The file list item under a mouse click, using where the list was last drawn. Clicks only
+/// reach the list while nothing (like a prompt or modal) is drawn over it
+fn clicked_item(mouse: &MouseEvent, app: &App) -> Option<usize> {
+    if !matches!(app.mode, AppMode::Normal | AppMode::MultiSelect) {
+        return None;
+    }
+    let position = app.list_area.position_at(mouse.column, mouse.row)?;
+    app.file_list.filtered_items.get(position).copied()
+}
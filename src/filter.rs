@@ -0,0 +1,189 @@
+/// How the filter pattern is matched against file names
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FilterMode {
+    #[default]
+    Substring, // name contains the pattern
+    Glob,  // name matches a `*`/`?`/`[...]` pattern
+    Fuzzy, // pattern characters appear in order, best matches first
+}
+
+impl FilterMode {
+    pub fn next(self) -> Self {
+        match self {
+            FilterMode::Substring => FilterMode::Glob,
+            FilterMode::Glob => FilterMode::Fuzzy,
+            FilterMode::Fuzzy => FilterMode::Substring,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "substring" => Some(FilterMode::Substring),
+            "glob" => Some(FilterMode::Glob),
+            "fuzzy" => Some(FilterMode::Fuzzy),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterMode::Substring => "substring",
+            FilterMode::Glob => "glob",
+            FilterMode::Fuzzy => "fuzzy",
+        }
+    }
+}
+
+/// Result of matching a name against a filter
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterMatch {
+    pub score: i64, // higher is a better match (only meaningful for fuzzy matching)
+    pub indices: Vec<usize>, // char indices of the matched characters, for highlighting
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub pattern: String,
+    pub mode: FilterMode,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    /// Match `name` against the filter (case-insensitively). An empty filter matches everything
+    pub fn matches(&self, name: &str) -> Option<FilterMatch> {
+        if self.is_empty() {
+            return Some(FilterMatch {
+                score: 0,
+                indices: Vec::new(),
+            });
+        }
+
+        let pattern = fold_chars(&self.pattern);
+        let name = fold_chars(name);
+        match self.mode {
+            FilterMode::Substring => substring_match(&pattern, &name),
+            FilterMode::Glob => {
+                let mut indices = Vec::new();
+                glob_match(&pattern, &name, 0, &mut indices)
+                    .then_some(FilterMatch { score: 0, indices })
+            }
+            FilterMode::Fuzzy => fuzzy_match(&pattern, &name),
+        }
+    }
+}
+
+fn fold_chars(s: &str) -> Vec<char> {
+    s.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+fn substring_match(pattern: &[char], name: &[char]) -> Option<FilterMatch> {
+    let start = name
+        .windows(pattern.len())
+        .position(|window| window == pattern)?;
+    Some(FilterMatch {
+        score: -(start as i64),
+        indices: (start..start + pattern.len()).collect(),
+    })
+}
+
+/// Recursive glob matcher. Records the positions matched by literal pattern characters
+fn glob_match(pattern: &[char], name: &[char], offset: usize, indices: &mut Vec<usize>) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| {
+            let checkpoint = indices.len();
+            if glob_match(&pattern[1..], &name[skip..], offset + skip, indices) {
+                true
+            } else {
+                indices.truncate(checkpoint);
+                false
+            }
+        }),
+        Some('?') => !name.is_empty() && glob_match(&pattern[1..], &name[1..], offset + 1, indices),
+        Some('[') => {
+            let Some(end) = pattern
+                .iter()
+                .skip(1)
+                .position(|&c| c == ']')
+                .map(|i| i + 1)
+            else {
+                // unterminated class, treat '[' literally:
+                return literal_match(pattern, name, offset, indices);
+            };
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            let (negated, class) = match pattern[1..end].split_first() {
+                Some(('!' | '^', rest)) => (true, rest),
+                _ => (false, &pattern[1..end]),
+            };
+            if char_class_contains(class, c) != negated {
+                glob_match(&pattern[end + 1..], &name[1..], offset + 1, indices)
+            } else {
+                false
+            }
+        }
+        Some(_) => literal_match(pattern, name, offset, indices),
+    }
+}
+
+fn literal_match(pattern: &[char], name: &[char], offset: usize, indices: &mut Vec<usize>) -> bool {
+    if name.first() != pattern.first() {
+        return false;
+    }
+    indices.push(offset);
+    if glob_match(&pattern[1..], &name[1..], offset + 1, indices) {
+        true
+    } else {
+        indices.pop();
+        false
+    }
+}
+
+fn char_class_contains(class: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if class[i] <= c && c <= class[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+/// Subsequence matcher, rewarding consecutive matches and matches at the start of words
+fn fuzzy_match(pattern: &[char], name: &[char]) -> Option<FilterMatch> {
+    let mut indices = Vec::with_capacity(pattern.len());
+    let mut score = 0;
+    let mut name_idx = 0;
+
+    for &pc in pattern {
+        let found = name[name_idx..].iter().position(|&c| c == pc)? + name_idx;
+
+        score += 1;
+        if indices.last().is_some_and(|&last| last + 1 == found) {
+            score += 5; // consecutive characters
+        }
+        if found == 0 || matches!(name[found - 1], ' ' | '_' | '-' | '.' | '/') {
+            score += 3; // start of a word
+        }
+        score -= (found - name_idx) as i64; // gap since the previous match
+
+        indices.push(found);
+        name_idx = found + 1;
+    }
+
+    Some(FilterMatch { score, indices })
+}
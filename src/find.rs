@@ -0,0 +1,379 @@
+use anyhow::Result;
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    app::{App, AppMode, StatefulList},
+    filter::{Filter, FilterMode},
+    listing::{IgnoreMode, ListingFilter},
+};
+
+/// Keep only this many matches (the best ones, for fuzzy searches), so a pattern matching
+/// everything doesn't fill up memory
+const MAX_MATCHES: usize = 10_000;
+
+/// Stop walking after this many entries, so a search from `/` doesn't go on forever
+const MAX_WALKED: usize = 1_000_000;
+
+/// How often the worker hands over the matches it has found so far
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How `:find` matches names
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FindMode {
+    Glob,  // whole name matches a `*`/`?`/`[...]` pattern
+    Regex, // name contains a match for a regular expression
+    Fuzzy, // pattern characters appear in order, best matches first
+}
+
+impl FindMode {
+    /// The mode for a pattern given without a flag: a glob if it has wildcards, else fuzzy
+    pub fn guess(pattern: &str) -> Self {
+        if pattern.contains(['*', '?', '[']) {
+            FindMode::Glob
+        } else {
+            FindMode::Fuzzy
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FindMode::Glob => "glob",
+            FindMode::Regex => "regex",
+            FindMode::Fuzzy => "fuzzy",
+        }
+    }
+}
+
+/// A compiled `:find` pattern. Matching ignores case, as the filter does
+enum Pattern {
+    Name(Filter),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn new(mode: FindMode, pattern: &str) -> Result<Self, String> {
+        let filter = |mode| {
+            Pattern::Name(Filter {
+                pattern: pattern.to_string(),
+                mode,
+            })
+        };
+        match mode {
+            FindMode::Glob => Ok(filter(FilterMode::Glob)),
+            FindMode::Fuzzy => Ok(filter(FilterMode::Fuzzy)),
+            FindMode::Regex => RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(Pattern::Regex)
+                .map_err(|e| {
+                    // the full message points at the mistake over several lines, which the
+                    // command line can't show:
+                    let message = e.to_string();
+                    let reason = message.lines().last().unwrap_or_default();
+                    format!("Invalid regex: {}", reason.trim_start_matches("error: "))
+                }),
+        }
+    }
+
+    /// How well `name` matches (higher is better), or `None` if it doesn't
+    fn score(&self, name: &str) -> Option<i64> {
+        match self {
+            Pattern::Name(filter) => filter.matches(name).map(|m| m.score),
+            Pattern::Regex(regex) => regex.is_match(name).then_some(0),
+        }
+    }
+}
+
+/// An item found by `:find`
+#[derive(Debug, Clone)]
+pub struct FindMatch {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    score: i64,
+}
+
+/// A `:find` search, walking the tree below `root` on a worker thread and streaming the
+/// matches back
+pub struct Search {
+    pub root: PathBuf,
+    pub pattern: String, // as typed, for the results title
+    pub mode: FindMode,
+    pub results: StatefulList<FindMatch>,
+    pub running: bool,
+    cancelled: Arc<AtomicBool>,
+    walk_stopped: Arc<AtomicBool>, // the walk gave up at `MAX_WALKED` entries
+    matches: Receiver<Vec<FindMatch>>,
+}
+
+impl Search {
+    fn start(
+        root: PathBuf,
+        pattern: String,
+        mode: FindMode,
+        listing: &ListingFilter,
+    ) -> Result<Self, String> {
+        let compiled = Pattern::new(mode, &pattern)?;
+        let (sender, matches) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let walk_stopped = Arc::new(AtomicBool::new(false));
+        let ranked = mode == FindMode::Fuzzy;
+
+        // walk with the same dotfile and ignore file settings as the file list:
+        let hide_ignored = listing.ignore == IgnoreMode::Hide;
+        let mut walker = WalkBuilder::new(&root);
+        walker
+            .standard_filters(false)
+            .hidden(!listing.show_hidden)
+            .ignore(hide_ignored)
+            .git_ignore(hide_ignored)
+            .git_exclude(hide_ignored)
+            .parents(hide_ignored)
+            .require_git(false)
+            // git's own files are never what's being looked for:
+            .filter_entry(|entry| entry.file_name() != ".git");
+        let walk = walker.build();
+
+        let stop = cancelled.clone();
+        let stopped_walking = walk_stopped.clone();
+        thread::spawn(move || {
+            let mut batch = Vec::new();
+            // scores of the best matches so far, lowest on top, to tell if a new one makes the cut
+            // (the results drop whatever it pushes out once they're ranked):
+            let mut kept = BinaryHeap::new();
+            let mut last_sent = Instant::now();
+            // unreadable directories are skipped over:
+            for (walked, entry) in walk.filter_map(|entry| entry.ok()).enumerate() {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                if walked >= MAX_WALKED {
+                    stopped_walking.store(true, Ordering::Relaxed);
+                    break;
+                }
+                if entry.depth() == 0 {
+                    continue;
+                }
+                if let Some(score) = compiled.score(&entry.file_name().to_string_lossy()) {
+                    let full = kept.len() >= MAX_MATCHES;
+                    // unranked matches stay in walk order, so later ones can never make the cut:
+                    if full && !ranked {
+                        break;
+                    }
+                    // (ties go to the match found first, as in the ranking)
+                    if !full || kept.peek().is_some_and(|&Reverse(lowest)| score > lowest) {
+                        if full {
+                            kept.pop();
+                        }
+                        kept.push(Reverse(score));
+                        batch.push(FindMatch {
+                            is_dir: entry.file_type().is_some_and(|kind| kind.is_dir()),
+                            path: entry.into_path(),
+                            score,
+                        });
+                    }
+                }
+                if !batch.is_empty() && last_sent.elapsed() >= BATCH_INTERVAL {
+                    if sender.send(std::mem::take(&mut batch)).is_err() {
+                        return;
+                    }
+                    last_sent = Instant::now();
+                }
+            }
+            let _ = sender.send(batch);
+        });
+
+        Ok(Self {
+            root,
+            pattern,
+            mode,
+            results: StatefulList::new(),
+            running: true,
+            cancelled,
+            walk_stopped,
+            matches,
+        })
+    }
+
+    /// Add the matches found since the last call, keeping the cursor on the same one.
+    /// Returns `true` once the search has finished
+    fn poll(&mut self) -> bool {
+        let selected = self.results.selected().map(|m| m.path.clone());
+        let mut added = false;
+        let mut finished = false;
+        loop {
+            match self.matches.try_recv() {
+                Ok(batch) => {
+                    added |= !batch.is_empty();
+                    self.results.items.extend(batch);
+                }
+                Err(TryRecvError::Empty) => break,
+                // the worker is done (or was stopped):
+                Err(TryRecvError::Disconnected) => {
+                    finished = self.running;
+                    self.running = false;
+                    break;
+                }
+            }
+        }
+        if added {
+            self.update_results(selected.as_deref());
+        }
+        finished
+    }
+
+    /// Why some matches may not be shown, if they may not all be
+    pub fn limit_note(&self) -> Option<String> {
+        if self.walk_stopped.load(Ordering::Relaxed) {
+            Some(format!("stopped after {} entries", MAX_WALKED))
+        } else if self.results.items.len() >= MAX_MATCHES {
+            let which = if self.mode == FindMode::Fuzzy {
+                "best"
+            } else {
+                "first"
+            };
+            Some(format!("only the {} {} kept", which, MAX_MATCHES))
+        } else {
+            None
+        }
+    }
+
+    /// Re-rank and show all the matches, putting the cursor back on `selected`
+    fn update_results(&mut self, selected: Option<&Path>) {
+        // fuzzy matches are ranked best-first (the sort is stable, so ties stay in walk order):
+        if self.mode == FindMode::Fuzzy {
+            self.results.items.sort_by_key(|m| Reverse(m.score));
+        }
+        // the worker only sends matches that were among the best when found:
+        self.results.items.truncate(MAX_MATCHES);
+        let index = selected
+            .and_then(|selected| self.results.items.iter().position(|m| m.path == selected));
+        let len = self.results.items.len();
+        self.results.state.select(index.or((len > 0).then_some(0)));
+        self.results.set_filtered_items((0..len).collect());
+    }
+
+    /// Stop walking, keeping the matches found so far
+    fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.running = false;
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        // a new search replaces this one, so there's no point finishing it:
+        self.cancel();
+    }
+}
+
+impl App {
+    /// Search the tree below the current directory for names matching `pattern`, showing
+    /// the matches as they're found
+    pub fn start_search(&mut self, mode: FindMode, pattern: String) -> Result<(), String> {
+        let search = Search::start(self.current_path.clone(), pattern, mode, &self.listing)?;
+        self.search = Some(search);
+        self.mode = AppMode::Find;
+        Ok(())
+    }
+
+    /// Show the results of the last search again
+    pub fn open_search_results(&mut self) {
+        if self.search.is_some() {
+            self.mode = AppMode::Find;
+        } else {
+            self.set_status("No search yet (find <pattern>)".to_string());
+        }
+    }
+
+    /// Pick up matches from a running search
+    pub fn process_search_events(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        if search.poll() {
+            let count = search.results.items.len();
+            let message = match search.limit_note() {
+                Some(note) => format!("Found {} match(es) ({})", count, note),
+                None => format!("Found {} match(es)", count),
+            };
+            self.set_status(message);
+        }
+    }
+
+    pub fn cancel_search(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.cancel();
+            let count = search.results.items.len();
+            self.set_status(format!("Search stopped after {} match(es)", count));
+        }
+    }
+
+    pub fn selected_match(&self) -> Option<&FindMatch> {
+        self.search
+            .as_ref()
+            .and_then(|search| search.results.selected())
+    }
+
+    /// Go to the directory of the selected match, with the cursor on it
+    pub fn jump_to_match(&mut self) -> Result<()> {
+        let Some(path) = self.selected_match().map(|m| m.path.clone()) else {
+            return Ok(());
+        };
+        if !path.exists() && !path.is_symlink() {
+            self.set_error(format!("'{}' no longer exists", path.display()));
+            return Ok(());
+        }
+        self.mode = AppMode::Normal;
+        if let Some(dir) = path.parent() {
+            self.navigate_to(dir.to_path_buf())?;
+            self.select_path(&path);
+        }
+        Ok(())
+    }
+
+    /// Follow a match that has been renamed
+    pub fn rename_match(&mut self, from: &Path, to: PathBuf) {
+        let found = self
+            .search
+            .as_mut()
+            .and_then(|search| search.results.items.iter_mut().find(|m| m.path == from));
+        if let Some(found) = found {
+            found.path = to;
+        }
+    }
+
+    /// Drop matches that have been deleted or moved away since they were found
+    pub fn prune_search_results(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let selected = search.results.selected().map(|m| m.path.clone());
+        let index = search.results.state.selected();
+        search
+            .results
+            .items
+            .retain(|m| m.path.exists() || m.path.is_symlink());
+        search.update_results(selected.as_deref());
+        // the selected match has gone, so stay at about the same position:
+        if let Some(index) = index
+            && search.results.selected().map(|m| &m.path) != selected.as_ref()
+            && !search.results.items.is_empty()
+        {
+            let index = index.min(search.results.items.len() - 1);
+            search.results.state.select(Some(index));
+        }
+    }
+}
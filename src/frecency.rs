@@ -0,0 +1,162 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::utils::{data_home, load_toml, save_toml};
+
+/// Once the ranks add up to more than this, they are all scaled down and the least used dropped
+const MAX_TOTAL_RANK: f64 = 10_000.0;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Directories ranked by how often and how recently they were visited (like `zoxide`),
+/// saved to `$XDG_DATA_HOME/clexp/frecency.toml`
+#[derive(Debug, Clone, Default)]
+pub struct Frecency {
+    file: Option<PathBuf>, // `None` when there's no data directory to save to
+    dirs: Vec<Visits>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Visits {
+    path: PathBuf,
+    rank: f64,        // goes up by one every visit, and is scaled down as the database ages
+    last_access: u64, // seconds since the Unix epoch
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FrecencyFile {
+    #[serde(default)]
+    dirs: Vec<Visits>,
+}
+
+impl Frecency {
+    pub fn load() -> Result<Self> {
+        let mut frecency = Self {
+            file: data_home().map(|dir| dir.join("clexp").join("frecency.toml")),
+            dirs: Vec::new(),
+        };
+        frecency.reload()?;
+        Ok(frecency)
+    }
+
+    /// Read the database again, picking up visits recorded by other instances
+    fn reload(&mut self) -> Result<()> {
+        if let Some(parsed) = load_toml::<FrecencyFile>(self.file.as_deref())? {
+            self.dirs = parsed.dirs;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let contents = FrecencyFile {
+            dirs: self.dirs.clone(),
+        };
+        save_toml(self.file.as_deref(), &contents)
+    }
+
+    /// Count a visit to `dir`, and save
+    pub fn record(&mut self, dir: &Path) -> Result<()> {
+        self.reload()?;
+        let now = now();
+        match self.dirs.iter_mut().find(|visits| visits.path == dir) {
+            Some(visits) => {
+                visits.rank += 1.0;
+                visits.last_access = now;
+            }
+            None => self.dirs.push(Visits {
+                path: dir.to_path_buf(),
+                rank: 1.0,
+                last_access: now,
+            }),
+        }
+        self.age();
+        self.save()
+    }
+
+    /// Keep the database from growing forever, favouring recent habits over old ones
+    fn age(&mut self) {
+        let total = self.dirs.iter().map(|visits| visits.rank).sum::<f64>();
+        if total > MAX_TOTAL_RANK {
+            let factor = 0.9 * MAX_TOTAL_RANK / total;
+            for visits in &mut self.dirs {
+                visits.rank *= factor;
+            }
+            self.dirs.retain(|visits| visits.rank >= 1.0);
+        }
+    }
+
+    /// Directories that still exist, best first
+    pub fn ranked(&self) -> Vec<PathBuf> {
+        let now = now();
+        let mut dirs = self
+            .dirs
+            .iter()
+            .filter(|visits| visits.path.is_dir())
+            .map(|visits| (visits.score(now), &visits.path))
+            .collect::<Vec<_>>();
+        dirs.sort_by(|a, b| b.0.total_cmp(&a.0));
+        dirs.into_iter().map(|(_, path)| path.clone()).collect()
+    }
+
+    /// The best ranked directory (other than `exclude`) matching all of `keywords`, like `z foo bar`
+    pub fn best_match(&self, keywords: &[&str], exclude: &Path) -> Option<PathBuf> {
+        let keywords = keywords
+            .iter()
+            .map(|keyword| keyword.to_lowercase())
+            .collect::<Vec<_>>();
+        self.ranked()
+            .into_iter()
+            .filter(|dir| dir != exclude)
+            .find(|dir| keywords_match(&dir.to_string_lossy(), &keywords))
+    }
+}
+
+impl Visits {
+    /// Frequent visits count for more the more recent they are
+    fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_access);
+        let weight = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        self.rank * weight
+    }
+}
+
+/// Whether the keywords appear in `path` in order (case-insensitively), with the last one
+/// in the final component, so `z proj src` finds `~/projects/app/src` but not `~/src/projects`
+fn keywords_match(path: &str, keywords: &[String]) -> bool {
+    let path = path.to_lowercase();
+    let mut rest = path.as_str();
+    for keyword in keywords {
+        match rest.find(keyword.as_str()) {
+            Some(start) => rest = &rest[start + keyword.len()..],
+            None => return false,
+        }
+    }
+    match keywords.last() {
+        Some(last) => path
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.contains(last.as_str())),
+        None => true,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,408 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// How an entry differs from what git has recorded. Directories combine the status of
+/// everything inside them
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GitStatus {
+    pub conflicted: bool, // unmerged, from a merge or rebase
+    pub modified: bool,   // changed in the working tree but not staged
+    pub staged: bool,     // changes added to the index
+    pub untracked: bool,
+    pub ignored: bool,
+}
+
+impl GitStatus {
+    fn merge(&mut self, other: GitStatus) {
+        self.conflicted |= other.conflicted;
+        self.modified |= other.modified;
+        self.staged |= other.staged;
+        self.untracked |= other.untracked;
+        self.ignored |= other.ignored;
+    }
+
+    /// Markers for the list, most important first: `U` conflicted, `M` modified, `+` staged,
+    /// `?` untracked and `!` ignored
+    pub fn markers(&self) -> Vec<(char, GitMarker)> {
+        [
+            (self.conflicted, 'U', GitMarker::Conflicted),
+            (self.modified, 'M', GitMarker::Modified),
+            (self.staged, '+', GitMarker::Staged),
+            (self.untracked, '?', GitMarker::Untracked),
+            (self.ignored, '!', GitMarker::Ignored),
+        ]
+        .into_iter()
+        .filter(|(set, _, _)| *set)
+        .map(|(_, c, marker)| (c, marker))
+        .collect()
+    }
+}
+
+/// Which kind of status a marker shows (used to colour it)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitMarker {
+    Conflicted,
+    Modified,
+    Staged,
+    Untracked,
+    Ignored,
+}
+
+/// The status of a repository, as of the last refresh
+#[derive(Debug, Clone, Default)]
+pub struct GitInfo {
+    pub root: PathBuf,
+    pub branch: String,                   // or `(detached)`
+    pub ahead: u32,                       // commits not yet pushed to the upstream branch
+    pub behind: u32,                      // commits on the upstream branch not yet pulled
+    entries: HashMap<PathBuf, GitStatus>, // every changed, untracked or ignored path
+    dirs: HashMap<PathBuf, GitStatus>,    // combined status of everything below a directory
+}
+
+impl GitInfo {
+    /// The status of `path`, or `None` if it's unchanged (or outside the repository)
+    pub fn status(&self, path: &Path) -> Option<GitStatus> {
+        let mut status = self.entries.get(path).copied();
+        if let Some(dir) = self.dirs.get(path) {
+            status.get_or_insert_default().merge(*dir);
+        }
+        if status.is_some() {
+            return status;
+        }
+
+        // untracked and ignored directories are listed as a whole, so everything in them is too:
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .find_map(|dir| self.entries.get(dir))
+            .filter(|status| status.untracked || status.ignored)
+            .copied()
+    }
+
+    /// e.g. `main ↑2 ↓1`
+    pub fn label(&self) -> String {
+        let mut label = self.branch.clone();
+        if self.ahead > 0 {
+            label.push_str(&format!(" ↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            label.push_str(&format!(" ↓{}", self.behind));
+        }
+        label
+    }
+
+    /// Read the status of the repository containing `dir`, or `None` if it isn't in one
+    fn load(dir: &Path) -> Option<Self> {
+        // where `dir` is within the repository, to find the root without resolving symlinks:
+        let prefix = git(dir, &["rev-parse", "--show-prefix"])?;
+        let prefix = prefix.trim_end_matches(['\n', '/']);
+        let depth = Path::new(prefix).components().count();
+        let root = dir.ancestors().nth(depth)?.to_path_buf();
+
+        let output = git(
+            dir,
+            &[
+                "status",
+                "--porcelain=v2",
+                "--branch",
+                "--ignored",
+                "-z",
+                "--no-renames",
+            ],
+        )?;
+        let mut info = Self {
+            root,
+            ..Self::default()
+        };
+        info.parse_status(&output);
+        Some(info)
+    }
+
+    /// Parse the output of `git status --porcelain=v2 --branch -z`
+    fn parse_status(&mut self, output: &str) {
+        let mut records = output.split('\0');
+        while let Some(record) = records.next() {
+            let (path, status) = if let Some(header) = record.strip_prefix("# ") {
+                match header.split_once(' ') {
+                    Some(("branch.head", head)) => self.branch = head.to_string(),
+                    Some(("branch.ab", counts)) => {
+                        for count in counts.split(' ') {
+                            if let Some(ahead) = count.strip_prefix('+') {
+                                self.ahead = ahead.parse().unwrap_or(0);
+                            } else if let Some(behind) = count.strip_prefix('-') {
+                                self.behind = behind.parse().unwrap_or(0);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            } else if let Some(rest) = record.strip_prefix("1 ") {
+                // `1 XY sub mH mI mW hH hI path`
+                let fields = rest.splitn(8, ' ').collect::<Vec<_>>();
+                let (Some(xy), Some(path)) = (fields.first(), fields.get(7)) else {
+                    continue;
+                };
+                (*path, changes(xy))
+            } else if let Some(rest) = record.strip_prefix("2 ") {
+                // `2 XY sub mH mI mW hH hI Xscore path`, then the original path as its own record
+                // (`-z` puts a NUL where the plain format has a tab). Only seen without `--no-renames`
+                let fields = rest.splitn(9, ' ').collect::<Vec<_>>();
+                records.next();
+                let (Some(xy), Some(path)) = (fields.first(), fields.get(8)) else {
+                    continue;
+                };
+                (*path, changes(xy))
+            } else if let Some(rest) = record.strip_prefix("u ") {
+                // `u XY sub m1 m2 m3 mW h1 h2 h3 path`
+                let Some(path) = rest.splitn(10, ' ').nth(9) else {
+                    continue;
+                };
+                let status = GitStatus {
+                    conflicted: true,
+                    ..GitStatus::default()
+                };
+                (path, status)
+            } else if let Some(path) = record.strip_prefix("? ") {
+                let status = GitStatus {
+                    untracked: true,
+                    ..GitStatus::default()
+                };
+                (path, status)
+            } else if let Some(path) = record.strip_prefix("! ") {
+                let status = GitStatus {
+                    ignored: true,
+                    ..GitStatus::default()
+                };
+                (path, status)
+            } else {
+                continue;
+            };
+
+            let path = self.root.join(path.trim_end_matches('/'));
+            self.entries.entry(path.clone()).or_default().merge(status);
+
+            // an ignored file doesn't make the directory it's in ignored:
+            if status.ignored {
+                continue;
+            }
+            for dir in path.ancestors().skip(1) {
+                if !dir.starts_with(&self.root) {
+                    break;
+                }
+                self.dirs
+                    .entry(dir.to_path_buf())
+                    .or_default()
+                    .merge(status);
+            }
+        }
+    }
+}
+
+/// The staged and unstaged changes in a porcelain `XY` field, where `.` means unchanged
+fn changes(xy: &str) -> GitStatus {
+    let mut chars = xy.chars();
+    GitStatus {
+        staged: chars.next().is_some_and(|x| x != '.'),
+        modified: chars.next().is_some_and(|y| y != '.'),
+        ..GitStatus::default()
+    }
+}
+
+/// Run a git command in `dir`, returning its output if it succeeded
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_OPTIONAL_LOCKS", "0") // don't hold up git commands run by the user
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs `git status` on a worker thread, as it can take a while in large repositories
+pub struct GitStatusLoader {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Option<GitInfo>)>,
+    requested: Option<PathBuf>,
+    current: Option<GitInfo>,
+}
+
+impl GitStatusLoader {
+    pub fn new() -> Self {
+        let (request_sender, request_receiver) = mpsc::channel::<PathBuf>();
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Ok(mut dir) = request_receiver.recv() {
+                // skip straight to the newest request, the others are already out of date:
+                while let Ok(newer) = request_receiver.try_recv() {
+                    dir = newer;
+                }
+                let info = GitInfo::load(&dir);
+                if result_sender.send((dir, info)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests: request_sender,
+            results: result_receiver,
+            requested: None,
+            current: None,
+        }
+    }
+
+    /// Work out the status of the repository containing `dir` (again)
+    pub fn request(&mut self, dir: &Path) {
+        self.requested = Some(dir.to_path_buf());
+        let _ = self.requests.send(dir.to_path_buf());
+    }
+
+    /// Pick up finished results, dropping any for directories that are no longer shown
+    pub fn poll(&mut self) {
+        while let Ok((dir, info)) = self.results.try_recv() {
+            if self.requested.as_ref() == Some(&dir) {
+                self.current = info;
+            }
+        }
+    }
+
+    /// The status of the repository the requested directory is in. While a refresh is running
+    /// this is the previous result, as long as it's for the same repository
+    pub fn current(&self) -> Option<&GitInfo> {
+        self.current.as_ref().filter(|info| {
+            self.requested
+                .as_ref()
+                .is_some_and(|dir| dir.starts_with(&info.root))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `git status --porcelain=v2 --branch --ignored -z` part way through a merge, with the
+    /// records joined by NULs as git writes them
+    const STATUS: &str = concat!(
+        "# branch.oid 91e11ce9d1270ea5bc2de0a2950560894042b231\0",
+        "# branch.head main\0",
+        "# branch.upstream origin/main\0",
+        "# branch.ab +2 -1\0",
+        "1 M. N... 100644 100644 100644 61780798228d17af2d34fce4cfbdf35556832472 ",
+        "7de6a6c57c99b6582bb692bf533458081438d396 kept.txt\0",
+        "2 R. N... 100644 100644 100644 78981922613b2afb6025042ff6bd878ac1994e85 ",
+        "78981922613b2afb6025042ff6bd878ac1994e85 R100 new name.txt\0",
+        "old name.txt\0",
+        "1 .M N... 100644 100644 100644 f2ad6c76f0115a6ba5b00456a849810e7ec0af20 ",
+        "f2ad6c76f0115a6ba5b00456a849810e7ec0af20 src/lib.rs\0",
+        "u UU N... 100644 100644 100644 100644 587be6b4c3f93f93c489c0111bba5596147a26cb ",
+        "b68025345d5301abad4d9ec9166f455243a0d746 975fbec8256d3e8a3797e7a3611380f27c49f4ac ",
+        "conflict.txt\0",
+        "? notes draft.md\0",
+        "? build/\0",
+        "! debug.log\0",
+    );
+
+    fn parse(output: &str) -> GitInfo {
+        let mut info = GitInfo {
+            root: PathBuf::from("/repo"),
+            ..GitInfo::default()
+        };
+        info.parse_status(output);
+        info
+    }
+
+    #[test]
+    fn parses_branch_headers() {
+        let info = parse(STATUS);
+        assert_eq!(info.branch, "main");
+        assert_eq!((info.ahead, info.behind), (2, 1));
+        assert_eq!(info.label(), "main ↑2 ↓1");
+    }
+
+    #[test]
+    fn parses_changed_and_conflicted_records() {
+        let info = parse(STATUS);
+        let status = |path: &str| info.status(Path::new(path)).unwrap_or_default();
+
+        assert!(status("/repo/kept.txt").staged);
+        assert!(!status("/repo/kept.txt").modified);
+        assert!(status("/repo/src/lib.rs").modified);
+        assert!(!status("/repo/src/lib.rs").staged);
+        assert!(status("/repo/conflict.txt").conflicted);
+
+        // directories combine everything below them:
+        assert!(status("/repo/src").modified);
+        assert!(status("/repo").conflicted);
+    }
+
+    #[test]
+    fn rename_uses_the_new_path_and_skips_the_original() {
+        let info = parse(STATUS);
+        let renamed = info.status(Path::new("/repo/new name.txt"));
+        assert_eq!(
+            renamed,
+            Some(GitStatus {
+                staged: true,
+                ..GitStatus::default()
+            })
+        );
+        assert_eq!(info.status(Path::new("/repo/old name.txt")), None);
+        // the original path isn't mistaken for a record of its own:
+        assert!(
+            !info
+                .entries
+                .keys()
+                .any(|path| path.ends_with("old name.txt"))
+        );
+    }
+
+    #[test]
+    fn keeps_spaces_in_untracked_and_ignored_paths() {
+        let info = parse(STATUS);
+        assert!(
+            info.status(Path::new("/repo/notes draft.md"))
+                .is_some_and(|status| status.untracked)
+        );
+        assert!(
+            info.status(Path::new("/repo/debug.log"))
+                .is_some_and(|status| status.ignored)
+        );
+        // everything in an untracked directory is untracked too:
+        assert!(
+            info.status(Path::new("/repo/build/out.o"))
+                .is_some_and(|status| status.untracked)
+        );
+        // but an ignored file doesn't mark the directory it's in:
+        assert!(!info.status(Path::new("/repo")).unwrap_or_default().ignored);
+    }
+
+    #[test]
+    fn original_path_that_looks_like_a_record_is_skipped() {
+        let output = concat!(
+            "2 R. N... 100644 100644 100644 78981922613b2afb6025042ff6bd878ac1994e85 ",
+            "78981922613b2afb6025042ff6bd878ac1994e85 R100 renamed.txt\0",
+            "? was untracked?.txt\0",
+        );
+        let info = parse(output);
+        assert_eq!(info.status(Path::new("/repo/was untracked?.txt")), None);
+        assert!(
+            info.status(Path::new("/repo/renamed.txt"))
+                .is_some_and(|status| status.staged)
+        );
+    }
+}
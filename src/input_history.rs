@@ -0,0 +1,104 @@
+use anyhow::Result;
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::utils::{data_home, load_toml, save_toml};
+
+/// How many lines are kept for each kind of input
+const MAX_ENTRIES: usize = 1000;
+
+/// Lines typed into the command line and input prompts, kept separately for each kind of input
+/// (`command`, `rename`, `create` and `filter`) and saved to `$XDG_DATA_HOME/clexp/history.toml`
+#[derive(Debug, Clone, Default)]
+pub struct InputHistory {
+    file: Option<PathBuf>, // `None` when there's no data directory to save to
+    entries: BTreeMap<String, Vec<String>>, // oldest first, each line once
+}
+
+/// Stepping through the history with Up/Down
+#[derive(Debug, Clone)]
+pub struct HistoryBrowse {
+    pub index: usize,  // entry currently in the input
+    pub draft: String, // what was typed before browsing, which also narrows the entries shown
+}
+
+/// A Ctrl+R search back through the history
+#[derive(Debug, Clone, Default)]
+pub struct HistorySearch {
+    pub query: String,
+    pub found: Option<usize>, // newest entry containing the query
+}
+
+impl InputHistory {
+    pub fn load() -> Result<Self> {
+        let mut history = Self {
+            file: data_home().map(|dir| dir.join("clexp").join("history.toml")),
+            entries: BTreeMap::new(),
+        };
+        history.reload()?;
+        Ok(history)
+    }
+
+    /// Read the history again, picking up lines entered in other instances
+    fn reload(&mut self) -> Result<()> {
+        if let Some(entries) = load_toml(self.file.as_deref())? {
+            self.entries = entries;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        save_toml(self.file.as_deref(), &self.entries)
+    }
+
+    /// Remember `line` as the newest entry of `kind`, and save
+    pub fn add(&mut self, kind: &str, line: &str) -> Result<()> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        self.reload()?;
+        let entries = self.entries.entry(kind.to_string()).or_default();
+        entries.retain(|entry| entry != line);
+        entries.push(line.to_string());
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+        self.save()
+    }
+
+    /// Entries of `kind`, oldest first
+    pub fn entries(&self, kind: &str) -> &[String] {
+        self.entries
+            .get(kind)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The newest entry before `before` (or at all) starting with `prefix`
+    pub fn previous(&self, kind: &str, before: Option<usize>, prefix: &str) -> Option<usize> {
+        let entries = self.entries(kind);
+        let end = before.unwrap_or(entries.len()).min(entries.len());
+        entries[..end]
+            .iter()
+            .rposition(|entry| entry.starts_with(prefix))
+    }
+
+    /// The oldest entry after `after` starting with `prefix`
+    pub fn next(&self, kind: &str, after: usize, prefix: &str) -> Option<usize> {
+        self.entries(kind)
+            .iter()
+            .enumerate()
+            .skip(after + 1)
+            .find(|(_, entry)| entry.starts_with(prefix))
+            .map(|(i, _)| i)
+    }
+
+    /// The newest entry before `before` (or at all) containing `query`, ignoring case
+    pub fn search(&self, kind: &str, query: &str, before: Option<usize>) -> Option<usize> {
+        let query = query.to_lowercase();
+        let entries = self.entries(kind);
+        let end = before.unwrap_or(entries.len()).min(entries.len());
+        entries[..end]
+            .iter()
+            .rposition(|entry| entry.to_lowercase().contains(&query))
+    }
+}
@@ -0,0 +1,373 @@
+use anyhow::Result;
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::commands::BackgroundCommand;
+
+/// How often a paused job checks whether it has been resumed or cancelled
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Error returned by a job that was cancelled by the user
+#[derive(Debug)]
+pub struct JobCancelled;
+
+impl std::fmt::Display for JobCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for JobCancelled {}
+
+/// Flags shared between the UI thread and a worker thread
+#[derive(Debug, Default)]
+struct JobControl {
+    paused: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+/// Messages sent from worker threads back to the UI thread
+enum JobEvent {
+    Totals {
+        id: usize,
+        files: u64,
+        bytes: u64,
+    },
+    Progress {
+        id: usize,
+        files: u64,
+        bytes: u64,
+    },
+    Finished {
+        id: usize,
+        command: Box<dyn BackgroundCommand>,
+        kind: JobKind,
+        result: Result<()>,
+    },
+}
+
+/// What a job does with its command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobKind {
+    Execute, // run it for the first time
+    Undo,    // undo it, from the history
+    Redo,    // run it again after it was undone
+}
+
+impl JobKind {
+    /// How a job of this kind running `command` is shown
+    pub fn describe(self, command: &dyn BackgroundCommand) -> String {
+        match self {
+            JobKind::Execute => command.description(),
+            JobKind::Undo => format!("Undo {}", command.description()),
+            JobKind::Redo => format!("Redo {}", command.description()),
+        }
+    }
+}
+
+/// Handed to a command running on a worker thread, to report progress and honour pause/cancel
+pub struct JobContext {
+    id: usize,
+    sender: Option<Sender<JobEvent>>, // `None` when running synchronously on the UI thread
+    control: Arc<JobControl>,
+    files_done: Cell<u64>,
+    bytes_done: Cell<u64>,
+    warnings: RefCell<Vec<String>>, // problems that didn't stop the work, for the command to report
+}
+
+impl JobContext {
+    /// A context for running a background command synchronously (progress goes nowhere)
+    pub fn detached() -> Self {
+        Self {
+            id: 0,
+            sender: None,
+            control: Arc::default(),
+            files_done: Cell::new(0),
+            bytes_done: Cell::new(0),
+            warnings: RefCell::default(),
+        }
+    }
+
+    pub fn set_totals(&self, files: u64, bytes: u64) {
+        self.send(JobEvent::Totals {
+            id: self.id,
+            files,
+            bytes,
+        });
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.set(self.bytes_done.get() + bytes);
+        self.send_progress();
+    }
+
+    pub fn file_done(&self) {
+        self.files_done.set(self.files_done.get() + 1);
+        self.send_progress();
+    }
+
+    /// Count everything up to these running totals as done, including work that needed no
+    /// copying (e.g. a rename within a filesystem, which moves a whole tree at once)
+    pub fn catch_up(&self, files: u64, bytes: u64) {
+        self.files_done.set(self.files_done.get().max(files));
+        self.bytes_done.set(self.bytes_done.get().max(bytes));
+        self.send_progress();
+    }
+
+    /// Note something that went wrong without failing the item (e.g. an attribute that
+    /// couldn't be kept)
+    pub fn warn(&self, message: String) {
+        self.warnings.borrow_mut().push(message);
+    }
+
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    /// Block while the job is paused, and bail out if it has been cancelled
+    pub fn checkpoint(&self) -> Result<()> {
+        while self.control.paused.load(Ordering::Relaxed)
+            && !self.control.cancelled.load(Ordering::Relaxed)
+        {
+            thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+        if self.control.cancelled.load(Ordering::Relaxed) {
+            return Err(JobCancelled.into());
+        }
+        Ok(())
+    }
+
+    fn send_progress(&self) {
+        self.send(JobEvent::Progress {
+            id: self.id,
+            files: self.files_done.get(),
+            bytes: self.bytes_done.get(),
+        });
+    }
+
+    fn send(&self, event: JobEvent) {
+        if let Some(sender) = &self.sender {
+            // the receiver only goes away when the app is shutting down:
+            let _ = sender.send(event);
+        }
+    }
+}
+
+/// A running job, as seen from the UI thread
+#[derive(Debug)]
+pub struct Job {
+    pub id: usize,
+    pub description: String,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    control: Arc<JobControl>,
+}
+
+impl Job {
+    pub fn is_paused(&self) -> bool {
+        self.control.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.control.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fraction of the job completed, by bytes if known, otherwise by files
+    pub fn ratio(&self) -> f64 {
+        let (done, total) = if self.bytes_total > 0 {
+            (self.bytes_done, self.bytes_total)
+        } else {
+            (self.files_done, self.files_total)
+        };
+        if total == 0 {
+            0.0
+        } else {
+            (done as f64 / total as f64).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Runs background commands on worker threads and collects their progress
+pub struct JobManager {
+    pub jobs: Vec<Job>,
+    pub selected: usize, // job highlighted in the jobs panel
+    next_id: usize,
+    sender: Sender<JobEvent>,
+    receiver: Receiver<JobEvent>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            jobs: Vec::new(),
+            selected: 0,
+            next_id: 1,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Start running (or undoing) `command` on a worker thread
+    pub fn spawn(&mut self, mut command: Box<dyn BackgroundCommand>, kind: JobKind) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let control = Arc::new(JobControl::default());
+        self.jobs.push(Job {
+            id,
+            description: kind.describe(command.as_ref()),
+            files_done: 0,
+            files_total: 0,
+            bytes_done: 0,
+            bytes_total: 0,
+            control: Arc::clone(&control),
+        });
+
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let context = JobContext {
+                id,
+                sender: Some(sender.clone()),
+                control,
+                files_done: Cell::new(0),
+                bytes_done: Cell::new(0),
+                warnings: RefCell::default(),
+            };
+            let result = match kind {
+                JobKind::Undo => command.run_undo(&context),
+                JobKind::Execute | JobKind::Redo => command.run(&context),
+            };
+            let _ = sender.send(JobEvent::Finished {
+                id,
+                command,
+                kind,
+                result,
+            });
+        });
+    }
+
+    /// Apply pending progress updates, returning the commands of any jobs that have finished
+    pub fn poll(&mut self) -> Vec<(Box<dyn BackgroundCommand>, JobKind, Result<()>)> {
+        let mut finished = Vec::new();
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                JobEvent::Totals { id, files, bytes } => {
+                    if let Some(job) = self.job_mut(id) {
+                        job.files_total = files;
+                        job.bytes_total = bytes;
+                    }
+                }
+                JobEvent::Progress { id, files, bytes } => {
+                    if let Some(job) = self.job_mut(id) {
+                        job.files_done = files;
+                        job.bytes_done = bytes;
+                    }
+                }
+                JobEvent::Finished {
+                    id,
+                    command,
+                    kind,
+                    result,
+                } => {
+                    self.jobs.retain(|job| job.id != id);
+                    finished.push((command, kind, result));
+                }
+            }
+        }
+        self.selected = self.selected.min(self.jobs.len().saturating_sub(1));
+        finished
+    }
+
+    fn job_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.jobs.is_empty() {
+            self.selected = (self.selected + 1) % self.jobs.len();
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        if !self.jobs.is_empty() {
+            self.selected = (self.selected + self.jobs.len() - 1) % self.jobs.len();
+        }
+    }
+
+    pub fn toggle_pause_selected(&mut self) {
+        if let Some(job) = self.jobs.get(self.selected) {
+            job.control
+                .paused
+                .store(!job.is_paused(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn cancel_selected(&mut self) {
+        if let Some(job) = self.jobs.get(self.selected) {
+            job.control.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn cancel_all(&mut self) {
+        for job in &self.jobs {
+            job.control.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Count the files and bytes below each of `paths` separately, setting their sum as the
+/// totals of `context`. Callers can then `catch_up` after each one without scanning again
+pub fn scan_each(paths: &[impl AsRef<Path>], context: &JobContext) -> Vec<(u64, u64)> {
+    let totals = paths
+        .iter()
+        .map(|path| scan_totals(&[path]))
+        .collect::<Vec<_>>();
+    let files = totals.iter().map(|(files, _)| files).sum();
+    let bytes = totals.iter().map(|(_, bytes)| bytes).sum();
+    context.set_totals(files, bytes);
+    totals
+}
+
+/// Count the files and bytes below `paths`, used for progress totals
+pub fn scan_totals(paths: &[impl AsRef<Path>]) -> (u64, u64) {
+    let mut files = 0;
+    let mut bytes = 0;
+    let mut stack = paths
+        .iter()
+        .map(|path| path.as_ref().to_path_buf())
+        .collect::<Vec<_>>();
+    while let Some(path) = stack.pop() {
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(&path) {
+                stack.extend(
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path()),
+                );
+            }
+        } else {
+            files += 1;
+            bytes += metadata.len();
+        }
+    }
+    (files, bytes)
+}
@@ -0,0 +1,728 @@
+use anyhow::{Result, anyhow};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{collections::HashMap, fmt};
+
+use crate::app::AppMode;
+
+/// Something a key can be bound to. Text entry (input, command and filter bars) isn't
+/// configurable, as every printable key types itself there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    // Navigation:
+    MoveDown,
+    MoveUp,
+    GoUp,
+    Open,
+    Command,
+    Help,
+    ClearMessages,
+    Filter,
+    SelectMode,
+    Clipboard,
+    Trash,
+    Jobs,
+    Back,
+    Forward,
+    JumpList,
+    SetMark,
+    JumpToMark,
+    Bookmarks,
+    Jumper,
+    CycleSort,
+    ReverseSort,
+    ToggleDirsFirst,
+    ToggleHidden,
+    CycleIgnored,
+    CycleLayout,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PrevTab,
+    Tab1,
+    Tab2,
+    Tab3,
+    Tab4,
+    Tab5,
+    Tab6,
+    Tab7,
+    Tab8,
+    Tab9,
+    SwitchPane,
+    ToggleTree,
+    ToggleExpand,
+    Quit,
+    Close,
+
+    // File operations:
+    Rename,
+    Delete,
+    DeletePermanent,
+    Cut,
+    Copy,
+    Paste,
+    PasteArchive,
+    NewFile,
+    NewDir,
+    Undo,
+    Redo,
+    ToggleSelection,
+
+    // Trash:
+    Restore,
+    Purge,
+    EmptyTrash,
+
+    // Jobs:
+    PauseJob,
+    CancelJob,
+
+    // Paste conflicts:
+    Overwrite,
+    Skip,
+    KeepBoth,
+    Merge,
+    ApplyToAll,
+
+    // Bookmarks:
+    RemoveBookmark,
+
+    // Confirm prompts:
+    Confirm,
+    Cancel,
+}
+
+impl Action {
+    pub const ALL: [Action; 68] = [
+        Action::MoveDown,
+        Action::MoveUp,
+        Action::GoUp,
+        Action::Open,
+        Action::Command,
+        Action::Help,
+        Action::ClearMessages,
+        Action::Filter,
+        Action::SelectMode,
+        Action::Clipboard,
+        Action::Trash,
+        Action::Jobs,
+        Action::Back,
+        Action::Forward,
+        Action::JumpList,
+        Action::SetMark,
+        Action::JumpToMark,
+        Action::Bookmarks,
+        Action::Jumper,
+        Action::CycleSort,
+        Action::ReverseSort,
+        Action::ToggleDirsFirst,
+        Action::ToggleHidden,
+        Action::CycleIgnored,
+        Action::CycleLayout,
+        Action::NewTab,
+        Action::CloseTab,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::Tab1,
+        Action::Tab2,
+        Action::Tab3,
+        Action::Tab4,
+        Action::Tab5,
+        Action::Tab6,
+        Action::Tab7,
+        Action::Tab8,
+        Action::Tab9,
+        Action::SwitchPane,
+        Action::ToggleTree,
+        Action::ToggleExpand,
+        Action::Quit,
+        Action::Close,
+        Action::Rename,
+        Action::Delete,
+        Action::DeletePermanent,
+        Action::Cut,
+        Action::Copy,
+        Action::Paste,
+        Action::PasteArchive,
+        Action::NewFile,
+        Action::NewDir,
+        Action::Undo,
+        Action::Redo,
+        Action::ToggleSelection,
+        Action::Restore,
+        Action::Purge,
+        Action::EmptyTrash,
+        Action::PauseJob,
+        Action::CancelJob,
+        Action::Overwrite,
+        Action::Skip,
+        Action::KeepBoth,
+        Action::Merge,
+        Action::ApplyToAll,
+        Action::RemoveBookmark,
+        Action::Confirm,
+        Action::Cancel,
+    ];
+
+    /// Name used for the action in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveDown => "move-down",
+            Action::MoveUp => "move-up",
+            Action::GoUp => "go-up",
+            Action::Open => "open",
+            Action::Command => "command",
+            Action::Help => "help",
+            Action::ClearMessages => "clear-messages",
+            Action::Filter => "filter",
+            Action::SelectMode => "select-mode",
+            Action::Clipboard => "clipboard",
+            Action::Trash => "trash",
+            Action::Jobs => "jobs",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::JumpList => "jump-list",
+            Action::SetMark => "set-mark",
+            Action::JumpToMark => "jump-to-mark",
+            Action::Bookmarks => "bookmarks",
+            Action::Jumper => "jumper",
+            Action::CycleSort => "cycle-sort",
+            Action::ReverseSort => "reverse-sort",
+            Action::ToggleDirsFirst => "toggle-dirs-first",
+            Action::ToggleHidden => "toggle-hidden",
+            Action::CycleIgnored => "cycle-ignored",
+            Action::CycleLayout => "cycle-layout",
+            Action::NewTab => "new-tab",
+            Action::CloseTab => "close-tab",
+            Action::NextTab => "next-tab",
+            Action::PrevTab => "prev-tab",
+            Action::Tab1 => "tab-1",
+            Action::Tab2 => "tab-2",
+            Action::Tab3 => "tab-3",
+            Action::Tab4 => "tab-4",
+            Action::Tab5 => "tab-5",
+            Action::Tab6 => "tab-6",
+            Action::Tab7 => "tab-7",
+            Action::Tab8 => "tab-8",
+            Action::Tab9 => "tab-9",
+            Action::SwitchPane => "switch-pane",
+            Action::ToggleTree => "toggle-tree",
+            Action::ToggleExpand => "toggle-expand",
+            Action::RemoveBookmark => "remove-bookmark",
+            Action::Quit => "quit",
+            Action::Close => "close",
+            Action::Rename => "rename",
+            Action::Delete => "delete",
+            Action::DeletePermanent => "delete-permanent",
+            Action::Cut => "cut",
+            Action::Copy => "copy",
+            Action::Paste => "paste",
+            Action::PasteArchive => "paste-archive",
+            Action::NewFile => "new-file",
+            Action::NewDir => "new-dir",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::ToggleSelection => "toggle-selection",
+            Action::Restore => "restore",
+            Action::Purge => "purge",
+            Action::EmptyTrash => "empty-trash",
+            Action::PauseJob => "pause-job",
+            Action::CancelJob => "cancel-job",
+            Action::Overwrite => "overwrite",
+            Action::Skip => "skip",
+            Action::KeepBoth => "keep-both",
+            Action::Merge => "merge",
+            Action::ApplyToAll => "apply-to-all",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+        }
+    }
+
+    /// Text shown for the action in the help modal
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::MoveDown => "Move selection down",
+            Action::MoveUp => "Move selection up",
+            Action::GoUp => "Up one directory",
+            Action::Open => "Enter directory / open file",
+            Action::Command => "Enter command",
+            Action::Help => "Show this help",
+            Action::ClearMessages => "Clear messages",
+            Action::Filter => "Filter files (Tab cycles substring/glob/fuzzy)",
+            Action::SelectMode => "Multi-select mode",
+            Action::Clipboard => "Show clipboard",
+            Action::Trash => "Show trash",
+            Action::Jobs => "Manage background jobs",
+            Action::Back => "Back to previous directory",
+            Action::Forward => "Forward to next directory",
+            Action::JumpList => "Jump to a recently visited directory",
+            Action::SetMark => "Bookmark current directory (then press a key)",
+            Action::JumpToMark => "Jump to bookmark (then press its key)",
+            Action::Bookmarks => "Show bookmarks",
+            Action::Jumper => "Jump to a frequently used directory",
+            Action::CycleSort => "Sort by next key (name/natural/size/mtime/ext/type)",
+            Action::ReverseSort => "Reverse sort order",
+            Action::ToggleDirsFirst => "Toggle directories first",
+            Action::ToggleHidden => "Show/hide dotfiles",
+            Action::CycleIgnored => "Show/dim/hide entries in .gitignore/.ignore",
+            Action::CycleLayout => "Switch layout (split / columns / dual-pane)",
+            Action::NewTab => "Open a new tab here",
+            Action::CloseTab => "Close tab",
+            Action::NextTab => "Next tab",
+            Action::PrevTab => "Previous tab",
+            Action::Tab1 => "Go to tab 1",
+            Action::Tab2 => "Go to tab 2",
+            Action::Tab3 => "Go to tab 3",
+            Action::Tab4 => "Go to tab 4",
+            Action::Tab5 => "Go to tab 5",
+            Action::Tab6 => "Go to tab 6",
+            Action::Tab7 => "Go to tab 7",
+            Action::Tab8 => "Go to tab 8",
+            Action::Tab9 => "Go to tab 9",
+            Action::SwitchPane => "Switch pane (dual-pane layout)",
+            Action::ToggleTree => "Toggle the tree view",
+            Action::ToggleExpand => "Expand/collapse directory (tree view)",
+            Action::RemoveBookmark => "Remove selected bookmark",
+            Action::Quit => "Quit",
+            Action::Close => "Close / go back",
+            Action::Rename => "Rename selected file/dir",
+            Action::Delete => "Move selected file(s)/dir(s) to trash",
+            Action::DeletePermanent => "Permanently delete selected file(s)/dir(s)",
+            Action::Cut => "Cut selected file(s)/dir(s) (dual-pane: move to other pane)",
+            Action::Copy => "Copy selected file(s)/dir(s) (dual-pane: copy to other pane)",
+            Action::Paste => "Paste clipboard",
+            Action::PasteArchive => "Paste keeping ownership and xattrs (like cp -a)",
+            Action::NewFile => "New file",
+            Action::NewDir => "New directory",
+            Action::Undo => "Undo last operation",
+            Action::Redo => "Redo last undone operation",
+            Action::ToggleSelection => "Toggle selection",
+            Action::Restore => "Restore selected item",
+            Action::Purge => "Permanently delete selected item",
+            Action::EmptyTrash => "Empty trash",
+            Action::PauseJob => "Pause/resume selected job",
+            Action::CancelJob => "Cancel selected job",
+            Action::Overwrite => "Overwrite existing item",
+            Action::Skip => "Skip this item",
+            Action::KeepBoth => "Keep both (paste under a new name)",
+            Action::Merge => "Merge directories",
+            Action::ApplyToAll => "Apply choice to all remaining conflicts",
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// The tab number (counting from 1) that a `Tab1`..`Tab9` action goes to
+    pub fn tab_number(self) -> Option<usize> {
+        match self {
+            Action::Tab1 => Some(1),
+            Action::Tab2 => Some(2),
+            Action::Tab3 => Some(3),
+            Action::Tab4 => Some(4),
+            Action::Tab5 => Some(5),
+            Action::Tab6 => Some(6),
+            Action::Tab7 => Some(7),
+            Action::Tab8 => Some(8),
+            Action::Tab9 => Some(9),
+            _ => None,
+        }
+    }
+}
+
+/// A key together with the Ctrl/Alt/Shift modifiers held
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // shift is already part of the character (`D` vs `d`) or of BackTab:
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    /// Parse a chord such as `j`, `D`, `ctrl+r`, `alt+left`, `shift+tab` or `space`
+    pub fn parse(s: &str) -> Result<Self> {
+        let (modifier_names, key) = match s.strip_suffix("++") {
+            Some(rest) => (rest, "+"),
+            None => match s.rsplit_once('+') {
+                Some((modifiers, key)) if !key.is_empty() => (modifiers, key),
+                _ => ("", s),
+            },
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(anyhow!("unknown modifier '{}' in '{}'", name, s)),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "space" => KeyCode::Char(' '),
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').map(str::parse::<u8>) {
+                    Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(anyhow!("unknown key '{}'", s)),
+                },
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Modes whose keys come from the keymap, with their config section name and help heading
+pub const KEYMAP_MODES: [(AppMode, &str, &str); 11] = [
+    (AppMode::Normal, "normal", "Normal mode"),
+    (AppMode::MultiSelect, "select", "Multi-select mode"),
+    (AppMode::Trash, "trash", "Trash"),
+    (AppMode::Jobs, "jobs", "Jobs"),
+    (AppMode::Conflict, "conflict", "Paste conflicts"),
+    (AppMode::Confirm, "confirm", "Confirm prompts"),
+    (AppMode::Help, "help", "Help"),
+    (AppMode::Clipboard, "clipboard", "Clipboard"),
+    (AppMode::JumpList, "jumps", "Jump list"),
+    (AppMode::Bookmarks, "bookmarks", "Bookmarks"),
+    (AppMode::Find, "find", "Find results"),
+];
+
+/// Built-in bindings, in the order they are listed in the help modal
+const DEFAULT_BINDINGS: &[(AppMode, &str, Action)] = &[
+    // Normal mode:
+    (AppMode::Normal, "down", Action::MoveDown),
+    (AppMode::Normal, "up", Action::MoveUp),
+    (AppMode::Normal, "left", Action::GoUp),
+    (AppMode::Normal, "right", Action::Open),
+    (AppMode::Normal, "enter", Action::Open),
+    (AppMode::Normal, "alt+left", Action::Back),
+    (AppMode::Normal, "alt+right", Action::Forward),
+    (AppMode::Normal, "H", Action::JumpList),
+    (AppMode::Normal, "m", Action::SetMark),
+    (AppMode::Normal, "'", Action::JumpToMark),
+    (AppMode::Normal, "b", Action::Bookmarks),
+    (AppMode::Normal, "z", Action::Jumper),
+    (AppMode::Normal, "f", Action::Filter),
+    (AppMode::Normal, "o", Action::CycleSort),
+    (AppMode::Normal, "O", Action::ReverseSort),
+    (AppMode::Normal, "ctrl+o", Action::ToggleDirsFirst),
+    (AppMode::Normal, ".", Action::ToggleHidden),
+    (AppMode::Normal, "i", Action::CycleIgnored),
+    (AppMode::Normal, "L", Action::CycleLayout),
+    (AppMode::Normal, "ctrl+t", Action::NewTab),
+    (AppMode::Normal, "ctrl+w", Action::CloseTab),
+    (AppMode::Normal, "]", Action::NextTab),
+    (AppMode::Normal, "[", Action::PrevTab),
+    (AppMode::Normal, "1", Action::Tab1),
+    (AppMode::Normal, "2", Action::Tab2),
+    (AppMode::Normal, "3", Action::Tab3),
+    (AppMode::Normal, "4", Action::Tab4),
+    (AppMode::Normal, "5", Action::Tab5),
+    (AppMode::Normal, "6", Action::Tab6),
+    (AppMode::Normal, "7", Action::Tab7),
+    (AppMode::Normal, "8", Action::Tab8),
+    (AppMode::Normal, "9", Action::Tab9),
+    (AppMode::Normal, "tab", Action::SwitchPane),
+    (AppMode::Normal, "t", Action::ToggleTree),
+    (AppMode::Normal, "space", Action::ToggleExpand),
+    (AppMode::Normal, "/", Action::Command),
+    (AppMode::Normal, "?", Action::Help),
+    (AppMode::Normal, "C", Action::Clipboard),
+    (AppMode::Normal, "T", Action::Trash),
+    (AppMode::Normal, "J", Action::Jobs),
+    (AppMode::Normal, "esc", Action::ClearMessages),
+    (AppMode::Normal, "q", Action::Quit),
+    (AppMode::Normal, "ctrl+c", Action::Quit),
+    (AppMode::Normal, "r", Action::Rename),
+    (AppMode::Normal, "d", Action::Delete),
+    (AppMode::Normal, "D", Action::DeletePermanent),
+    (AppMode::Normal, "x", Action::Cut),
+    (AppMode::Normal, "c", Action::Copy),
+    (AppMode::Normal, "v", Action::Paste),
+    (AppMode::Normal, "V", Action::PasteArchive),
+    (AppMode::Normal, "n", Action::NewFile),
+    (AppMode::Normal, "N", Action::NewDir),
+    (AppMode::Normal, "u", Action::Undo),
+    (AppMode::Normal, "ctrl+r", Action::Redo),
+    (AppMode::Normal, "s", Action::SelectMode),
+    // Multi-select mode:
+    (AppMode::MultiSelect, "down", Action::MoveDown),
+    (AppMode::MultiSelect, "up", Action::MoveUp),
+    (AppMode::MultiSelect, "left", Action::GoUp),
+    (AppMode::MultiSelect, "right", Action::Open),
+    (AppMode::MultiSelect, "enter", Action::Open),
+    (AppMode::MultiSelect, "space", Action::ToggleSelection),
+    (AppMode::MultiSelect, "d", Action::Delete),
+    (AppMode::MultiSelect, "D", Action::DeletePermanent),
+    (AppMode::MultiSelect, "c", Action::Copy),
+    (AppMode::MultiSelect, "x", Action::Cut),
+    (AppMode::MultiSelect, "tab", Action::SwitchPane),
+    (AppMode::MultiSelect, "1", Action::Tab1),
+    (AppMode::MultiSelect, "2", Action::Tab2),
+    (AppMode::MultiSelect, "3", Action::Tab3),
+    (AppMode::MultiSelect, "4", Action::Tab4),
+    (AppMode::MultiSelect, "5", Action::Tab5),
+    (AppMode::MultiSelect, "6", Action::Tab6),
+    (AppMode::MultiSelect, "7", Action::Tab7),
+    (AppMode::MultiSelect, "8", Action::Tab8),
+    (AppMode::MultiSelect, "9", Action::Tab9),
+    (AppMode::MultiSelect, "esc", Action::Close),
+    (AppMode::MultiSelect, "q", Action::Close),
+    (AppMode::MultiSelect, "s", Action::Close),
+    // Trash:
+    (AppMode::Trash, "down", Action::MoveDown),
+    (AppMode::Trash, "up", Action::MoveUp),
+    (AppMode::Trash, "enter", Action::Restore),
+    (AppMode::Trash, "r", Action::Restore),
+    (AppMode::Trash, "d", Action::Purge),
+    (AppMode::Trash, "D", Action::Purge),
+    (AppMode::Trash, "E", Action::EmptyTrash),
+    (AppMode::Trash, "esc", Action::Close),
+    (AppMode::Trash, "q", Action::Close),
+    (AppMode::Trash, "T", Action::Close),
+    // Jobs:
+    (AppMode::Jobs, "down", Action::MoveDown),
+    (AppMode::Jobs, "up", Action::MoveUp),
+    (AppMode::Jobs, "p", Action::PauseJob),
+    (AppMode::Jobs, "space", Action::PauseJob),
+    (AppMode::Jobs, "x", Action::CancelJob),
+    (AppMode::Jobs, "c", Action::CancelJob),
+    (AppMode::Jobs, "esc", Action::Close),
+    (AppMode::Jobs, "q", Action::Close),
+    (AppMode::Jobs, "J", Action::Close),
+    // Paste conflicts:
+    (AppMode::Conflict, "o", Action::Overwrite),
+    (AppMode::Conflict, "s", Action::Skip),
+    (AppMode::Conflict, "r", Action::KeepBoth),
+    (AppMode::Conflict, "m", Action::Merge),
+    (AppMode::Conflict, "a", Action::ApplyToAll),
+    (AppMode::Conflict, "esc", Action::Cancel),
+    (AppMode::Conflict, "q", Action::Cancel),
+    // Confirm prompts:
+    (AppMode::Confirm, "y", Action::Confirm),
+    (AppMode::Confirm, "Y", Action::Confirm),
+    (AppMode::Confirm, "n", Action::Cancel),
+    (AppMode::Confirm, "N", Action::Cancel),
+    (AppMode::Confirm, "q", Action::Cancel),
+    (AppMode::Confirm, "Q", Action::Cancel),
+    (AppMode::Confirm, "esc", Action::Cancel),
+    // Help:
+    (AppMode::Help, "down", Action::MoveDown),
+    (AppMode::Help, "up", Action::MoveUp),
+    (AppMode::Help, "esc", Action::Close),
+    (AppMode::Help, "q", Action::Close),
+    (AppMode::Help, "ctrl+c", Action::Quit),
+    // Clipboard:
+    (AppMode::Clipboard, "down", Action::MoveDown),
+    (AppMode::Clipboard, "up", Action::MoveUp),
+    (AppMode::Clipboard, "esc", Action::Close),
+    (AppMode::Clipboard, "q", Action::Close),
+    // Jump list:
+    (AppMode::JumpList, "down", Action::MoveDown),
+    (AppMode::JumpList, "up", Action::MoveUp),
+    (AppMode::JumpList, "enter", Action::Open),
+    (AppMode::JumpList, "esc", Action::Close),
+    (AppMode::JumpList, "q", Action::Close),
+    (AppMode::JumpList, "H", Action::Close),
+    // Bookmarks:
+    (AppMode::Bookmarks, "down", Action::MoveDown),
+    (AppMode::Bookmarks, "up", Action::MoveUp),
+    (AppMode::Bookmarks, "enter", Action::Open),
+    (AppMode::Bookmarks, "d", Action::RemoveBookmark),
+    (AppMode::Bookmarks, "esc", Action::Close),
+    (AppMode::Bookmarks, "q", Action::Close),
+    (AppMode::Bookmarks, "b", Action::Close),
+    // Find results:
+    (AppMode::Find, "down", Action::MoveDown),
+    (AppMode::Find, "up", Action::MoveUp),
+    (AppMode::Find, "enter", Action::Open),
+    (AppMode::Find, "c", Action::Copy),
+    (AppMode::Find, "x", Action::Cut),
+    (AppMode::Find, "r", Action::Rename),
+    (AppMode::Find, "d", Action::Delete),
+    (AppMode::Find, "D", Action::DeletePermanent),
+    (AppMode::Find, "esc", Action::Close),
+    (AppMode::Find, "q", Action::Close),
+];
+
+/// Extra bindings added by the `vim` preset
+const VIM_BINDINGS: &[(AppMode, &str, Action)] = &[
+    (AppMode::Normal, "j", Action::MoveDown),
+    (AppMode::Normal, "k", Action::MoveUp),
+    (AppMode::Normal, "h", Action::GoUp),
+    (AppMode::Normal, "l", Action::Open),
+    (AppMode::MultiSelect, "j", Action::MoveDown),
+    (AppMode::MultiSelect, "k", Action::MoveUp),
+    (AppMode::MultiSelect, "h", Action::GoUp),
+    (AppMode::MultiSelect, "l", Action::Open),
+    (AppMode::Trash, "j", Action::MoveDown),
+    (AppMode::Trash, "k", Action::MoveUp),
+    (AppMode::Jobs, "j", Action::MoveDown),
+    (AppMode::Jobs, "k", Action::MoveUp),
+    (AppMode::Help, "j", Action::MoveDown),
+    (AppMode::Help, "k", Action::MoveUp),
+    (AppMode::Clipboard, "j", Action::MoveDown),
+    (AppMode::Clipboard, "k", Action::MoveUp),
+    (AppMode::JumpList, "j", Action::MoveDown),
+    (AppMode::JumpList, "k", Action::MoveUp),
+    (AppMode::Bookmarks, "j", Action::MoveDown),
+    (AppMode::Bookmarks, "k", Action::MoveUp),
+    (AppMode::Find, "j", Action::MoveDown),
+    (AppMode::Find, "k", Action::MoveUp),
+];
+
+/// Which key chords trigger which actions, per mode
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<AppMode, Vec<(KeyChord, Action)>>, // in help order
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: HashMap::new(),
+        };
+        keymap.bind_all(DEFAULT_BINDINGS);
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Start from a named preset (`default` or `vim`)
+    pub fn preset(name: &str) -> Result<Self> {
+        let mut keymap = Self::default();
+        match name {
+            "default" => {}
+            "vim" => keymap.bind_all(VIM_BINDINGS),
+            _ => return Err(anyhow!("unknown keymap preset '{}'", name)),
+        }
+        Ok(keymap)
+    }
+
+    fn bind_all(&mut self, bindings: &[(AppMode, &str, Action)]) {
+        for (mode, chord, action) in bindings {
+            let chord = KeyChord::parse(chord).expect("built-in bindings are valid");
+            self.bind(mode.clone(), chord, Some(*action));
+        }
+    }
+
+    /// Bind `chord` to `action` in `mode`, replacing whatever it was bound to. `None` unbinds it
+    pub fn bind(&mut self, mode: AppMode, chord: KeyChord, action: Option<Action>) {
+        let bindings = self.bindings.entry(mode).or_default();
+        match (
+            bindings.iter_mut().find(|(bound, _)| *bound == chord),
+            action,
+        ) {
+            (Some(binding), Some(action)) => binding.1 = action,
+            (None, Some(action)) => bindings.push((chord, action)),
+            (_, None) => bindings.retain(|(bound, _)| *bound != chord),
+        }
+    }
+
+    /// The action a key press triggers in `mode`, if any
+    pub fn action(&self, mode: &AppMode, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(key);
+        self.bindings
+            .get(mode)?
+            .iter()
+            .find(|(bound, _)| *bound == chord)
+            .map(|(_, action)| *action)
+    }
+
+    /// Every chord bound to `action` in `mode`
+    pub fn chords(&self, mode: &AppMode, action: Action) -> Vec<KeyChord> {
+        self.bindings
+            .get(mode)
+            .map(|bindings| {
+                bindings
+                    .iter()
+                    .filter(|(_, bound)| *bound == action)
+                    .map(|(chord, _)| *chord)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Actions bound in `mode` (each once, in help order) with the chords that trigger them
+    pub fn describe(&self, mode: &AppMode) -> Vec<(Action, Vec<KeyChord>)> {
+        let mut described: Vec<(Action, Vec<KeyChord>)> = Vec::new();
+        for (chord, action) in self.bindings.get(mode).into_iter().flatten() {
+            match described.iter_mut().find(|(seen, _)| seen == action) {
+                Some((_, chords)) => chords.push(*chord),
+                None => described.push((*action, vec![*chord])),
+            }
+        }
+        described
+    }
+}
@@ -0,0 +1,116 @@
+use ignore::WalkBuilder;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::app::FileItem;
+
+/// What happens to entries matched by `.gitignore`/`.ignore` rules
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IgnoreMode {
+    #[default]
+    Off, // ignore files aren't read
+    Dim,  // ignored entries are listed, but greyed out
+    Hide, // ignored entries aren't listed
+}
+
+impl IgnoreMode {
+    pub fn next(self) -> Self {
+        match self {
+            IgnoreMode::Off => IgnoreMode::Dim,
+            IgnoreMode::Dim => IgnoreMode::Hide,
+            IgnoreMode::Hide => IgnoreMode::Off,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "off" => Some(IgnoreMode::Off),
+            "dim" => Some(IgnoreMode::Dim),
+            "hide" => Some(IgnoreMode::Hide),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IgnoreMode::Off => "off",
+            IgnoreMode::Dim => "dim",
+            IgnoreMode::Hide => "hide",
+        }
+    }
+}
+
+/// Which entries of a directory make it into the file list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ListingFilter {
+    pub show_hidden: bool, // dotfiles
+    pub ignore: IgnoreMode,
+}
+
+impl Default for ListingFilter {
+    fn default() -> Self {
+        Self {
+            show_hidden: true,
+            ignore: IgnoreMode::Off,
+        }
+    }
+}
+
+impl ListingFilter {
+    /// Drop the entries of `dir` this filter hides, and return the ignored ones that are
+    /// still listed (to be dimmed)
+    pub fn apply(&self, dir: &Path, entries: &mut Vec<FileItem>) -> HashSet<PathBuf> {
+        if !self.show_hidden {
+            entries.retain(|item| !is_hidden(&item.name));
+        }
+        if self.ignore == IgnoreMode::Off {
+            return HashSet::new();
+        }
+
+        let listed = not_ignored(dir);
+        let mut ignored = HashSet::new();
+        entries.retain(|item| {
+            if listed.contains(&item.path) {
+                return true;
+            }
+            ignored.insert(item.path.clone());
+            self.ignore == IgnoreMode::Dim
+        });
+        ignored
+    }
+
+    /// e.g. `dotfiles hidden, ignored dimmed`, or `None` when everything is listed
+    pub fn label(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if !self.show_hidden {
+            parts.push("dotfiles hidden");
+        }
+        match self.ignore {
+            IgnoreMode::Off => {}
+            IgnoreMode::Dim => parts.push("ignored dimmed"),
+            IgnoreMode::Hide => parts.push("ignored hidden"),
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// Whether `name` is a dotfile
+pub fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') && name != ".."
+}
+
+/// Entries of `dir` not matched by any `.gitignore`, `.ignore` or git exclude rule, including
+/// rules from parent directories (the same ones `git` and `rg` use)
+fn not_ignored(dir: &Path) -> HashSet<PathBuf> {
+    WalkBuilder::new(dir)
+        .max_depth(Some(1))
+        .hidden(false)
+        .require_git(false) // also honour `.gitignore` files outside a repository
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| entry.into_path())
+        .collect()
+}
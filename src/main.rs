@@ -15,10 +15,32 @@ use ratatui::{
 };
 
 mod app;
+mod bookmarks;
+mod columns;
+mod command_line;
 mod commands;
+mod config;
 mod event_handler;
+mod filter;
+mod find;
+mod frecency;
+mod git;
+mod input_history;
+mod jobs;
+mod keymap;
+mod listing;
+mod navigation;
+mod opener;
+mod panes;
+mod preview;
+mod sort;
+mod tabs;
+mod theme;
+mod trash;
+mod tree;
 mod ui;
 mod utils;
+mod watcher;
 
 use app::App;
 
@@ -59,12 +81,30 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+fn run_app<B: Backend + io::Write>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
     let tick_rate = Duration::from_millis(50);
-    let last_tick = Instant::now();
+    let mut last_tick = Instant::now();
 
     // Main app event loop:
     loop {
+        // Pick up progress from background jobs:
+        app.process_job_events();
+
+        // Pick up matches from a running `:find`:
+        app.process_search_events();
+
+        // Load the preview of the selected item in the background:
+        app.update_preview();
+
+        // List the directories beside the file list in the columns layout:
+        app.update_columns();
+
+        // Refresh whatever other programs have changed on screen:
+        app.process_watch_events();
+
+        // Pick up the git status of the current directory once it's worked out:
+        app.git.poll();
+
         // Draw the UI:
         terminal.draw(|f| ui::draw(f, app))?;
 
@@ -76,10 +116,10 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
         if crossterm::event::poll(timeout)? {
             match event::read()? {
                 Event::Key(key_event) => {
-                    if key_event.kind == crossterm::event::KeyEventKind::Press {
-                        if let Err(e) = handle_key_event(key_event, app) {
-                            app.set_error(format!("Key Event Error: {}", e));
-                        }
+                    if key_event.kind == crossterm::event::KeyEventKind::Press
+                        && let Err(e) = handle_key_event(key_event, app)
+                    {
+                        app.set_error(format!("Key Event Error: {}", e));
                     }
                 }
                 Event::Mouse(mouse_event) => {
@@ -91,11 +131,61 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
                 _ => {}
             }
         }
+        // start the next tick, so an idle loop waits for events rather than spinning:
+        if last_tick.elapsed() >= tick_rate {
+            last_tick = Instant::now();
+        }
+
+        // Run terminal programs (like `$EDITOR`) with the UI suspended:
+        if let Some(launch) = app.pending_launch.take() {
+            let name = launch
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            match suspend_while(terminal, || launch.run_in_terminal()) {
+                Ok(status) if status.success() => app.clear_messages(),
+                Ok(status) => app.set_error(format!("{} exited with {}", launch.program(), status)),
+                Err(e) => app.set_error(format!("Couldn't open '{}': {:#}", name, e)),
+            }
+            // the file may well have been edited:
+            app.previewer.invalidate();
+        }
 
         if app.should_exit {
+            // don't leave a copy or move half-done:
+            app.cancel_jobs();
             break;
         }
     }
 
     Ok(())
 }
+
+/// Give the terminal back to the shell (cooked mode, main screen) while `f` runs, then take it back
+fn suspend_while<B: Backend + io::Write, T>(
+    terminal: &mut Terminal<B>,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    let result = f();
+
+    enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture
+    )?;
+    terminal.hide_cursor()?;
+    // the screen was drawn over, so redraw all of it:
+    terminal.clear()?;
+
+    result
+}
@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// How many directories the jump list remembers
+const MAX_RECENT: usize = 100;
+
+/// Where the cursor was left in a directory
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    pub item: PathBuf, // the item under the cursor
+    pub index: usize,  // its position, for when the item has since gone
+}
+
+/// Where the user has been this session: back/forward stacks, the recently visited
+/// directories for the jump list, and the cursor position in every directory left behind
+#[derive(Debug, Default)]
+pub struct NavigationHistory {
+    back: Vec<PathBuf>,
+    forward: Vec<PathBuf>,
+    recent: Vec<PathBuf>, // most recent first, each directory once
+    cursors: HashMap<PathBuf, Cursor>,
+}
+
+impl NavigationHistory {
+    /// Record leaving `from` for somewhere new, which makes going forward impossible
+    pub fn push(&mut self, from: PathBuf) {
+        self.back.push(from);
+        self.forward.clear();
+    }
+
+    /// The previous directory (skipping any that have been deleted since), with `current`
+    /// becoming the next one forward
+    pub fn go_back(&mut self, current: &Path) -> Option<PathBuf> {
+        let previous = pop_existing(&mut self.back, current)?;
+        self.forward.push(current.to_path_buf());
+        Some(previous)
+    }
+
+    /// The directory that was last gone back from, with `current` going back onto the back stack
+    pub fn go_forward(&mut self, current: &Path) -> Option<PathBuf> {
+        let next = pop_existing(&mut self.forward, current)?;
+        self.back.push(current.to_path_buf());
+        Some(next)
+    }
+
+    /// Move `dir` to the top of the recently visited directories
+    pub fn visit(&mut self, dir: &Path) {
+        self.recent.retain(|recent| recent != dir);
+        self.recent.insert(0, dir.to_path_buf());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Recently visited directories, most recent first
+    pub fn recent(&self) -> &[PathBuf] {
+        &self.recent
+    }
+
+    pub fn save_cursor(&mut self, dir: &Path, cursor: Cursor) {
+        self.cursors.insert(dir.to_path_buf(), cursor);
+    }
+
+    pub fn cursor(&self, dir: &Path) -> Option<&Cursor> {
+        self.cursors.get(dir)
+    }
+}
+
+fn pop_existing(stack: &mut Vec<PathBuf>, current: &Path) -> Option<PathBuf> {
+    while let Some(dir) = stack.pop() {
+        if dir != current && dir.is_dir() {
+            return Some(dir);
+        }
+    }
+    None
+}
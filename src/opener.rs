@@ -0,0 +1,208 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::{
+    cell::OnceCell,
+    fs::File,
+    io::Read,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    thread,
+};
+
+use crate::preview::as_text;
+
+/// How much of a file is sniffed to guess its MIME type when `file` isn't available
+const SNIFF_BYTES: u64 = 8 * 1024;
+
+/// Decides which program opens a file, like `rifle` or `xdg-open`. The first matching rule wins
+#[derive(Debug, Clone)]
+pub struct Opener {
+    rules: Vec<OpenerRule>,
+}
+
+/// One rule, as written in the config:
+///
+/// ```toml
+/// [[opener]]
+/// ext = ["md", "txt"]         # matches by extension...
+/// mime = ["text/*"]           # ...or by MIME type (a rule with neither matches everything)
+/// command = "nvim"            # run with `sh`, the file is passed as "$@" (or appended)
+/// terminal = true             # suspend the UI while it runs, rather than spawning it detached
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenerRule {
+    #[serde(default)]
+    ext: Vec<String>,
+    #[serde(default)]
+    mime: Vec<String>,
+    command: String,
+    #[serde(default)]
+    terminal: bool,
+}
+
+/// A program about to be run on a file
+#[derive(Debug, Clone)]
+pub struct Launch {
+    pub command: String,
+    pub path: PathBuf,
+    pub terminal: bool, // needs the terminal to itself
+}
+
+impl Default for Opener {
+    fn default() -> Self {
+        let editor = OpenerRule {
+            ext: Vec::new(),
+            mime: [
+                "text/*",
+                "inode/x-empty",
+                "application/json",
+                "application/toml",
+                "application/xml",
+                "application/javascript",
+                "application/x-shellscript",
+            ]
+            .map(String::from)
+            .to_vec(),
+            command: "${VISUAL:-${EDITOR:-vi}}".to_string(),
+            terminal: true,
+        };
+        let system = OpenerRule {
+            ext: Vec::new(),
+            mime: Vec::new(),
+            command: if cfg!(target_os = "macos") {
+                "open"
+            } else {
+                "xdg-open"
+            }
+            .to_string(),
+            terminal: false,
+        };
+        Self {
+            rules: vec![editor, system],
+        }
+    }
+}
+
+impl Opener {
+    /// The default rules, with the user's `rules` tried first
+    pub fn with_rules(rules: Vec<OpenerRule>) -> Result<Self> {
+        for rule in &rules {
+            if rule.command.trim().is_empty() {
+                return Err(anyhow!("[[opener]]: empty command"));
+            }
+        }
+        let mut opener = Self::default();
+        opener.rules.splice(0..0, rules);
+        Ok(opener)
+    }
+
+    /// Pick the program to open `path` with
+    pub fn launch_for(&self, path: &Path) -> Option<Launch> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        let mime = OnceCell::new(); // only worked out if a rule needs it
+
+        self.rules
+            .iter()
+            .find(|rule| {
+                let any_condition = !rule.ext.is_empty() || !rule.mime.is_empty();
+                let ext_matches = extension.as_ref().is_some_and(|ext| {
+                    rule.ext
+                        .iter()
+                        .any(|rule_ext| rule_ext.eq_ignore_ascii_case(ext))
+                });
+                let mime_matches = !rule.mime.is_empty() && {
+                    let mime = mime.get_or_init(|| mime_type(path));
+                    rule.mime.iter().any(|pattern| mime_matches(pattern, mime))
+                };
+                !any_condition || ext_matches || mime_matches
+            })
+            .map(|rule| Launch {
+                command: rule.command.clone(),
+                path: path.to_path_buf(),
+                terminal: rule.terminal,
+            })
+    }
+}
+
+impl Launch {
+    /// The program's name, for status messages
+    pub fn program(&self) -> &str {
+        self.command.split_whitespace().next().unwrap_or_default()
+    }
+
+    fn process(&self) -> Command {
+        // like rifle, the file is passed to the shell as "$@":
+        let script = if self.command.contains("$@") || self.command.contains("$1") {
+            self.command.clone()
+        } else {
+            format!("{} \"$@\"", self.command)
+        };
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(script).arg("sh").arg(&self.path);
+        if let Some(parent) = self.path.parent() {
+            process.current_dir(parent);
+        }
+        process
+    }
+
+    /// Run the program and wait for it. The caller is expected to have handed it the terminal
+    pub fn run_in_terminal(&self) -> Result<ExitStatus> {
+        self.process()
+            .status()
+            .with_context(|| format!("running '{}'", self.program()))
+    }
+
+    /// Start the program in its own process group with no access to the terminal, without waiting
+    pub fn spawn_detached(&self) -> Result<()> {
+        let mut child = self
+            .process()
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()
+            .with_context(|| format!("starting '{}'", self.program()))?;
+
+        // reap it once it exits, so it doesn't linger as a zombie:
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/// Whether a MIME type matches a pattern like `text/plain` or `image/*`
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(kind) => mime.split('/').next() == Some(kind),
+        None => pattern == mime,
+    }
+}
+
+/// The MIME type of a file from `file --mime-type`, or a guess from its contents if that fails
+fn mime_type(path: &Path) -> String {
+    let output = Command::new("file")
+        .args(["--brief", "--dereference", "--mime-type", "--"])
+        .arg(path)
+        .stderr(Stdio::null())
+        .output();
+    if let Ok(output) = output
+        && output.status.success()
+    {
+        let mime = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !mime.is_empty() {
+            return mime;
+        }
+    }
+
+    let mut bytes = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(SNIFF_BYTES).read_to_end(&mut bytes));
+    match read {
+        Ok(0) => "inode/x-empty",
+        Ok(_) if as_text(&bytes).is_some() => "text/plain",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
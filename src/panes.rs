@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use crate::{
+    app::{App, ClipboardOperation},
+    columns::ViewLayout,
+    commands::{CopyOptions, PastePlan},
+};
+
+impl App {
+    /// Make the other pane of the dual-pane layout the active one
+    pub fn switch_pane(&mut self) {
+        if self.layout != ViewLayout::Dual {
+            self.set_status("Panes are only in the dual-pane layout (L to switch)".to_string());
+            return;
+        }
+        let other = std::mem::take(&mut self.other_pane);
+        self.other_pane = self.save_tab();
+        self.load_tab(other);
+        self.left_pane_active = !self.left_pane_active;
+
+        // what was cached for either pane may be out of date by now:
+        self.columns.invalidate(&self.current_path);
+        self.columns.invalidate(&self.other_pane.path);
+    }
+
+    /// Copy or move `sources` straight into the other pane's directory, without going
+    /// through the clipboard
+    pub fn transfer_to_other_pane(
+        &mut self,
+        operation: ClipboardOperation,
+        sources: Vec<PathBuf>,
+        options: CopyOptions,
+    ) {
+        if sources.is_empty() {
+            return;
+        }
+        let destination = self.other_pane.path.clone();
+        if let Some(source) = sources
+            .iter()
+            .find(|source| source.is_dir() && destination.starts_with(source))
+        {
+            self.set_error(format!("Can't put '{}' inside itself", source.display()));
+            return;
+        }
+        match PastePlan::new(operation, sources, destination, options) {
+            Ok(plan) => self.transfer(plan),
+            Err(e) => self.set_error(format!("Couldn't transfer: {:#}", e)),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::{Result, anyhow};
+use flate2::read::GzDecoder;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::{
+    fs::{self, File},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+use crate::{app::FileItem, sort::SortOrder, utils::format_size};
+
+/// How much of a file is read for a text or hex preview
+const MAX_PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// How many lines of a text file are shown
+const MAX_PREVIEW_LINES: usize = 200;
+
+/// How much of a binary file is shown as a hex dump
+const MAX_HEX_BYTES: usize = 4 * 1024;
+
+/// How many directory children or archive members are listed
+const MAX_PREVIEW_ENTRIES: usize = 500;
+
+/// How much of a tar archive is read (after decompressing) to list its members
+const MAX_ARCHIVE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// What the preview pane shows for the selected item
+#[derive(Debug, Clone)]
+pub enum PreviewContent {
+    Text(Vec<Line<'static>>),
+    Directory(Vec<FileItem>),
+    Hex(Vec<u8>),
+    Archive(Vec<String>),
+    Message(String), // nothing to preview, or it couldn't be read
+}
+
+#[derive(Debug, Clone)]
+pub struct Preview {
+    pub path: PathBuf,
+    pub content: PreviewContent,
+    pub truncated: bool, // only part of the item is shown
+}
+
+/// Loads previews on a worker thread, so large or slow files never hold up the UI
+pub struct Previewer {
+    requests: Sender<PathBuf>,
+    results: Receiver<Preview>,
+    requested: Option<PathBuf>,
+    current: Option<Preview>,
+}
+
+impl Previewer {
+    /// `syntax_theme` names a syntect theme for highlighting, or `None` for plain text
+    pub fn new(syntax_theme: Option<String>) -> Self {
+        let (request_sender, request_receiver) = mpsc::channel::<PathBuf>();
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut highlighter = Highlighter::new(syntax_theme);
+            while let Ok(mut path) = request_receiver.recv() {
+                // skip straight to the newest request, the others are already out of date:
+                while let Ok(newer) = request_receiver.try_recv() {
+                    path = newer;
+                }
+                let preview = load_preview(&path, &mut highlighter);
+                if result_sender.send(preview).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests: request_sender,
+            results: result_receiver,
+            requested: None,
+            current: None,
+        }
+    }
+
+    /// Ask for a preview of `path`, unless it is already shown or being loaded
+    pub fn request(&mut self, path: Option<&Path>) {
+        if self.requested.as_deref() == path {
+            return;
+        }
+        self.requested = path.map(Path::to_path_buf);
+        if let Some(path) = path {
+            let _ = self.requests.send(path.to_path_buf());
+        }
+    }
+
+    /// Forget the loaded preview, so it's loaded again the next time it is requested
+    pub fn invalidate(&mut self) {
+        self.requested = None;
+    }
+
+    /// Pick up finished previews, dropping any for items that are no longer selected
+    pub fn poll(&mut self) {
+        while let Ok(preview) = self.results.try_recv() {
+            if self.requested.as_ref() == Some(&preview.path) {
+                self.current = Some(preview);
+            }
+        }
+    }
+
+    /// The preview of the requested item, or `None` while it's still loading
+    pub fn current(&self) -> Option<&Preview> {
+        self.current
+            .as_ref()
+            .filter(|preview| self.requested.as_ref() == Some(&preview.path))
+    }
+}
+
+fn load_preview(path: &Path, highlighter: &mut Highlighter) -> Preview {
+    let (content, truncated) = match preview_content(path, highlighter) {
+        Ok(loaded) => loaded,
+        Err(e) => (
+            PreviewContent::Message(format!("Can't preview: {}", e)),
+            false,
+        ),
+    };
+    Preview {
+        path: path.to_path_buf(),
+        content,
+        truncated,
+    }
+}
+
+fn preview_content(path: &Path, highlighter: &mut Highlighter) -> Result<(PreviewContent, bool)> {
+    // follow symlinks, so a link previews whatever it points to:
+    let metadata = fs::metadata(path)?;
+    if metadata.is_dir() {
+        let mut items = FileItem::read_dir(path, &SortOrder::default())?;
+        let truncated = items.len() > MAX_PREVIEW_ENTRIES;
+        items.truncate(MAX_PREVIEW_ENTRIES);
+        return Ok((PreviewContent::Directory(items), truncated));
+    }
+    if !metadata.is_file() {
+        // reading a FIFO or device could block forever:
+        return Ok((
+            PreviewContent::Message("No preview for special files".to_string()),
+            false,
+        ));
+    }
+
+    if let Some((members, cut_short)) = archive_members(path)? {
+        let truncated = cut_short || members.len() > MAX_PREVIEW_ENTRIES;
+        let members = members.into_iter().take(MAX_PREVIEW_ENTRIES).collect();
+        return Ok((PreviewContent::Archive(members), truncated));
+    }
+
+    let mut bytes = Vec::new();
+    File::open(path)?
+        .take(MAX_PREVIEW_BYTES)
+        .read_to_end(&mut bytes)?;
+    let cut_short = metadata.len() > bytes.len() as u64;
+
+    match as_text(&bytes) {
+        Some(text) => {
+            let line_count = text.lines().count();
+            let lines = highlighter.highlight(path, text);
+            Ok((
+                PreviewContent::Text(lines),
+                cut_short || line_count > MAX_PREVIEW_LINES,
+            ))
+        }
+        None => {
+            let truncated = metadata.len() > MAX_HEX_BYTES as u64;
+            bytes.truncate(MAX_HEX_BYTES);
+            Ok((PreviewContent::Hex(bytes), truncated))
+        }
+    }
+}
+
+/// The bytes as text, or `None` if they look binary
+pub fn as_text(bytes: &[u8]) -> Option<&str> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        // the read may have stopped part way through a multi-byte character:
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    }
+}
+
+/// Names (and sizes) of the members of a zip or tar archive, and whether the listing stopped
+/// early because the archive is too big to read through, or `None` if `path` isn't one
+fn archive_members(path: &Path) -> Result<Option<(Vec<String>, bool)>> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let is_any = |suffixes: &[&str]| suffixes.iter().any(|suffix| name.ends_with(suffix));
+
+    if is_any(&[
+        ".zip", ".jar", ".war", ".apk", ".whl", ".epub", ".docx", ".xlsx", ".pptx",
+    ]) {
+        let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))
+            .map_err(|e| anyhow!("not a valid zip archive ({})", e))?;
+        let mut members = Vec::new();
+        for i in 0..archive.len().min(MAX_PREVIEW_ENTRIES + 1) {
+            let member = archive.by_index_raw(i)?;
+            members.push(member_line(member.name(), member.is_dir(), member.size()));
+        }
+        Ok(Some((members, false)))
+    } else if is_any(&[".tar"]) {
+        tar_members(File::open(path)?).map(Some)
+    } else if is_any(&[".tar.gz", ".tgz"]) {
+        tar_members(GzDecoder::new(File::open(path)?)).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn tar_members(reader: impl Read) -> Result<(Vec<String>, bool)> {
+    // listing means reading past every member's contents, so give up after a while:
+    let mut archive = tar::Archive::new(reader.take(MAX_ARCHIVE_BYTES));
+    let mut members = Vec::new();
+    let mut error = None;
+    // entries are spread through the whole archive, so stop once there are enough:
+    for entry in archive.entries()?.take(MAX_PREVIEW_ENTRIES + 1) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        };
+        let header = entry.header();
+        members.push(member_line(
+            &entry.path()?.to_string_lossy(),
+            header.entry_type().is_dir(),
+            header.size().unwrap_or(0),
+        ));
+    }
+
+    // running into the limit ends the archive early, which isn't the archive's fault:
+    let cut_short = archive.into_inner().limit() == 0;
+    match error {
+        Some(e) if !cut_short => Err(e.into()),
+        _ => Ok((members, cut_short)),
+    }
+}
+
+fn member_line(name: &str, is_dir: bool, size: u64) -> String {
+    if is_dir {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, format_size(size))
+    }
+}
+
+/// Syntax highlighting for text previews. The syntax definitions are only loaded on first use
+struct Highlighter {
+    theme_name: Option<String>,
+    loaded: Option<(SyntaxSet, ThemeSet)>,
+}
+
+impl Highlighter {
+    fn new(theme_name: Option<String>) -> Self {
+        Self {
+            theme_name,
+            loaded: None,
+        }
+    }
+
+    fn highlight(&mut self, path: &Path, text: &str) -> Vec<Line<'static>> {
+        let plain = |text: &str| {
+            text.lines()
+                .take(MAX_PREVIEW_LINES)
+                .map(|line| Line::from(expand_tabs(line)))
+                .collect()
+        };
+        let Some(theme_name) = &self.theme_name else {
+            return plain(text);
+        };
+        let (syntaxes, themes) = self.loaded.get_or_insert_with(|| {
+            (
+                SyntaxSet::load_defaults_newlines(),
+                ThemeSet::load_defaults(),
+            )
+        });
+        let Some(theme) = themes.themes.get(theme_name) else {
+            return plain(text);
+        };
+        let syntax = syntaxes
+            .find_syntax_for_file(path)
+            .ok()
+            .flatten()
+            .or_else(|| syntaxes.find_syntax_by_first_line(text.lines().next().unwrap_or("")))
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+        let mut highlight = HighlightLines::new(syntax, theme);
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(text).take(MAX_PREVIEW_LINES) {
+            let Ok(regions) = highlight.highlight_line(line, syntaxes) else {
+                return plain(text);
+            };
+            let spans = regions
+                .into_iter()
+                .map(|(style, piece)| {
+                    let piece = expand_tabs(piece.trim_end_matches(['\n', '\r']));
+                    // keep the terminal's own background:
+                    let mut span_style = Style::default().fg(Color::Rgb(
+                        style.foreground.r,
+                        style.foreground.g,
+                        style.foreground.b,
+                    ));
+                    if style.font_style.contains(FontStyle::BOLD) {
+                        span_style = span_style.add_modifier(Modifier::BOLD);
+                    }
+                    if style.font_style.contains(FontStyle::ITALIC) {
+                        span_style = span_style.add_modifier(Modifier::ITALIC);
+                    }
+                    Span::styled(piece, span_style)
+                })
+                .collect::<Vec<_>>();
+            lines.push(Line::from(spans));
+        }
+        lines
+    }
+}
+
+/// Tabs don't render reliably inside a ratatui paragraph, so replace them with spaces
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', "    ")
+}
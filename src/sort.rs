@@ -0,0 +1,236 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    app::{FileItem, FileKind},
+    utils::{data_home, get_file_extension, load_toml, save_toml},
+};
+
+/// What the file list is ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Natural, // like name, but numbers compare by value so `file2` comes before `file10`
+    Size,
+    #[serde(rename = "mtime")]
+    Modified,
+    Extension,
+    Type, // kind of entry (symlink, executable, ...), then extension
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 6] = [
+        SortKey::Name,
+        SortKey::Natural,
+        SortKey::Size,
+        SortKey::Modified,
+        SortKey::Extension,
+        SortKey::Type,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "name" => Some(SortKey::Name),
+            "natural" | "version" => Some(SortKey::Natural),
+            "size" => Some(SortKey::Size),
+            "mtime" | "modified" => Some(SortKey::Modified),
+            "extension" | "ext" => Some(SortKey::Extension),
+            "type" => Some(SortKey::Type),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Natural => "natural",
+            SortKey::Size => "size",
+            SortKey::Modified => "mtime",
+            SortKey::Extension => "extension",
+            SortKey::Type => "type",
+        }
+    }
+
+    /// The key after this one, for cycling through them
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&key| key == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// How directory listings are sorted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub reverse: bool,    // descending rather than ascending
+    pub dirs_first: bool, // directories before files, whichever way the rest is sorted
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        Self {
+            key: SortKey::default(),
+            reverse: false,
+            dirs_first: true,
+        }
+    }
+}
+
+impl SortOrder {
+    pub fn sort(&self, items: &mut [FileItem]) {
+        items.sort_by(|a, b| {
+            let dirs = if self.dirs_first {
+                b.is_dir.cmp(&a.is_dir)
+            } else {
+                Ordering::Equal
+            };
+            dirs.then_with(|| {
+                let ordering = self.compare(a, b);
+                if self.reverse {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+        });
+    }
+
+    fn compare(&self, a: &FileItem, b: &FileItem) -> Ordering {
+        let by_name = || {
+            a.name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then_with(|| a.name.cmp(&b.name))
+        };
+        let extension = |item: &FileItem| {
+            get_file_extension(&item.name)
+                .unwrap_or_default()
+                .to_lowercase()
+        };
+        match self.key {
+            SortKey::Name => by_name(),
+            SortKey::Natural => natural_cmp(&a.name, &b.name).then_with(by_name),
+            SortKey::Size => a.size.cmp(&b.size).then_with(by_name),
+            SortKey::Modified => a.modified.cmp(&b.modified).then_with(by_name),
+            SortKey::Extension => extension(a).cmp(&extension(b)).then_with(by_name),
+            SortKey::Type => kind_rank(a.kind)
+                .cmp(&kind_rank(b.kind))
+                .then_with(|| extension(a).cmp(&extension(b)))
+                .then_with(by_name),
+        }
+    }
+
+    /// e.g. `size ↓`, with `dirs mixed` when directories aren't kept first
+    pub fn label(&self) -> String {
+        let mut label = format!(
+            "{} {}",
+            self.key.label(),
+            if self.reverse { "↓" } else { "↑" }
+        );
+        if !self.dirs_first {
+            label.push_str(", dirs mixed");
+        }
+        label
+    }
+}
+
+/// Where each kind of entry goes when sorting by type
+fn kind_rank(kind: FileKind) -> u8 {
+    match kind {
+        FileKind::Directory => 0,
+        FileKind::Symlink | FileKind::BrokenSymlink => 1,
+        FileKind::Executable => 2,
+        FileKind::File => 3,
+        FileKind::Fifo | FileKind::Socket => 4,
+        FileKind::BlockDevice | FileKind::CharDevice => 5,
+    }
+}
+
+/// Compare names the way people count: runs of digits by their value, the rest ignoring case
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                // a longer number (without leading zeros) is bigger:
+                x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()))
+            }
+            (Some(x), Some(y)) => {
+                a.next();
+                b.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        number.push(c);
+    }
+    number
+}
+
+/// The sort order picked in each directory, saved to `$XDG_DATA_HOME/clexp/sort.toml` when
+/// sorting per directory is turned on
+#[derive(Debug, Clone, Default)]
+pub struct SortMemory {
+    file: Option<PathBuf>, // `None` when there's no data directory to save to
+    dirs: BTreeMap<PathBuf, SortOrder>,
+}
+
+impl SortMemory {
+    pub fn load() -> Result<Self> {
+        let mut memory = Self {
+            file: data_home().map(|dir| dir.join("clexp").join("sort.toml")),
+            dirs: BTreeMap::new(),
+        };
+        memory.reload()?;
+        Ok(memory)
+    }
+
+    /// Read the saved orders again, picking up changes made by other instances
+    fn reload(&mut self) -> Result<()> {
+        if let Some(dirs) = load_toml(self.file.as_deref())? {
+            self.dirs = dirs;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        save_toml(self.file.as_deref(), &self.dirs)
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<SortOrder> {
+        self.dirs.get(dir).copied()
+    }
+
+    /// Remember `order` for `dir`, and save
+    pub fn set(&mut self, dir: &Path, order: SortOrder) -> Result<()> {
+        self.reload()?;
+        self.dirs.insert(dir.to_path_buf(), order);
+        self.save()
+    }
+}
@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+
+use crate::{app::App, filter::Filter, navigation::NavigationHistory, sort::SortOrder};
+
+/// A directory open in a tab. The active tab's state lives in `App` itself (its slot here
+/// is left empty), the others are kept here until they're switched to
+#[derive(Debug, Default)]
+pub struct Tab {
+    pub path: PathBuf,
+    pub cursor: Option<PathBuf>, // item under the cursor
+    pub selection: Vec<PathBuf>, // items picked in multi-select mode
+    pub filter: Filter,
+    pub sort: SortOrder,
+    navigation: NavigationHistory, // back/forward and cursor memory are per tab
+}
+
+impl Tab {
+    pub fn new(path: PathBuf, sort: SortOrder) -> Self {
+        Self {
+            path,
+            sort,
+            ..Self::default()
+        }
+    }
+
+    /// Name shown in the tab bar
+    pub fn label(path: &Path) -> String {
+        match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => "/".to_string(),
+        }
+    }
+}
+
+impl App {
+    /// Open a tab on `path` after the current one, and switch to it
+    pub fn new_tab(&mut self, path: PathBuf) {
+        let tab = Tab::new(path, self.sort);
+        let saved = self.save_tab();
+        self.tabs[self.active_tab] = saved;
+        self.active_tab += 1;
+        self.tabs.insert(self.active_tab, tab);
+        self.restore_tab();
+        self.navigation.visit(&self.current_path);
+    }
+
+    /// Close the current tab, unless it's the only one
+    pub fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            self.set_status("Can't close the last tab".to_string());
+            return;
+        }
+        self.tabs.remove(self.active_tab);
+        self.active_tab = self.active_tab.min(self.tabs.len() - 1);
+        self.restore_tab();
+    }
+
+    /// Switch to the tab at `index` (counting from 0)
+    pub fn switch_tab(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
+        }
+        let saved = self.save_tab();
+        self.tabs[self.active_tab] = saved;
+        self.active_tab = index;
+        self.restore_tab();
+    }
+
+    /// Switch to tab `number` as numbered in the tab bar (counting from 1)
+    pub fn go_to_tab(&mut self, number: usize) {
+        if number > self.tabs.len() {
+            self.set_error(format!("No tab {} (there are {})", number, self.tabs.len()));
+            return;
+        }
+        self.switch_tab(number - 1);
+    }
+
+    pub fn next_tab(&mut self) {
+        self.switch_tab((self.active_tab + 1) % self.tabs.len());
+    }
+
+    pub fn prev_tab(&mut self) {
+        self.switch_tab((self.active_tab + self.tabs.len() - 1) % self.tabs.len());
+    }
+
+    /// The state of the current tab, to put aside while another one is shown
+    pub fn save_tab(&mut self) -> Tab {
+        Tab {
+            path: self.current_path.clone(),
+            cursor: self.file_list.selected().map(|item| item.path.clone()),
+            selection: self
+                .selected_items()
+                .iter()
+                .map(|item| item.path.clone())
+                .collect(),
+            filter: self.filter.clone(),
+            sort: self.sort,
+            navigation: std::mem::take(&mut self.navigation),
+        }
+    }
+
+    /// Show the tab at `active_tab`, listing its directory again
+    fn restore_tab(&mut self) {
+        let tab = std::mem::take(&mut self.tabs[self.active_tab]);
+        self.load_tab(tab);
+        self.set_status(format!(
+            "Tab {}/{}: {}",
+            self.active_tab + 1,
+            self.tabs.len(),
+            self.current_path.display()
+        ));
+    }
+
+    /// Take over the state put aside in `tab`, listing its directory again
+    pub fn load_tab(&mut self, tab: Tab) {
+        self.current_path = tab.path;
+        self.filter = tab.filter;
+        self.sort = tab.sort;
+        self.navigation = tab.navigation;
+
+        // the multi-selection is by index, which means nothing in another directory:
+        self.selection.clear();
+        if let Err(e) = self.refresh_file_list() {
+            self.set_error(format!("Failed to refresh: {}", e));
+        }
+        self.selection = self
+            .file_list
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| tab.selection.contains(&item.path))
+            .map(|(i, _)| i)
+            .collect();
+        if let Some(cursor) = tab.cursor {
+            self.select_path(&cursor);
+        }
+    }
+}
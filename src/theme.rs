@@ -0,0 +1,483 @@
+use anyhow::{Result, anyhow};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::BorderType,
+};
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{
+    app::{AppMode, FileItem, FileKind},
+    git::GitMarker,
+};
+
+/// Colours and borders used throughout the UI
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: &'static str,
+    pub border_type: BorderType,
+    pub directory: Style, // directories in file lists (unless LS_COLORS says otherwise)
+    pub selected: Style,  // items picked in multi-select mode
+    pub highlight: Style, // the row under the cursor
+    pub filter_match: Style, // characters matched by the filter
+    pub ignored: Style,   // entries matched by .gitignore/.ignore, when dimmed
+    pub accent: Style,    // text being typed (input modal, filter bar)
+    pub status: Style,    // status messages
+    pub error: Style,     // error messages
+    pub hint: Style,      // key hints in the status bar
+    pub muted: Style,     // less important text, like modal hints
+    pub warning: Style,   // confirm prompts for destructive actions
+    pub conflict: Style,  // paste conflict prompt
+    pub job_running: Style, // progress bar of a running job
+    pub job_paused: Style, // progress bar of a paused job
+    pub focused: Style,   // border of a focused panel
+    pub git_conflicted: Style, // git status markers in file lists
+    pub git_modified: Style,
+    pub git_staged: Style,
+    pub git_untracked: Style,
+    pub git_ignored: Style,
+    pub syntax: Option<String>, // syntect theme for highlighting previews, `None` for plain text
+    modes: HashMap<AppMode, Style>, // mode indicator in the header
+    pub file_types: LsColors,   // per-filetype colours for file entries
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 4] = ["dark", "light", "high-contrast", "no-color"];
+
+    /// A built-in theme by name
+    pub fn named(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            "no-color" => Ok(Self::no_color()),
+            _ => Err(anyhow!(
+                "unknown theme '{}' (expected one of: {})",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// Whether colour has been turned off with the `NO_COLOR` environment variable (see no-color.org)
+    pub fn no_color_requested() -> bool {
+        std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            name: "dark",
+            border_type: BorderType::Rounded,
+            directory: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD),
+            selected: Style::default().bg(Color::LightYellow).fg(Color::Black),
+            highlight: Style::default()
+                .bg(Color::Gray)
+                .add_modifier(Modifier::BOLD),
+            filter_match: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            ignored: Style::default().add_modifier(Modifier::DIM),
+            accent: Style::default().fg(Color::Yellow),
+            status: Style::default().fg(Color::Cyan),
+            error: Style::default().fg(Color::Red),
+            hint: Style::default().fg(Color::White),
+            muted: Style::default().fg(Color::DarkGray),
+            warning: Style::default().fg(Color::Red),
+            conflict: Style::default().fg(Color::LightYellow),
+            job_running: Style::default().fg(Color::Green),
+            job_paused: Style::default().fg(Color::Yellow),
+            focused: Style::default().fg(Color::LightBlue),
+            git_conflicted: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            git_modified: Style::default().fg(Color::Yellow),
+            git_staged: Style::default().fg(Color::Green),
+            git_untracked: Style::default().fg(Color::LightMagenta),
+            git_ignored: Style::default().fg(Color::DarkGray),
+            syntax: Some("base16-ocean.dark".to_string()),
+            modes: mode_styles([
+                Color::Green,
+                Color::Magenta,
+                Color::Yellow,
+                Color::Blue,
+                Color::Cyan,
+                Color::Red,
+                Color::LightGreen,
+                Color::LightRed,
+                Color::LightBlue,
+                Color::LightYellow,
+                Color::LightMagenta,
+                Color::LightCyan,
+                Color::LightGreen,
+                Color::Yellow,
+            ]),
+            file_types: LsColors::default(),
+        }
+    }
+
+    /// For terminals with a light background, avoiding pale foreground colours
+    pub fn light() -> Self {
+        Self {
+            name: "light",
+            border_type: BorderType::Rounded,
+            directory: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            selected: Style::default().bg(Color::Yellow).fg(Color::Black),
+            highlight: Style::default()
+                .bg(Color::LightBlue)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            filter_match: Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            ignored: Style::default().add_modifier(Modifier::DIM),
+            accent: Style::default().fg(Color::Magenta),
+            status: Style::default().fg(Color::Blue),
+            error: Style::default().fg(Color::Red),
+            hint: Style::default().fg(Color::Black),
+            muted: Style::default().fg(Color::DarkGray),
+            warning: Style::default().fg(Color::Red),
+            conflict: Style::default().fg(Color::Magenta),
+            job_running: Style::default().fg(Color::Green),
+            job_paused: Style::default().fg(Color::Magenta),
+            focused: Style::default().fg(Color::Blue),
+            git_conflicted: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            git_modified: Style::default().fg(Color::Magenta),
+            git_staged: Style::default().fg(Color::Green),
+            git_untracked: Style::default().fg(Color::Blue),
+            git_ignored: Style::default().fg(Color::DarkGray),
+            syntax: Some("InspiredGitHub".to_string()),
+            modes: mode_styles([
+                Color::Green,
+                Color::Magenta,
+                Color::Blue,
+                Color::Blue,
+                Color::Cyan,
+                Color::Red,
+                Color::Green,
+                Color::Red,
+                Color::Blue,
+                Color::Magenta,
+                Color::Magenta,
+                Color::Cyan,
+                Color::Green,
+                Color::Red,
+            ]),
+            file_types: LsColors::default(),
+        }
+    }
+
+    /// Bright colours on black/white blocks, and heavy borders
+    pub fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            name: "high-contrast",
+            border_type: BorderType::Thick,
+            directory: bold.fg(Color::LightCyan),
+            selected: bold.bg(Color::LightYellow).fg(Color::Black),
+            highlight: bold.bg(Color::White).fg(Color::Black),
+            filter_match: bold
+                .bg(Color::LightMagenta)
+                .fg(Color::Black)
+                .add_modifier(Modifier::UNDERLINED),
+            ignored: Style::default().add_modifier(Modifier::ITALIC),
+            accent: bold.fg(Color::LightYellow),
+            status: bold.fg(Color::White),
+            error: bold.bg(Color::Red).fg(Color::White),
+            hint: bold.fg(Color::White),
+            muted: Style::default().fg(Color::White),
+            warning: bold.fg(Color::LightRed),
+            conflict: bold.fg(Color::LightYellow),
+            job_running: Style::default().fg(Color::LightGreen),
+            job_paused: Style::default().fg(Color::LightYellow),
+            focused: bold.fg(Color::White),
+            git_conflicted: bold.bg(Color::Red).fg(Color::White),
+            git_modified: bold.fg(Color::LightYellow),
+            git_staged: bold.fg(Color::LightGreen),
+            git_untracked: bold.fg(Color::LightMagenta),
+            git_ignored: Style::default().fg(Color::White),
+            syntax: Some("base16-eighties.dark".to_string()),
+            modes: mode_styles([
+                Color::LightGreen,
+                Color::LightMagenta,
+                Color::LightYellow,
+                Color::LightBlue,
+                Color::LightCyan,
+                Color::LightRed,
+                Color::LightGreen,
+                Color::LightRed,
+                Color::LightBlue,
+                Color::LightYellow,
+                Color::LightMagenta,
+                Color::LightCyan,
+                Color::LightGreen,
+                Color::LightYellow,
+            ]),
+            file_types: LsColors::default(),
+        }
+    }
+
+    /// No colours at all, only text attributes
+    pub fn no_color() -> Self {
+        let plain = Style::default();
+        Self {
+            name: "no-color",
+            border_type: BorderType::Plain,
+            directory: plain.add_modifier(Modifier::BOLD),
+            selected: plain.add_modifier(Modifier::BOLD | Modifier::ITALIC),
+            highlight: plain.add_modifier(Modifier::REVERSED),
+            filter_match: plain.add_modifier(Modifier::UNDERLINED),
+            ignored: plain.add_modifier(Modifier::DIM),
+            accent: plain,
+            status: plain,
+            error: plain.add_modifier(Modifier::BOLD),
+            hint: plain,
+            muted: plain.add_modifier(Modifier::DIM),
+            warning: plain.add_modifier(Modifier::BOLD),
+            conflict: plain.add_modifier(Modifier::BOLD),
+            job_running: plain,
+            job_paused: plain.add_modifier(Modifier::DIM),
+            focused: plain.add_modifier(Modifier::BOLD),
+            git_conflicted: plain.add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            git_modified: plain.add_modifier(Modifier::BOLD),
+            git_staged: plain,
+            git_untracked: plain,
+            git_ignored: plain.add_modifier(Modifier::DIM),
+            syntax: None,
+            modes: HashMap::new(),
+            file_types: LsColors::default(),
+        }
+    }
+
+    /// Style of the mode indicator in the header
+    pub fn mode_style(&self, mode: &AppMode) -> Style {
+        self.modes.get(mode).copied().unwrap_or_default()
+    }
+
+    /// Style for an entry in a file list, before any selection or highlighting
+    pub fn file_style(&self, item: &FileItem) -> Style {
+        let base = if item.kind == FileKind::Directory {
+            self.directory
+        } else {
+            Style::default()
+        };
+        match self.file_types.style_for(item) {
+            Some(style) => base.patch(style),
+            None => base,
+        }
+    }
+
+    /// Style of a git status marker
+    pub fn git_style(&self, marker: GitMarker) -> Style {
+        match marker {
+            GitMarker::Conflicted => self.git_conflicted,
+            GitMarker::Modified => self.git_modified,
+            GitMarker::Staged => self.git_staged,
+            GitMarker::Untracked => self.git_untracked,
+            GitMarker::Ignored => self.git_ignored,
+        }
+    }
+
+    /// Override one of the theme's styles from the config, e.g. `directory = "bold blue"`
+    pub fn set_style(&mut self, element: &str, spec: &str) -> Result<()> {
+        let style = parse_style(spec)?;
+        let target = match element {
+            "directory" => &mut self.directory,
+            "selected" => &mut self.selected,
+            "highlight" => &mut self.highlight,
+            "filter_match" => &mut self.filter_match,
+            "ignored" => &mut self.ignored,
+            "accent" => &mut self.accent,
+            "status" => &mut self.status,
+            "error" => &mut self.error,
+            "hint" => &mut self.hint,
+            "muted" => &mut self.muted,
+            "warning" => &mut self.warning,
+            "conflict" => &mut self.conflict,
+            "job_running" => &mut self.job_running,
+            "job_paused" => &mut self.job_paused,
+            "focused" => &mut self.focused,
+            "git_conflicted" => &mut self.git_conflicted,
+            "git_modified" => &mut self.git_modified,
+            "git_staged" => &mut self.git_staged,
+            "git_untracked" => &mut self.git_untracked,
+            "git_ignored" => &mut self.git_ignored,
+            _ => return Err(anyhow!("unknown theme style '{}'", element)),
+        };
+        *target = style;
+        Ok(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// Mode indicator styles, for the modes in the order listed here
+fn mode_styles(colors: [Color; 14]) -> HashMap<AppMode, Style> {
+    [
+        AppMode::Normal,
+        AppMode::Help,
+        AppMode::MultiSelect,
+        AppMode::Input,
+        AppMode::Command,
+        AppMode::Confirm,
+        AppMode::Clipboard,
+        AppMode::Trash,
+        AppMode::Jobs,
+        AppMode::Conflict,
+        AppMode::JumpList,
+        AppMode::Bookmarks,
+        AppMode::Jumper,
+        AppMode::Find,
+    ]
+    .into_iter()
+    .zip(colors)
+    .map(|(mode, color)| (mode, Style::default().fg(color)))
+    .collect()
+}
+
+/// Parse a style written as words, e.g. `bold blue`, `black on yellow` or `underlined #ff8800`
+pub fn parse_style(spec: &str) -> Result<Style> {
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        style = match word.to_lowercase().as_str() {
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underline" | "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reverse" | "reversed" => style.add_modifier(Modifier::REVERSED),
+            "crossed" | "strikethrough" => style.add_modifier(Modifier::CROSSED_OUT),
+            "on" => {
+                let color = words
+                    .next()
+                    .ok_or_else(|| anyhow!("missing background colour in '{}'", spec))?;
+                style.bg(parse_color(color)?)
+            }
+            _ => style.fg(parse_color(word)?),
+        };
+    }
+    Ok(style)
+}
+
+fn parse_color(s: &str) -> Result<Color> {
+    Color::from_str(s).map_err(|_| anyhow!("unknown colour '{}'", s))
+}
+
+/// Per-filetype colours, in the format of the `LS_COLORS` environment variable
+/// (e.g. `di=01;34:ln=01;36:ex=01;32:*.tar=01;31`)
+#[derive(Debug, Clone, Default)]
+pub struct LsColors {
+    kinds: HashMap<String, Style>, // two-letter file type codes (`di`, `ln`, `ex`, ...)
+    extensions: Vec<(String, Style)>, // `*<suffix>` patterns, later ones take priority
+}
+
+impl LsColors {
+    /// Colours from `$LS_COLORS`, if set
+    pub fn from_env() -> Self {
+        let mut colors = Self::default();
+        if let Ok(spec) = std::env::var("LS_COLORS") {
+            colors.extend(&spec);
+        }
+        colors
+    }
+
+    /// Add the entries of an `LS_COLORS`-style string (entries that don't parse are skipped, like `ls`)
+    pub fn extend(&mut self, spec: &str) {
+        for entry in spec.split(':') {
+            if let Some((key, codes)) = entry.split_once('=') {
+                self.set(key, codes);
+            }
+        }
+    }
+
+    /// Set the colour of a file type (`di`) or suffix (`*.rs`) from SGR codes like `01;34`
+    pub fn set(&mut self, key: &str, codes: &str) {
+        let style = parse_sgr(codes);
+        match key.strip_prefix('*') {
+            Some(suffix) => self.extensions.push((suffix.to_lowercase(), style)),
+            None => {
+                self.kinds.insert(key.to_string(), style);
+            }
+        }
+    }
+
+    pub fn style_for(&self, item: &FileItem) -> Option<Style> {
+        let kind = |code: &str| self.kinds.get(code).copied();
+        match item.kind {
+            FileKind::Directory => kind("di"),
+            FileKind::Symlink => kind("ln"),
+            FileKind::BrokenSymlink => kind("or").or_else(|| kind("ln")),
+            FileKind::Fifo => kind("pi"),
+            FileKind::Socket => kind("so"),
+            FileKind::BlockDevice => kind("bd"),
+            FileKind::CharDevice => kind("cd"),
+            FileKind::Executable => kind("ex"),
+            // like `ls`, suffixes only apply to plain files:
+            FileKind::File => {
+                let name = item.name.to_lowercase();
+                self.extensions
+                    .iter()
+                    .rev()
+                    .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+                    .map(|(_, style)| *style)
+                    .or_else(|| kind("fi"))
+            }
+        }
+    }
+}
+
+/// Convert ANSI SGR parameters (`01;38;5;208`) into a style
+fn parse_sgr(codes: &str) -> Style {
+    let mut style = Style::default();
+    let codes = codes
+        .split(';')
+        .map(|code| code.parse::<u8>().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => style = Style::default(),
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            5 => style = style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            code @ 30..=37 => style = style.fg(Color::Indexed(code - 30)),
+            code @ 40..=47 => style = style.bg(Color::Indexed(code - 40)),
+            code @ 90..=97 => style = style.fg(Color::Indexed(code - 90 + 8)),
+            code @ 100..=107 => style = style.bg(Color::Indexed(code - 100 + 8)),
+            code @ (38 | 48) => {
+                // extended colours: `38;5;<index>` or `38;2;<r>;<g>;<b>`
+                let color = match codes.get(i + 1).copied() {
+                    Some(5) => codes.get(i + 2).map(|&index| {
+                        i += 2;
+                        Color::Indexed(index)
+                    }),
+                    Some(2) if i + 4 < codes.len() => {
+                        i += 4;
+                        Some(Color::Rgb(codes[i - 2], codes[i - 1], codes[i]))
+                    }
+                    _ => None,
+                };
+                if let Some(color) = color {
+                    style = if code == 38 {
+                        style.fg(color)
+                    } else {
+                        style.bg(color)
+                    };
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    style
+}
@@ -0,0 +1,314 @@
+use anyhow::{Result, anyhow};
+use chrono::{Local, NaiveDateTime};
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    io::{ErrorKind, Write},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    commands::move_cmd::{SourceNotRemoved, move_path},
+    jobs::JobContext,
+    utils::{data_home, numbered_name, remove_path},
+};
+
+const TRASH_INFO_EXTENSION: &str = "trashinfo";
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// The user's home trash, laid out as described by the FreeDesktop.org trash specification:
+/// trashed items live in `files/`, with a matching `info/<name>.trashinfo` recording where
+/// they came from and when they were deleted.
+#[derive(Debug, Clone)]
+pub struct Trash {
+    files_dir: PathBuf,
+    info_dir: PathBuf,
+}
+
+/// A single item in the trash
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    pub name: String,                      // name of the item inside the trash
+    pub original_path: PathBuf,            // where the item was deleted from
+    pub deleted_at: Option<NaiveDateTime>, // when the item was deleted
+    pub trashed_path: PathBuf,             // location of the item inside the trash
+    pub info_path: PathBuf,                // location of the .trashinfo file
+}
+
+impl Trash {
+    /// Open (creating if necessary) the home trash at `$XDG_DATA_HOME/Trash`
+    pub fn home() -> Result<Self> {
+        let root = data_home()
+            .ok_or_else(|| anyhow!("Could not determine the user data directory"))?
+            .join("Trash");
+        Self::at(root)
+    }
+
+    pub fn at(root: PathBuf) -> Result<Self> {
+        let trash = Self {
+            files_dir: root.join("files"),
+            info_dir: root.join("info"),
+        };
+        fs::create_dir_all(&trash.files_dir)?;
+        fs::create_dir_all(&trash.info_dir)?;
+        Ok(trash)
+    }
+
+    /// Move `path` into the trash, returning the new trash entry
+    pub fn trash(&self, path: &Path, context: &JobContext) -> Result<TrashEntry> {
+        let original_path = std::path::absolute(path)?;
+        let file_name = original_path
+            .file_name()
+            .ok_or_else(|| anyhow!("Cannot trash '{}'", path.display()))?
+            .to_os_string();
+        let deleted_at = Local::now().naive_local();
+
+        // reserve a unique name by atomically creating the info file first (as required by the spec):
+        let (name, info_path, mut info_file) = self.reserve_name(&file_name)?;
+        let trashed_path = self.files_dir.join(&name);
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&original_path),
+            deleted_at.format(DELETION_DATE_FORMAT)
+        );
+
+        let result = info_file
+            .write_all(info.as_bytes())
+            .map_err(anyhow::Error::from)
+            .and_then(|()| move_path(&original_path, &trashed_path, context));
+        if let Err(e) = result {
+            // an item that made it into the trash stays restorable, even if its original lingers:
+            if !e.is::<SourceNotRemoved>() {
+                let _ = fs::remove_file(&info_path);
+            }
+            return Err(e);
+        }
+
+        Ok(TrashEntry {
+            name: name.to_string_lossy().to_string(),
+            original_path,
+            deleted_at: Some(deleted_at),
+            trashed_path,
+            info_path,
+        })
+    }
+
+    fn reserve_name(&self, file_name: &OsStr) -> Result<(OsString, PathBuf, fs::File)> {
+        let mut n = 1;
+        loop {
+            let name = if n == 1 {
+                file_name.to_os_string()
+            } else {
+                numbered_os_name(file_name, n)
+            };
+            let info_path = self.info_path(&name);
+            if !self.files_dir.join(&name).exists() {
+                match fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&info_path)
+                {
+                    Ok(file) => return Ok((name, info_path, file)),
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            n += 1;
+        }
+    }
+
+    fn info_path(&self, name: &OsStr) -> PathBuf {
+        let mut file_name = name.to_os_string();
+        file_name.push(format!(".{}", TRASH_INFO_EXTENSION));
+        self.info_dir.join(file_name)
+    }
+
+    /// List all items in the trash, most recently deleted first
+    pub fn list(&self) -> Result<Vec<TrashEntry>> {
+        let mut entries = fs::read_dir(&self.info_dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let info_path = entry.path();
+                if info_path.extension()? != TRASH_INFO_EXTENSION {
+                    return None;
+                }
+                // the name may not be valid UTF-8, so the path is built from the raw one:
+                let stem = info_path.file_stem()?;
+                let name = stem.to_string_lossy().to_string();
+                let trashed_path = self.files_dir.join(stem);
+                if !trashed_path.exists() && !trashed_path.is_symlink() {
+                    return None;
+                }
+                let (original_path, deleted_at) = parse_trash_info(&info_path).ok()?;
+                Some(TrashEntry {
+                    name,
+                    original_path,
+                    deleted_at,
+                    trashed_path,
+                    info_path,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+        Ok(entries)
+    }
+
+    /// Permanently delete everything in the trash, returning the number of items removed
+    pub fn empty(&self) -> Result<usize> {
+        let mut count = 0;
+        for entry in fs::read_dir(&self.files_dir)? {
+            remove_path(&entry?.path())?;
+            count += 1;
+        }
+        for entry in fs::read_dir(&self.info_dir)? {
+            fs::remove_file(entry?.path())?;
+        }
+        Ok(count)
+    }
+}
+
+impl TrashEntry {
+    pub fn is_dir(&self) -> bool {
+        self.trashed_path.is_dir()
+    }
+
+    /// Move the item back to where it was deleted from
+    pub fn restore(&self) -> Result<PathBuf> {
+        if self.original_path.exists() || self.original_path.is_symlink() {
+            return Err(anyhow!("'{}' already exists", self.original_path.display()));
+        }
+        if let Some(parent) = self.original_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_path(
+            &self.trashed_path,
+            &self.original_path,
+            &JobContext::detached(),
+        )?;
+        fs::remove_file(&self.info_path)?;
+        Ok(self.original_path.clone())
+    }
+
+    /// Permanently delete the item from the trash
+    pub fn purge(&self) -> Result<()> {
+        remove_path(&self.trashed_path)?;
+        fs::remove_file(&self.info_path)?;
+        Ok(())
+    }
+}
+
+fn parse_trash_info(info_path: &Path) -> Result<(PathBuf, Option<NaiveDateTime>)> {
+    let contents = fs::read_to_string(info_path)?;
+    let mut original_path = None;
+    let mut deleted_at = None;
+    for line in contents.lines() {
+        if let Some(path) = line.strip_prefix("Path=") {
+            original_path = Some(PathBuf::from(decode_path(path)));
+        } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+            deleted_at = NaiveDateTime::parse_from_str(date, DELETION_DATE_FORMAT).ok();
+        }
+    }
+    let original_path =
+        original_path.ok_or_else(|| anyhow!("Missing Path in '{}'", info_path.display()))?;
+    Ok((original_path, deleted_at))
+}
+
+// Paths in .trashinfo files are URL-encoded, byte by byte (names needn't be valid UTF-8):
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode_path(encoded: &str) -> OsString {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = encoded
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    OsString::from_vec(decoded)
+}
+
+/// `numbered_name` for names that may not be valid UTF-8 (those just get the counter at the end)
+fn numbered_os_name(name: &OsStr, n: usize) -> OsString {
+    match name.to_str() {
+        Some(name) => numbered_name(name, n).into(),
+        None => {
+            let mut numbered = name.to_os_string();
+            numbered.push(format!(" ({})", n));
+            numbered
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trash_info_paths_round_trip() {
+        let paths = [
+            OsString::from("/home/user/notes.txt"),
+            OsString::from("/home/user/with space & 100%/é.md"),
+            // not valid UTF-8:
+            OsString::from_vec(b"/tmp/caf\xe9 \xff\xfe".to_vec()),
+        ];
+        for path in paths {
+            let encoded = encode_path(Path::new(&path));
+            assert!(encoded.is_ascii(), "{encoded}");
+            assert_eq!(decode_path(&encoded), path);
+        }
+    }
+
+    #[test]
+    fn restores_non_utf8_names() {
+        let root = std::env::temp_dir().join(format!("clexp-trash-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let trash = Trash::at(root.join("Trash")).unwrap();
+        let original = root.join(OsStr::from_bytes(b"caf\xe9"));
+        fs::write(&original, "contents").unwrap();
+
+        trash.trash(&original, &JobContext::detached()).unwrap();
+        assert!(!original.exists());
+        let entries = trash.list().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].original_path, original);
+        assert_eq!(entries[0].restore().unwrap(), original);
+        assert_eq!(fs::read_to_string(&original).unwrap(), "contents");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn numbered_names_keep_raw_bytes() {
+        let name = OsString::from_vec(b"caf\xe9.txt".to_vec());
+        assert_eq!(
+            numbered_os_name(&name, 2),
+            OsString::from_vec(b"caf\xe9.txt (2)".to_vec())
+        );
+        assert_eq!(
+            numbered_os_name(OsStr::new("notes.txt"), 2),
+            "notes (2).txt"
+        );
+    }
+}
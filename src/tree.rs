@@ -0,0 +1,167 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    app::{App, FileItem},
+    listing::ListingFilter,
+    sort::SortOrder,
+};
+
+/// The tree view, where directories open up inline below themselves
+#[derive(Debug, Default)]
+pub struct TreeView {
+    pub enabled: bool,
+    expanded: HashSet<PathBuf>, // kept by path, so they stay open across refreshes and visits
+}
+
+impl TreeView {
+    pub fn is_expanded(&self, path: &Path) -> bool {
+        self.expanded.contains(path)
+    }
+
+    /// Put the contents of expanded directories below them, a level deeper, adding any
+    /// ignored entries among them to `ignored`
+    pub fn build(
+        &self,
+        items: Vec<FileItem>,
+        sort: &SortOrder,
+        listing: &ListingFilter,
+        ignored: &mut HashSet<PathBuf>,
+    ) -> Vec<FileItem> {
+        let mut nodes = Vec::with_capacity(items.len());
+        self.push_nodes(&mut nodes, items, None, sort, listing, ignored);
+        nodes
+    }
+
+    fn push_nodes(
+        &self,
+        nodes: &mut Vec<FileItem>,
+        items: Vec<FileItem>,
+        parent: Option<usize>,
+        sort: &SortOrder,
+        listing: &ListingFilter,
+        ignored: &mut HashSet<PathBuf>,
+    ) {
+        let depth = parent.map_or(0, |parent| nodes[parent].depth + 1);
+        for mut item in items {
+            item.depth = depth;
+            item.parent = parent;
+            // `..` is the parent directory, which can't open up below itself:
+            let expanded = item.is_dir && item.name != ".." && self.is_expanded(&item.path);
+            let dir = item.path.clone();
+            nodes.push(item);
+
+            if expanded {
+                // unreadable directories just open up empty:
+                let mut children = FileItem::read_dir(&dir, sort).unwrap_or_default();
+                ignored.extend(listing.apply(&dir, &mut children));
+                let index = nodes.len() - 1;
+                self.push_nodes(nodes, children, Some(index), sort, listing, ignored);
+            }
+        }
+    }
+}
+
+/// The indentation guides in front of each of `items` (listed in tree order): `│ ` where
+/// the directory at that level has more entries further down, then `├─` or `└─` for the
+/// item itself
+pub fn guides(items: &[&FileItem]) -> Vec<String> {
+    // working upwards, whether an item has been seen at each depth since the last shallower one:
+    let mut continues = Vec::new();
+    let mut guides = items
+        .iter()
+        .rev()
+        .map(|item| {
+            let depth = item.depth;
+            continues.resize(depth + 1, false);
+            let mut guide = String::new();
+            if depth > 0 {
+                for &more in &continues[1..depth] {
+                    guide.push_str(if more { "│ " } else { "  " });
+                }
+                guide.push_str(if continues[depth] { "├─" } else { "└─" });
+            }
+            continues[depth] = true;
+            guide
+        })
+        .collect::<Vec<_>>();
+    guides.reverse();
+    guides
+}
+
+impl App {
+    /// Switch between the flat list and the tree view
+    pub fn toggle_tree(&mut self) {
+        self.tree.enabled = !self.tree.enabled;
+        if let Err(e) = self.reload_file_list() {
+            self.set_error(format!("Failed to refresh: {}", e));
+        }
+        self.set_status(format!(
+            "Tree view: {}",
+            if self.tree.enabled { "on" } else { "off" }
+        ));
+    }
+
+    /// Open up the directory under the cursor below itself, or fold it away again
+    pub fn toggle_expanded(&mut self) {
+        let Some(item) = self.file_list.selected() else {
+            return;
+        };
+        if !item.is_dir || item.name == ".." {
+            return;
+        }
+        if !self.tree.enabled {
+            self.set_status("Directories only expand in the tree view (t to switch)".to_string());
+            return;
+        }
+        let path = item.path.clone();
+        if !self.tree.expanded.remove(&path) {
+            self.tree.expanded.insert(path);
+        }
+        if let Err(e) = self.reload_file_list() {
+            self.set_error(format!("Failed to refresh: {}", e));
+        }
+    }
+
+    /// Whether `collapse_selected` has anything to do, rather than going up a directory
+    pub fn can_collapse(&self) -> bool {
+        self.tree.enabled
+            && self.file_list.selected().is_some_and(|item| {
+                item.parent.is_some() || (item.name != ".." && self.tree.is_expanded(&item.path))
+            })
+    }
+
+    /// In the tree view, fold away the expanded directory under the cursor, or else move up
+    /// to the directory the item is in
+    pub fn collapse_selected(&mut self) {
+        let Some(item) = self.file_list.selected() else {
+            return;
+        };
+        if item.name != ".." && self.tree.is_expanded(&item.path) {
+            self.toggle_expanded();
+        } else if let Some(parent) = item.parent {
+            let parent = self.file_list.items[parent].path.clone();
+            self.select_path(&parent);
+        }
+    }
+
+    /// Where new and pasted items go: beside the item under the cursor in the tree view
+    /// (or into it, if it's an expanded directory), otherwise the current directory
+    pub fn target_dir(&self) -> PathBuf {
+        let item = self
+            .file_list
+            .selected()
+            .filter(|item| self.tree.enabled && item.name != "..");
+        match item {
+            Some(item) if self.tree.is_expanded(&item.path) => item.path.clone(),
+            Some(item) => item
+                .path
+                .parent()
+                .unwrap_or(&self.current_path)
+                .to_path_buf(),
+            None => self.current_path.clone(),
+        }
+    }
+}
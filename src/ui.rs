@@ -1,16 +1,26 @@
 use crate::{
-    app::{App, AppMode, ClipboardOperation, InputContext},
+    app::{App, AppMode, ClipboardOperation, InputContext, ListArea},
+    columns::{Column, ViewLayout},
+    command_line::{COMMANDS, OPTIONS, suggestions},
+    filter::{Filter, FilterMode},
+    keymap::{Action, KEYMAP_MODES, Keymap},
+    preview::PreviewContent,
+    tabs::Tab,
+    tree,
     utils::{format_size, format_time, get_file_icon, truncate_string},
 };
 use ratatui::{
     Frame,
     layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph, Wrap},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, Gauge, List, ListItem, ListState, Padding, Paragraph, Wrap,
+        block::{Position, Title},
+    },
 };
 
-pub fn draw(f: &mut Frame, app: &App) {
+pub fn draw(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(ratatui::layout::Direction::Vertical)
         .constraints([
@@ -23,8 +33,8 @@ pub fn draw(f: &mut Frame, app: &App) {
     // Draw header:
     draw_header(f, chunks[0], app);
 
-    // Draw Main content view:
-    draw_main_content(f, chunks[1], app);
+    // Draw Main content view (remembering where the file list went, for the mouse):
+    app.list_area = draw_main_content(f, chunks[1], app);
 
     // Draw status bar:
     draw_status_bar(f, chunks[2], app);
@@ -34,7 +44,8 @@ pub fn draw(f: &mut Frame, app: &App) {
         AppMode::Help => {
             draw_help_modal(f, app);
         }
-        AppMode::Input => {
+        // the filter is typed into the filter bar rather than a modal:
+        AppMode::Input if app.input_context != Some(InputContext::Filter) => {
             draw_input_modal(f, app);
         }
         AppMode::Confirm => {
@@ -46,7 +57,31 @@ pub fn draw(f: &mut Frame, app: &App) {
         }
 
         AppMode::Command => {
-            draw_input_modal(f, app);
+            draw_command_modal(f, app);
+        }
+
+        AppMode::Trash => {
+            draw_trash_modal(f, app);
+        }
+
+        AppMode::Conflict => {
+            draw_conflict_modal(f, app);
+        }
+
+        AppMode::JumpList => {
+            draw_jump_list_modal(f, app);
+        }
+
+        AppMode::Bookmarks => {
+            draw_bookmarks_modal(f, app);
+        }
+
+        AppMode::Jumper => {
+            draw_jumper_modal(f, app);
+        }
+
+        AppMode::Find => {
+            draw_find_modal(f, app);
         }
 
         _ => {}
@@ -65,38 +100,106 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) {
             AppMode::Command => "COMMAND",
             AppMode::Confirm => "CONFIRM",
             AppMode::Clipboard => "CLIPBOARD",
+            AppMode::Trash => "TRASH",
+            AppMode::Jobs => "JOBS",
+            AppMode::Conflict => "CONFLICT",
+            AppMode::JumpList => "JUMPS",
+            AppMode::Bookmarks => "BOOKMARKS",
+            AppMode::Jumper => "JUMP",
+            AppMode::Find => "FIND",
         }
     );
+    let sort_text = format!(" ⇅ {} ", app.sort.label());
+    let listing_text = app.listing.label().map(|label| format!(" {} ", label));
+    let git_text = app
+        .git
+        .current()
+        .map(|info| format!(" ⎇ {} ", info.label()));
 
     // get area chunks for header:
     let header_chunks = Layout::default()
         .direction(ratatui::layout::Direction::Horizontal)
         .constraints([
             Constraint::Min(0),
+            Constraint::Length(git_text.as_ref().map_or(0, |text| text.chars().count() + 2) as u16),
+            Constraint::Length(
+                listing_text
+                    .as_ref()
+                    .map_or(0, |text| text.chars().count() + 2) as u16,
+            ),
+            Constraint::Length((sort_text.chars().count() + 2) as u16),
             Constraint::Length((mode_text.len() + 2) as u16), // +2 to account for the borders
         ])
         .split(area);
 
+    // the tab bar takes over the title once there's more than one tab:
+    let title = if app.tabs.len() > 1 {
+        let tabs = app.tabs.iter().enumerate().map(|(i, tab)| {
+            let path = if i == app.active_tab {
+                &app.current_path
+            } else {
+                &tab.path
+            };
+            let style = if i == app.active_tab {
+                app.theme.highlight
+            } else {
+                Style::default()
+            };
+            Span::styled(format!(" {}:{} ", i + 1, Tab::label(path)), style)
+        });
+        Line::from(tabs.collect::<Vec<_>>())
+    } else {
+        Line::from("Clexp - Command Line Explorer")
+    };
+
     // display the path:
     let path_paragraph = Paragraph::new(path_text)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
-                .title("Clexp - Command Line Explorer"),
+                .border_type(app.theme.border_type)
+                .title(title),
         )
         .wrap(Wrap { trim: true });
     f.render_widget(path_paragraph, header_chunks[0]);
 
-    let mode_style = match app.mode {
-        AppMode::Normal => Style::default().fg(Color::Green),
-        AppMode::Help => Style::default().fg(Color::Magenta),
-        AppMode::MultiSelect => Style::default().fg(Color::Yellow),
-        AppMode::Input => Style::default().fg(Color::Blue),
-        AppMode::Command => Style::default().fg(Color::Cyan),
-        AppMode::Confirm => Style::default().fg(Color::Red),
-        AppMode::Clipboard => Style::default().fg(Color::LightGreen),
-    };
+    // only shown inside a repository:
+    if let Some(git_text) = git_text {
+        let git_paragraph = Paragraph::new(git_text)
+            .alignment(ratatui::layout::Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(app.theme.border_type)
+                    .title("Git"),
+            );
+        f.render_widget(git_paragraph, header_chunks[1]);
+    }
+
+    // only shown while something is left out of the list:
+    if let Some(listing_text) = listing_text {
+        let listing_paragraph = Paragraph::new(listing_text)
+            .alignment(ratatui::layout::Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(app.theme.border_type)
+                    .title("Filters"),
+            );
+        f.render_widget(listing_paragraph, header_chunks[2]);
+    }
+
+    let sort_paragraph = Paragraph::new(sort_text)
+        .alignment(ratatui::layout::Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(app.theme.border_type)
+                .title("Sort"),
+        );
+    f.render_widget(sort_paragraph, header_chunks[3]);
+
+    let mode_style = app.theme.mode_style(&app.mode);
 
     let mode_paragraph = Paragraph::new(mode_text)
         .style(mode_style.add_modifier(Modifier::BOLD))
@@ -104,34 +207,314 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded),
+                .border_type(app.theme.border_type),
         );
-    f.render_widget(mode_paragraph, header_chunks[1]);
+    f.render_widget(mode_paragraph, header_chunks[4]);
 }
 
-fn draw_main_content(f: &mut Frame, area: Rect, app: &App) {
+fn draw_main_content(f: &mut Frame, area: Rect, app: &App) -> ListArea {
+    // show the jobs panel underneath while anything is running:
+    let area = if app.jobs.is_empty() {
+        area
+    } else {
+        let visible_jobs = app.jobs.jobs.len().min(MAX_VISIBLE_JOBS) as u16;
+        let content_chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(visible_jobs + 2)])
+            .split(area);
+        draw_jobs_panel(f, content_chunks[1], app);
+        content_chunks[0]
+    };
+
+    match app.layout {
+        ViewLayout::Columns => return draw_columns(f, area, app),
+        ViewLayout::Dual => return draw_dual(f, area, app),
+        ViewLayout::Split => {}
+    }
+
+    if !app.show_preview {
+        return draw_files_list(f, area, app);
+    }
+
     let main_chunks = Layout::default()
         .direction(ratatui::layout::Direction::Horizontal)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
         .split(area);
 
     // draw files:
-    draw_files_list(f, main_chunks[0], app);
+    let list_area = draw_files_list(f, main_chunks[0], app);
+
+    // draw a preview of the selected item:
+    draw_preview_panel(f, main_chunks[1], app);
+    list_area
+}
+
+/// The parent directory, the file list, then the selected directory or a preview, like ranger
+fn draw_columns(f: &mut Frame, area: Rect, app: &App) -> ListArea {
+    let constraints = if app.show_preview {
+        vec![
+            Constraint::Percentage(20),
+            Constraint::Percentage(40),
+            Constraint::Percentage(40),
+        ]
+    } else {
+        vec![Constraint::Percentage(30), Constraint::Percentage(70)]
+    };
+    let column_chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints(constraints)
+        .split(area);
+
+    let parent = app.current_path.parent();
+    let parent_name = parent
+        .map(|dir| match dir.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => "/".to_string(),
+        })
+        .unwrap_or_default();
+    draw_column(
+        f,
+        column_chunks[0],
+        app,
+        parent.and_then(|dir| app.columns.get(dir)),
+        parent_name,
+    );
+
+    let list_area = draw_files_list(f, column_chunks[1], app);
+
+    if app.show_preview {
+        match app.file_list.selected() {
+            Some(item) if item.is_dir && item.name != ".." => draw_column(
+                f,
+                column_chunks[2],
+                app,
+                app.columns.get(&item.path),
+                item.display_name(),
+            ),
+            _ => draw_preview_panel(f, column_chunks[2], app),
+        }
+    }
+    list_area
+}
+
+/// A directory listing beside the file list, with its cursor shown but not focused
+fn draw_column(f: &mut Frame, area: Rect, app: &App, column: Option<&Column>, name: String) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(format!(" {} ", name));
+    let Some(column) = column.filter(|column| !column.list.items.is_empty()) else {
+        let message = if column.is_some() {
+            "Empty directory"
+        } else {
+            ""
+        };
+        f.render_widget(
+            Paragraph::new(Line::styled(message, app.theme.muted)).block(block),
+            area,
+        );
+        return;
+    };
+
+    let name_width = area.width.saturating_sub(5) as usize; // borders and icon
+    let items = column
+        .list
+        .items
+        .iter()
+        .map(|item| {
+            let mut style = app.theme.file_style(item);
+            if column.ignored.contains(&item.path) {
+                style = style.patch(app.theme.ignored);
+            }
+            ListItem::new(Line::styled(
+                format!(
+                    "{} {}",
+                    get_file_icon(&item.name, item.is_dir),
+                    truncate_string(&item.display_name(), name_width)
+                ),
+                style,
+            ))
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(app.theme.highlight);
+    f.render_stateful_widget(list, area, &mut column.list.state.clone());
+}
+
+/// Two file lists side by side, like mc. The active pane is the usual file list
+fn draw_dual(f: &mut Frame, area: Rect, app: &App) -> ListArea {
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let (active, other) = if app.left_pane_active {
+        (chunks[0], chunks[1])
+    } else {
+        (chunks[1], chunks[0])
+    };
+    draw_other_pane(f, other, app);
+    draw_files_list(f, active, app)
+}
+
+/// The inactive pane of the dual-pane layout, with its own filter and selection
+fn draw_other_pane(f: &mut Frame, area: Rect, app: &App) {
+    let pane = &app.other_pane;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(format!(" {} ", pane.path.display()));
+    let Some(column) = app.columns.get(&pane.path) else {
+        f.render_widget(block, area);
+        return;
+    };
+
+    let name_width = area.width.saturating_sub(7) as usize; // borders, cursor and icon
+    let mut cursor = None;
+    let items = column
+        .list
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| pane.filter.matches(&item.name).is_some())
+        .enumerate()
+        .map(|(position, (idx, item))| {
+            if column.list.state.selected() == Some(idx) {
+                cursor = Some(position);
+            }
+            let mut style = app.theme.file_style(item);
+            if column.ignored.contains(&item.path) {
+                style = style.patch(app.theme.ignored);
+            }
+            if pane.selection.contains(&item.path) {
+                style = style.patch(app.theme.selected);
+            }
+            ListItem::new(Line::styled(
+                format!(
+                    "{} {}",
+                    get_file_icon(&item.name, item.is_dir),
+                    truncate_string(&item.display_name(), name_width)
+                ),
+                style,
+            ))
+        })
+        .collect::<Vec<_>>();
+    // only an arrow at its cursor, so it's clear the keys act on the other pane:
+    let list = List::new(items).block(block).highlight_symbol("→ ");
+    f.render_stateful_widget(list, area, &mut ListState::default().with_selected(cursor));
+}
+
+fn draw_jobs_panel(f: &mut Frame, area: Rect, app: &App) {
+    let focused = app.mode == AppMode::Jobs;
+    let hints = if focused {
+        "p:pause/resume  x:cancel  Esc:back"
+    } else {
+        "J:manage"
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .border_style(if focused {
+            app.theme.focused
+        } else {
+            Style::default()
+        })
+        .title(format!(" Jobs ({}) - {} ", app.jobs.jobs.len(), hints));
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    // keep the selected job in view:
+    let height = inner_area.height as usize;
+    let offset = app.jobs.selected.saturating_sub(height.saturating_sub(1));
+    for (row, (idx, job)) in app
+        .jobs
+        .jobs
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .enumerate()
+    {
+        let state = if job.is_cancelled() {
+            "cancelling"
+        } else if job.is_paused() {
+            "paused"
+        } else {
+            "running"
+        };
+        let progress = if job.bytes_total > 0 {
+            format!(
+                "{}/{} files, {}/{}",
+                job.files_done,
+                job.files_total,
+                format_size(job.bytes_done),
+                format_size(job.bytes_total)
+            )
+        } else {
+            format!("{}/{} items", job.files_done, job.files_total)
+        };
+        let label = format!(
+            "[{}] {} - {} ({:.0}%)",
+            state,
+            job.description,
+            progress,
+            job.ratio() * 100.0
+        );
 
-    // draw info side panel
-    draw_info_panel(f, main_chunks[1], app);
+        let mut gauge_style = if job.is_paused() {
+            app.theme.job_paused
+        } else {
+            app.theme.job_running
+        };
+        if focused && idx == app.jobs.selected {
+            gauge_style = gauge_style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        }
+        let gauge = Gauge::default()
+            .gauge_style(gauge_style)
+            .ratio(job.ratio())
+            .label(label);
+        let row_area = Rect {
+            y: inner_area.y + row as u16,
+            height: 1,
+            ..inner_area
+        };
+        f.render_widget(gauge, row_area);
+    }
 }
 
-fn draw_files_list(f: &mut Frame, area: Rect, app: &App) {
+/// Draw the file list, returning where its items went
+fn draw_files_list(f: &mut Frame, area: Rect, app: &App) -> ListArea {
+    // show the filter bar below the list while a filter is active or being typed:
+    let editing_filter = app.input_context == Some(InputContext::Filter);
+    let area = if editing_filter || !app.filter.is_empty() {
+        let list_chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(area);
+        draw_filter_bar(f, list_chunks[1], app, editing_filter);
+        list_chunks[0]
+    } else {
+        area
+    };
+
     let multi_select_mode = app.mode == AppMode::MultiSelect;
-    // todo more selection mode stuff
     let selected_indices = &app.selection;
-    let items: Vec<ListItem> = app
+    let git = app.git.current();
+    let visible = app
         .file_list
-        .filtered_items()
+        .filtered_items
+        .iter()
+        .filter_map(|&idx| app.file_list.items.get(idx).map(|item| (idx, item)))
+        .collect::<Vec<_>>();
+    let guides = if app.tree.enabled {
+        tree::guides(&visible.iter().map(|&(_, item)| item).collect::<Vec<_>>())
+    } else {
+        Vec::new()
+    };
+    let items: Vec<ListItem> = visible
         .iter()
         .enumerate()
-        .map(|(_idx, item)| {
+        .map(|(position, &(idx, item))| {
             let icon = get_file_icon(&item.name, item.is_dir);
             let size_text = if let Some(size) = item.size {
                 format_size(size)
@@ -143,135 +526,258 @@ fn draw_files_list(f: &mut Frame, area: Rect, app: &App) {
             } else {
                 area.width.saturating_sub(20) as usize
             };
+            // leave room for the git status markers inside a repository:
+            let name_width = if git.is_some() {
+                name_width.saturating_sub(GIT_MARKERS_WIDTH + 1)
+            } else {
+                name_width
+            };
+            let guide = guides.get(position).map_or("", String::as_str);
+            let name_width = name_width.saturating_sub(guide.chars().count());
 
             let display_name = truncate_string(&item.display_name(), name_width);
-            let is_selected = app
-                .file_list
-                .items
-                .iter()
-                .position(|f| f.name == item.name && f.path == item.path)
-                .map_or(false, |i| selected_indices.contains(&i));
+            let is_selected = selected_indices.contains(&idx);
             let checkbox = if multi_select_mode {
-                if is_selected { "[x]" } else { "[ ]" }
+                if is_selected { "[x] " } else { "[ ] " }
             } else {
                 ""
             };
 
-            let content = if multi_select_mode {
-                format!(
-                    "{} {} {:<width$} {:>8}",
-                    checkbox,
-                    icon,
-                    display_name,
-                    size_text,
-                    width = name_width
-                )
-            } else {
-                format!(
-                    "{} {:<width$} {:>8}",
-                    icon,
-                    display_name,
-                    size_text,
-                    width = name_width
-                )
-            };
-
-            let mut style = if item.is_dir {
-                Style::default()
-                    .fg(Color::LightCyan)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default()
-            };
+            let mut style = app.theme.file_style(item);
+            if app.ignored.contains(&item.path) {
+                style = style.patch(app.theme.ignored);
+            }
             if multi_select_mode && is_selected {
-                style = style.bg(Color::LightYellow).fg(Color::Black);
+                style = style.patch(app.theme.selected);
+            }
+
+            // highlight the characters matched by the filter:
+            let matched = app
+                .filter
+                .matches(&item.name)
+                .map(|m| m.indices)
+                .unwrap_or_default();
+            let match_style = style.patch(app.theme.filter_match);
+
+            let padding = name_width.saturating_sub(display_name.chars().count());
+            let mut spans = vec![
+                Span::styled(checkbox, style),
+                Span::styled(guide, app.theme.muted),
+                Span::styled(format!("{} ", icon), style),
+            ];
+            spans.extend(highlight_matches(
+                &display_name,
+                &matched,
+                style,
+                match_style,
+            ));
+            spans.push(Span::styled(" ".repeat(padding), style));
+            if let Some(git) = git {
+                let markers = match git.status(&item.path) {
+                    Some(status) if item.name != ".." => status.markers(),
+                    _ => Vec::new(),
+                };
+                let shown = markers.len().min(GIT_MARKERS_WIDTH);
+                spans.push(Span::styled(
+                    " ".repeat(GIT_MARKERS_WIDTH + 1 - shown),
+                    style,
+                ));
+                spans.extend(markers.into_iter().take(shown).map(|(marker, kind)| {
+                    Span::styled(marker.to_string(), style.patch(app.theme.git_style(kind)))
+                }));
             }
-            ListItem::new(Line::from(Span::styled(content, style)))
+            spans.push(Span::styled(format!(" {:>8}", size_text), style));
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
-    let title = if multi_select_mode {
-        // todo: fix this when filtering is applied (not implemented yet)
+    let count = if app.filter.is_empty() {
+        format!("{}", app.file_list.items.len())
+    } else {
         format!(
-            " Files ({}) [{} items selected]",
-            app.file_list.items.len(),
-            app.selection.len()
+            "{}/{}",
+            app.file_list.filtered_items.len(),
+            app.file_list.items.len()
         )
+    };
+    let title = if multi_select_mode {
+        let hidden = app
+            .selection
+            .iter()
+            .filter(|i| !app.file_list.filtered_items.contains(i))
+            .count();
+        if hidden > 0 {
+            format!(
+                " Files ({}) [{} items selected, {} hidden by filter]",
+                count,
+                app.selection.len(),
+                hidden
+            )
+        } else {
+            format!(
+                " Files ({}) [{} items selected]",
+                count,
+                app.selection.len()
+            )
+        }
     } else {
-        format!(" Files ({}) ", app.file_list.items.len())
+        format!(" Files ({}) ", count)
     };
 
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(title);
+    // in the dual-pane layout, mark which pane the keys act on:
+    if app.layout == ViewLayout::Dual {
+        block = block.border_style(app.theme.focused);
+    }
+    let rows = block.inner(area);
     let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
-                .title(title),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::Gray)
-                .add_modifier(Modifier::BOLD),
-        )
+        .block(block)
+        .highlight_style(app.theme.highlight)
         .highlight_symbol("→ ");
 
-    f.render_stateful_widget(list, area, &mut app.file_list.state.clone());
+    // the list widget indexes the visible items, not the full item list:
+    let mut state = ListState::default().with_selected(app.file_list.selected_position());
+    f.render_stateful_widget(list, area, &mut state);
+    ListArea {
+        rows,
+        offset: state.offset(),
+    }
 }
 
-fn draw_info_panel(f: &mut Frame, area: Rect, app: &App) {
-    let content = if let Some(selected_item) = app.file_list.selected() {
-        let mut lines = vec![
-            Line::from(vec![
-                Span::styled("Name: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(&selected_item.name),
-            ]),
-            Line::from(vec![
-                Span::styled("Name: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(if selected_item.is_dir {
-                    "Directory"
-                } else {
-                    "File"
-                }),
-            ]),
-        ];
-
-        if let Some(size) = selected_item.size {
-            lines.push(Line::from(vec![
-                Span::styled("Size: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(format_size(size)),
-            ]));
-        }
-
-        if let Some(modified) = selected_item.modified {
-            lines.push(Line::from(vec![
-                Span::styled("Modified: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(format_time(modified)),
-            ]));
-        }
-
-        lines.push(Line::from(""));
-        lines.push(Line::from(vec![Span::styled(
-            "Path: ",
-            Style::default().add_modifier(Modifier::BOLD),
-        )]));
-        lines.push(Line::from(Span::raw(
-            selected_item.path.display().to_string(),
-        )));
-
-        Text::from(lines)
+fn draw_filter_bar(f: &mut Frame, area: Rect, app: &App, editing: bool) {
+    let pattern = if editing {
+        input_line(app)
+    } else {
+        app.filter.pattern.clone()
+    };
+    let title = if editing {
+        format!(
+            " Filter ({}) - Tab: change mode, Enter: keep, Esc: clear ",
+            app.filter.mode.label()
+        )
     } else {
-        Text::from("No file selected")
+        format!(" Filter ({}) - f: edit ", app.filter.mode.label())
     };
-    let details = Paragraph::new(content)
+
+    let paragraph = Paragraph::new(format!("🔍 {}", pattern))
+        .style(app.theme.accent)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
-                .title("Details"),
-        )
-        .wrap(Wrap { trim: true });
+                .border_type(app.theme.border_type)
+                .title(title),
+        );
+    f.render_widget(paragraph, area);
+}
+
+fn draw_preview_panel(f: &mut Frame, area: Rect, app: &App) {
+    let Some(selected_item) = app.file_list.selected() else {
+        let paragraph = Paragraph::new("No file selected").block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(app.theme.border_type)
+                .title(" Preview "),
+        );
+        f.render_widget(paragraph, area);
+        return;
+    };
+
+    // size and modified time along the bottom border:
+    let details = [
+        selected_item.size.map(format_size),
+        selected_item.modified.map(format_time),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ");
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(format!(" {} ", selected_item.display_name()));
+    if !details.is_empty() {
+        block = block.title(Title::from(format!(" {} ", details)).position(Position::Bottom));
+    }
+    let inner_area = block.inner(area);
+
+    let mut lines = match app.previewer.current() {
+        None => vec![Line::styled("Loading…", app.theme.muted)],
+        Some(preview) => match &preview.content {
+            PreviewContent::Text(lines) => lines.clone(),
+            PreviewContent::Directory(items) if items.is_empty() => {
+                vec![Line::styled("Empty directory", app.theme.muted)]
+            }
+            PreviewContent::Directory(items) => items
+                .iter()
+                .map(|item| {
+                    Line::styled(
+                        format!(
+                            "{} {}",
+                            get_file_icon(&item.name, item.is_dir),
+                            item.display_name()
+                        ),
+                        app.theme.file_style(item),
+                    )
+                })
+                .collect(),
+            PreviewContent::Hex(bytes) => hex_dump(bytes, inner_area.width as usize, app),
+            PreviewContent::Archive(members) if members.is_empty() => {
+                vec![Line::styled("Empty archive", app.theme.muted)]
+            }
+            PreviewContent::Archive(members) => members
+                .iter()
+                .map(|member| Line::raw(member.clone()))
+                .collect(),
+            PreviewContent::Message(message) => {
+                vec![Line::styled(message.clone(), app.theme.muted)]
+            }
+        },
+    };
+    if let Some(preview) = app.previewer.current()
+        && preview.truncated
+    {
+        let marker = match preview.content {
+            PreviewContent::Directory(_) | PreviewContent::Archive(_) => "… listing truncated",
+            _ => "…",
+        };
+        lines.push(Line::styled(marker, app.theme.muted));
+    }
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
 
-    f.render_widget(details, area);
+/// Offset, hex bytes and printable characters, with as many bytes per row as fit in `width`
+fn hex_dump(bytes: &[u8], width: usize, app: &App) -> Vec<Line<'static>> {
+    // each byte takes 4 columns ("xx " plus its character), after a 10 column offset:
+    let per_row = (width.saturating_sub(11) / 4 / 4 * 4).max(4);
+    bytes
+        .chunks(per_row)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{:02x} ", byte))
+                .collect::<String>();
+            let chars = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            Line::from(vec![
+                Span::styled(format!("{:08x}  ", row * per_row), app.theme.muted),
+                Span::raw(format!("{:<width$} ", hex, width = per_row * 3)),
+                Span::styled(chars, app.theme.accent),
+            ])
+        })
+        .collect()
 }
 
 fn draw_status_bar(f: &mut Frame, area: Rect, app: &App) {
@@ -289,28 +795,33 @@ fn draw_status_bar(f: &mut Frame, area: Rect, app: &App) {
         " Ready".to_string()
     };
     let status_style = if app.error_message.is_some() {
-        Style::default().fg(Color::Red)
+        app.theme.error
     } else {
-        Style::default().fg(Color::Cyan)
+        app.theme.status
     };
     let status_paragraph = Paragraph::new(status_text).style(status_style).block(
         Block::default()
             .borders(Borders::ALL)
-            .border_type(ratatui::widgets::BorderType::Rounded),
+            .border_type(app.theme.border_type),
     );
     f.render_widget(status_paragraph, status_bar_chunks[0]);
 
     // right side = usage hints
-    let hints = "q:quit  ?:help /:run cmd";
+    let hints = format!(
+        "{}:quit  {}:help {}:run cmd",
+        key_hint(&app.keymap, &AppMode::Normal, Action::Quit),
+        key_hint(&app.keymap, &AppMode::Normal, Action::Help),
+        key_hint(&app.keymap, &AppMode::Normal, Action::Command)
+    );
     // todo: show different hints based on app mode
-    let hints_style = Style::default().fg(Color::White);
+    let hints_style = app.theme.hint;
     let hints_paragraph = Paragraph::new(hints)
         .style(hints_style)
         .alignment(ratatui::layout::Alignment::Center)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded),
+                .border_type(app.theme.border_type),
         );
     f.render_widget(hints_paragraph, status_bar_chunks[1]);
 }
@@ -321,13 +832,14 @@ fn draw_help_modal(f: &mut Frame, app: &App) {
     f.render_widget(Clear, area);
 
     let height = area.height.saturating_sub(2) as usize; // account for borders
-    let max_offset = if HELP_DIALOG.len() > height {
-        HELP_DIALOG.len() - height
+    let help_lines = help_lines(&app.keymap);
+    let max_offset = if help_lines.len() > height {
+        help_lines.len() - height
     } else {
         0
     };
     let offset = app.help_scroll_offset.min(max_offset);
-    let visible_lines = &HELP_DIALOG[offset..HELP_DIALOG.len().min(offset + height)];
+    let visible_lines = &help_lines[offset..help_lines.len().min(offset + height)];
     let help_text = visible_lines.join("\n");
 
     // todo: add a close button for mouse support eventually
@@ -336,7 +848,7 @@ fn draw_help_modal(f: &mut Frame, app: &App) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
+                .border_type(app.theme.border_type)
                 .title(" Help Manual ")
                 .padding(Padding {
                     left: 2,
@@ -363,30 +875,129 @@ fn draw_input_modal(f: &mut Frame, app: &App) {
         None => "Input",
     };
 
-    let input_text = &app.input_buffer;
-    let cursor_pos = app.cursor_position;
-    let before_cursor = &input_text[..cursor_pos.min(input_text.len())];
-    let after_cursor = &input_text[cursor_pos.min(input_text.len())..];
-    let text_with_cursor = format!("{}█{}", before_cursor, after_cursor);
-    let paragraph = Paragraph::new(text_with_cursor)
-        .style(Style::default().fg(Color::Yellow))
+    let paragraph = Paragraph::new(input_line(app))
+        .style(app.theme.accent)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
+                .border_type(app.theme.border_type)
                 .title(title),
         );
     f.render_widget(paragraph, area);
 }
 
+fn draw_command_modal(f: &mut Frame, app: &App) {
+    // just tall enough for the command and an error, leaving room for suggestions underneath:
+    let area = centered_rect(60, 20, f.size());
+    let area = Rect {
+        height: area.height.min(4),
+        ..area
+    };
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title("Command Mode");
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner_area);
+
+    // the command, with a cursor:
+    let command_line = Paragraph::new(input_line(app)).style(app.theme.accent);
+    f.render_widget(command_line, chunks[0]);
+
+    // what was wrong with the last attempt:
+    if let Some(error) = &app.command_error {
+        let error_text = Paragraph::new(error.as_str())
+            .style(app.theme.error)
+            .wrap(Wrap { trim: true });
+        f.render_widget(error_text, chunks[1]);
+    }
+
+    draw_command_suggestions(f, area, app);
+}
+
+/// Completion candidates, or the commands matching what's typed, in a popup under the command line
+fn draw_command_suggestions(f: &mut Frame, command_area: Rect, app: &App) {
+    let (title, items, selected) = match &app.completion {
+        Some(completion) => (
+            format!(
+                " Completions ({}) - Tab: next ",
+                completion.candidates.len()
+            ),
+            completion
+                .candidates
+                .iter()
+                .map(|candidate| ListItem::new(candidate.as_str()))
+                .collect::<Vec<_>>(),
+            completion.selected,
+        ),
+        None => {
+            let commands = suggestions(&app.input_buffer)
+                .into_iter()
+                .map(|spec| (format!("{} {}", spec.name, spec.usage), spec.description))
+                .collect::<Vec<_>>();
+            let width = commands
+                .iter()
+                .map(|(command, _)| command.len())
+                .max()
+                .unwrap_or(0);
+            (
+                " Commands ".to_string(),
+                commands
+                    .into_iter()
+                    .map(|(command, description)| {
+                        ListItem::new(Line::from(vec![
+                            Span::styled(format!("{:<width$}  ", command), app.theme.accent),
+                            Span::styled(description, app.theme.muted),
+                        ]))
+                    })
+                    .collect::<Vec<_>>(),
+                None,
+            )
+        }
+    };
+    if items.is_empty() {
+        return;
+    }
+
+    let space_below = f.size().bottom().saturating_sub(command_area.bottom());
+    let height = (items.len().min(MAX_SUGGESTIONS) as u16 + 2).min(space_below);
+    if height < 3 {
+        return;
+    }
+    let area = Rect {
+        y: command_area.bottom(),
+        height,
+        ..command_area
+    };
+    f.render_widget(Clear, area);
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(app.theme.border_type)
+                .title(title),
+        )
+        .highlight_style(app.theme.highlight);
+    let mut state = ListState::default().with_selected(selected);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
 fn draw_confirm_modal(f: &mut Frame, app: &App) {
     let area = centered_rect(50, 25, f.size());
     f.render_widget(Clear, area);
 
     let outer_block = Block::default()
         .borders(Borders::ALL)
-        .border_type(ratatui::widgets::BorderType::Rounded)
-        .style(Style::default().fg(Color::Red));
+        .border_type(app.theme.border_type)
+        .style(app.theme.warning);
     f.render_widget(outer_block, area);
 
     let inner_area = Rect {
@@ -397,15 +1008,19 @@ fn draw_confirm_modal(f: &mut Frame, app: &App) {
     };
 
     let text = app.status_message.as_deref().unwrap_or("Confirm action?");
-    let disclaimer = "Press Y to confirm, N to cancel";
+    let disclaimer = format!(
+        "Press {} to confirm, {} to cancel",
+        key_hint(&app.keymap, &AppMode::Confirm, Action::Confirm),
+        key_hint(&app.keymap, &AppMode::Confirm, Action::Cancel)
+    );
     let text = format!("{}\n\n{}", text, disclaimer);
 
     let paragraph = Paragraph::new(text)
-        .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+        .style(app.theme.warning.add_modifier(Modifier::BOLD))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
+                .border_type(app.theme.border_type)
                 .title(" Confirm ")
                 .padding(Padding {
                     left: 1,
@@ -419,6 +1034,74 @@ fn draw_confirm_modal(f: &mut Frame, app: &App) {
     f.render_widget(paragraph, inner_area);
 }
 
+fn draw_conflict_modal(f: &mut Frame, app: &App) {
+    let Some(plan) = &app.pending_paste else {
+        return;
+    };
+    let Some(conflict) = plan.current_conflict() else {
+        return;
+    };
+
+    let area = centered_rect(60, 40, f.size());
+    f.render_widget(Clear, area);
+
+    let name = conflict
+        .destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let kind = if conflict.destination.is_dir() {
+        "directory"
+    } else {
+        "file"
+    };
+    let mut lines = vec![
+        format!("A {} named '{}' already exists in", kind, name),
+        plan.destination.display().to_string(),
+        "".to_string(),
+        format!("Pasting: {}", conflict.source.display()),
+        "".to_string(),
+        format!(
+            "{}: overwrite   {}: skip   {}: keep both (rename)",
+            key_hint(&app.keymap, &AppMode::Conflict, Action::Overwrite),
+            key_hint(&app.keymap, &AppMode::Conflict, Action::Skip),
+            key_hint(&app.keymap, &AppMode::Conflict, Action::KeepBoth)
+        ),
+    ];
+    if conflict.can_merge() {
+        lines.push(format!(
+            "{}: merge directories",
+            key_hint(&app.keymap, &AppMode::Conflict, Action::Merge)
+        ));
+    }
+    lines.push("".to_string());
+    lines.push(format!(
+        "{}: apply to all {} remaining conflict(s) [{}]   {}: cancel paste",
+        key_hint(&app.keymap, &AppMode::Conflict, Action::ApplyToAll),
+        plan.remaining_conflicts(),
+        if plan.apply_to_all { "x" } else { " " },
+        key_hint(&app.keymap, &AppMode::Conflict, Action::Cancel)
+    ));
+
+    let paragraph = Paragraph::new(lines.join("\n"))
+        .style(app.theme.conflict)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(app.theme.border_type)
+                .title(" Paste Conflict ")
+                .padding(Padding {
+                    left: 1,
+                    right: 1,
+                    top: 1,
+                    bottom: 1,
+                }),
+        )
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
+
 fn draw_clipboard_modal(f: &mut Frame, app: &App) {
     let area = centered_rect(60, 60, f.size());
     f.render_widget(Clear, area);
@@ -453,7 +1136,7 @@ fn draw_clipboard_modal(f: &mut Frame, app: &App) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
+                .border_type(app.theme.border_type)
                 .title(" Clipboard ")
                 .padding(Padding {
                     left: 1,
@@ -466,7 +1149,439 @@ fn draw_clipboard_modal(f: &mut Frame, app: &App) {
     f.render_widget(paragraph, area);
 }
 
+fn draw_trash_modal(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(format!(" Trash ({}) ", app.trash_list.items.len()));
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let trash_chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner_area);
+
+    if app.trash_list.items.is_empty() {
+        f.render_widget(Paragraph::new(" Trash is empty"), trash_chunks[0]);
+    } else {
+        let path_width = inner_area.width.saturating_sub(24) as usize;
+        let items: Vec<ListItem> = app
+            .trash_list
+            .items
+            .iter()
+            .map(|entry| {
+                let is_dir = entry.is_dir();
+                let icon = get_file_icon(&entry.name, is_dir);
+                let deleted_at = entry
+                    .deleted_at
+                    .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                let path = truncate_string(&entry.original_path.display().to_string(), path_width);
+                let content = format!(
+                    "{} {:<width$} {:>16}",
+                    icon,
+                    path,
+                    deleted_at,
+                    width = path_width
+                );
+                let style = if is_dir {
+                    app.theme.directory
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(Span::styled(content, style)))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(app.theme.highlight)
+            .highlight_symbol("→ ");
+        f.render_stateful_widget(list, trash_chunks[0], &mut app.trash_list.state.clone());
+    }
+
+    let hints = Paragraph::new(format!(
+        " {}:restore  {}:delete forever  {}:empty trash  {}:close",
+        key_hint(&app.keymap, &AppMode::Trash, Action::Restore),
+        key_hint(&app.keymap, &AppMode::Trash, Action::Purge),
+        key_hint(&app.keymap, &AppMode::Trash, Action::EmptyTrash),
+        key_hint(&app.keymap, &AppMode::Trash, Action::Close)
+    ))
+    .style(app.theme.muted);
+    f.render_widget(hints, trash_chunks[1]);
+}
+
+fn draw_jump_list_modal(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 60, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(" Jump List ");
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner_area);
+
+    let path_width = chunks[0].width.saturating_sub(5) as usize;
+    let items: Vec<ListItem> = app
+        .jump_list
+        .items
+        .iter()
+        .map(|dir| {
+            let path = truncate_string(&dir.display().to_string(), path_width);
+            ListItem::new(Line::from(Span::styled(
+                format!("📁 {}", path),
+                app.theme.directory,
+            )))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol("→ ");
+    f.render_stateful_widget(list, chunks[0], &mut app.jump_list.state.clone());
+
+    let hints = Paragraph::new(format!(
+        " {}:jump  {}:close",
+        key_hint(&app.keymap, &AppMode::JumpList, Action::Open),
+        key_hint(&app.keymap, &AppMode::JumpList, Action::Close)
+    ))
+    .style(app.theme.muted);
+    f.render_widget(hints, chunks[1]);
+}
+
+fn draw_find_modal(f: &mut Frame, app: &App) {
+    let Some(search) = &app.search else {
+        return;
+    };
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(format!(
+            " Find '{}' ({}) in {} ",
+            search.pattern,
+            search.mode.label(),
+            search.root.display()
+        ));
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner_area);
+
+    // matches are shown relative to where the search started:
+    let path_width = chunks[0].width.saturating_sub(5) as usize;
+    let items: Vec<ListItem> = search
+        .results
+        .items
+        .iter()
+        .map(|found| {
+            let relative = found.path.strip_prefix(&search.root).unwrap_or(&found.path);
+            let mut path = relative.display().to_string();
+            if found.is_dir {
+                path.push('/');
+            }
+            let name = found.path.file_name().unwrap_or_default().to_string_lossy();
+            let style = if found.is_dir {
+                app.theme.directory
+            } else {
+                Style::default()
+            };
+            ListItem::new(Line::from(Span::styled(
+                format!(
+                    "{} {}",
+                    get_file_icon(&name, found.is_dir),
+                    truncate_string(&path, path_width)
+                ),
+                style,
+            )))
+        })
+        .collect();
+    if items.is_empty() {
+        let message = if search.running {
+            "Searching..."
+        } else {
+            "No matches"
+        };
+        f.render_widget(
+            Paragraph::new(Line::styled(message, app.theme.muted)),
+            chunks[0],
+        );
+    } else {
+        let list = List::new(items)
+            .highlight_style(app.theme.highlight)
+            .highlight_symbol("→ ");
+        f.render_stateful_widget(list, chunks[0], &mut search.results.state.clone());
+    }
+
+    let count = search.results.items.len();
+    let progress = if search.running {
+        format!("{} found, searching...", count)
+    } else if let Some(note) = search.limit_note() {
+        format!("{} found ({})", count, note)
+    } else {
+        format!("{} found", count)
+    };
+    let hints = Paragraph::new(format!(
+        " {}  {}:go to  {}:copy  {}:cut  {}:rename  {}:delete  {}:{}",
+        progress,
+        key_hint(&app.keymap, &AppMode::Find, Action::Open),
+        key_hint(&app.keymap, &AppMode::Find, Action::Copy),
+        key_hint(&app.keymap, &AppMode::Find, Action::Cut),
+        key_hint(&app.keymap, &AppMode::Find, Action::Rename),
+        key_hint(&app.keymap, &AppMode::Find, Action::Delete),
+        key_hint(&app.keymap, &AppMode::Find, Action::Close),
+        if search.running { "stop" } else { "close" }
+    ))
+    .style(app.theme.muted);
+    f.render_widget(hints, chunks[1]);
+}
+
+fn draw_bookmarks_modal(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 60, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(format!(" Bookmarks ({}) ", app.bookmark_list.items.len()));
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner_area);
+
+    if app.bookmark_list.items.is_empty() {
+        let text = format!(
+            " No bookmarks yet. Press {} and then a key to bookmark the current directory",
+            key_hint(&app.keymap, &AppMode::Normal, Action::SetMark)
+        );
+        f.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), chunks[0]);
+    } else {
+        let name_width = app
+            .bookmark_list
+            .items
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let path_width = (chunks[0].width as usize).saturating_sub(name_width + 6);
+        let items: Vec<ListItem> = app
+            .bookmark_list
+            .items
+            .iter()
+            .map(|(name, dir)| {
+                let path = truncate_string(&dir.display().to_string(), path_width);
+                // bookmarks whose directory has gone are dimmed:
+                let path_style = if dir.is_dir() {
+                    app.theme.directory
+                } else {
+                    app.theme.muted
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$}  ", name, width = name_width),
+                        app.theme.accent,
+                    ),
+                    Span::styled(path, path_style),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(app.theme.highlight)
+            .highlight_symbol("→ ");
+        f.render_stateful_widget(list, chunks[0], &mut app.bookmark_list.state.clone());
+    }
+
+    let hints = Paragraph::new(format!(
+        " {}:jump  {}:remove  {}:close",
+        key_hint(&app.keymap, &AppMode::Bookmarks, Action::Open),
+        key_hint(&app.keymap, &AppMode::Bookmarks, Action::RemoveBookmark),
+        key_hint(&app.keymap, &AppMode::Bookmarks, Action::Close)
+    ))
+    .style(app.theme.muted);
+    f.render_widget(hints, chunks[1]);
+}
+
+fn draw_jumper_modal(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 60, f.size());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .title(format!(
+            " Jump ({}/{}) - Enter: jump, Esc: close ",
+            app.jumper_list.filtered_items.len(),
+            app.jumper_list.items.len()
+        ));
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner_area);
+
+    // the query, with a cursor:
+    let query = &app.input_buffer;
+    let query_line = Paragraph::new(format!("🔍 {}", with_cursor(query, app.cursor_position)))
+        .style(app.theme.accent);
+    f.render_widget(query_line, chunks[0]);
+
+    // matching directories, with the matched characters highlighted:
+    let filter = Filter {
+        pattern: query.clone(),
+        mode: FilterMode::Fuzzy,
+    };
+    let match_style = app.theme.directory.patch(app.theme.filter_match);
+    let items: Vec<ListItem> = app
+        .jumper_list
+        .filtered_items
+        .iter()
+        .map(|&i| {
+            let path = app.jumper_list.items[i].display().to_string();
+            let matched = filter.matches(&path).map(|m| m.indices).unwrap_or_default();
+            ListItem::new(Line::from(highlight_matches(
+                &path,
+                &matched,
+                app.theme.directory,
+                match_style,
+            )))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol("→ ");
+    let mut state = ListState::default().with_selected(app.jumper_list.selected_position());
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
+
 // UI-specific helper functions:
+
+/// The text being typed with a cursor, or the Ctrl+R history search in its place
+fn input_line(app: &App) -> String {
+    if let Some(search) = &app.history_search {
+        let found = app.history_search_match();
+        let failed = if found.is_none() && !search.query.is_empty() {
+            "failed "
+        } else {
+            ""
+        };
+        return format!(
+            "({}reverse-i-search)`{}█': {}",
+            failed,
+            search.query,
+            found.unwrap_or_default()
+        );
+    }
+    with_cursor(&app.input_buffer, app.cursor_position)
+}
+
+/// `text` with a block cursor drawn at byte offset `cursor`
+fn with_cursor(text: &str, cursor: usize) -> String {
+    // never split a character, even if the cursor is somehow off a boundary:
+    let mut cursor = cursor.min(text.len());
+    while !text.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    format!("{}█{}", &text[..cursor], &text[cursor..])
+}
+
+/// Help modal contents, generated from the active keymap so it always matches the real bindings
+pub fn help_lines(keymap: &Keymap) -> Vec<String> {
+    let mut lines = vec!["Clexp Quick Help".to_string(), "".to_string()];
+    for (mode, _, heading) in &KEYMAP_MODES {
+        lines.push(format!("{}:", heading));
+        for (action, chords) in keymap.describe(mode) {
+            let chords = chords
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.push(format!("  {:<15} {}", chords, action.description()));
+        }
+        lines.push("".to_string());
+        lines.push("".to_string());
+    }
+
+    lines.push("Command Mode:".to_string());
+    for spec in &COMMANDS {
+        let command = format!("{} {}", spec.name, spec.usage);
+        lines.push(format!("  {:<36} {}", command, spec.description));
+        if !spec.aliases.is_empty() {
+            lines.push(format!(
+                "  {:<36}   (also: {})",
+                "",
+                spec.aliases.join(", ")
+            ));
+        }
+    }
+    lines.push("".to_string());
+    lines.push("Options (set name=value, set name / noname / name! for on/off):".to_string());
+    for option in &OPTIONS {
+        let values = format!("{} = {}", option.name, option.values.join("|"));
+        lines.push(format!("  {:<36} {}", values, option.description));
+    }
+    lines.push("".to_string());
+    lines.push("".to_string());
+
+    lines.extend(STATIC_HELP.iter().map(|line| line.to_string()));
+    lines
+}
+
+/// Keys bound to `action` in `mode` for short hints, e.g. `Enter/r` (or `-` if unbound)
+fn key_hint(keymap: &Keymap, mode: &AppMode, action: Action) -> String {
+    let chords = keymap.chords(mode, action);
+    if chords.is_empty() {
+        return "-".to_string();
+    }
+    chords
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+fn highlight_matches<'a>(
+    text: &str,
+    indices: &[usize],
+    style: Style,
+    match_style: Style,
+) -> Vec<Span<'a>> {
+    // group consecutive characters with the same style into a single span:
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut current_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = indices.contains(&i);
+        if matched != current_matched && !current.is_empty() {
+            let span_style = if current_matched { match_style } else { style };
+            spans.push(Span::styled(std::mem::take(&mut current), span_style));
+        }
+        current_matched = matched;
+        current.push(c);
+    }
+    if !current.is_empty() {
+        let span_style = if current_matched { match_style } else { style };
+        spans.push(Span::styled(current, span_style));
+    }
+    spans
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(ratatui::layout::Direction::Vertical)
@@ -487,39 +1602,22 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 }
 
 // Consts:
-pub static HELP_DIALOG: [&str; 43] = [
-    "Clexp Quick Help",
-    // todo: "For more help, see documentation at XYZ"
-    "",
-    // Navigation:
-    "Navigation:",
-    "  ↑↓              Move selection",
-    "  ←               Up one directory",
-    "  →, Enter        Enter Directory/Open File",
-    "  q, Ctrl+C       Quit",
-    "  :               Filter files",
-    "  /               Enter command",
-    "  ?, :help        Show this help",
-    "  C               Show clipboard",
-    "",
-    "",
-    // File operations:
-    "File operations:",
-    "  r               Rename selected file/dir",
-    "  d               Delete selected file(s)/dir(s)",
-    "  x               Cut selected file(s)/dir(s)",
-    "  c               Copy selected file(s)/dir(s)",
-    "  v               Paste selected file(s)/dir(s)",
-    "  n               New file",
-    "  N               New directory",
-    "",
-    "",
-    // Modes:
-    "Modes:",
-    "  s               Multi-Select Mode",
-    "  Space           Toggle selection in Multi-Select Mode",
-    "  Esc, s          Exit Multi-Select Mode",
-    "  [x]             Indicates selected files in Multi-Select Mode",
+const MAX_VISIBLE_JOBS: usize = 5;
+const MAX_SUGGESTIONS: usize = 8;
+const GIT_MARKERS_WIDTH: usize = 3; // at most this many git status markers per entry
+
+/// Help text that doesn't come from the keymap
+static STATIC_HELP: [&str; 23] = [
+    // Text input:
+    "Text input (rename, create, filter, command):",
+    "  ←→, Home, End   Move cursor",
+    "  Enter           Confirm",
+    "  Esc             Cancel (clears the filter in the filter bar)",
+    "  Tab             Cycle substring/glob/fuzzy (filter bar)",
+    "  Tab, Shift+Tab  Complete commands, paths and options (command mode)",
+    "  ↑↓, Ctrl+P/N    Earlier entries starting with what's typed (Ctrl+P/N in the filter bar)",
+    "  Ctrl+R          Search earlier entries (again: older match, Esc: cancel)",
+    "                  Quote or \\-escape paths with spaces",
     "",
     "",
     // Mouse controls (not implemented yet)
@@ -530,12 +1628,11 @@ pub static HELP_DIALOG: [&str; 43] = [
     "  Scroll wheel    Move selection up/down (or scroll modals)",
     "",
     "",
-    // Commands:
-    "Command Mode:",
-    "  q              Quit",
-    "  s <term>       Filter View",
-    "  h or :help     Show this help",
-    "",
+    // Config:
+    "Keys can be rebound in $XDG_CONFIG_HOME/clexp/config.toml, e.g.",
+    "  keymap = \"vim\"          (hjkl navigation)",
+    "  [keys.normal]",
+    "  \"ctrl+d\" = \"delete\"    (\"none\" unbinds a key)",
     "",
     //todo allow user to create own commands? need to think about how to store commands between program instances
 ];
@@ -1,5 +1,14 @@
+use anyhow::{Context, anyhow};
 use chrono::{DateTime, Local};
-use std::time::SystemTime;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    collections::hash_map::RandomState,
+    fs,
+    hash::{BuildHasher, Hasher},
+    io::{ErrorKind, Write},
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
 
 pub static DOUBLE_CLICK_DURATION: u128 = 400;
 
@@ -94,3 +103,121 @@ pub fn format_time(time: SystemTime) -> String {
     let date_time: DateTime<Local> = time.into();
     date_time.format("%Y-%m-%d %H:%M").to_string()
 }
+
+/// Base directory for user data files (`$XDG_DATA_HOME`, falling back to `~/.local/share`)
+pub fn data_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+}
+
+/// Base directory for user config files (`$XDG_CONFIG_HOME`, falling back to `~/.config`)
+pub fn config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+/// Read a data file written by `save_toml`, or `None` if there isn't one yet (`file` is
+/// `None` when there's no data directory to keep it in)
+pub fn load_toml<T: DeserializeOwned>(file: Option<&Path>) -> anyhow::Result<Option<T>> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading '{}'", file.display())),
+    };
+    toml::from_str(&contents)
+        .map(Some)
+        .with_context(|| format!("in '{}'", file.display()))
+}
+
+/// Save a data file by writing a new file then swapping it in, so a crash never leaves a
+/// half-written one. The new file gets a unique name, so other instances saving at the same
+/// time can't write into it
+pub fn save_toml<T: Serialize>(file: Option<&Path>, value: &T) -> anyhow::Result<()> {
+    let file = file.ok_or_else(|| anyhow!("Could not determine the user data directory"))?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = toml::to_string(value)?;
+
+    let suffix = RandomState::new().build_hasher().finish();
+    let temp_file = file.with_extension(format!("toml.{}-{:x}.tmp", std::process::id(), suffix));
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_file)
+        .and_then(|mut temp| temp.write_all(contents.as_bytes()))
+        .and_then(|()| fs::rename(&temp_file, file));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_file);
+        return Err(e).with_context(|| format!("saving '{}'", file.display()));
+    }
+    Ok(())
+}
+
+/// Insert a counter before the extension of a file name, e.g. `notes (2).txt`
+pub fn numbered_name(name: &str, n: usize) -> String {
+    labelled_name(name, &n.to_string())
+}
+
+/// Name for a copy of a file in its own directory, e.g. `notes (copy).txt`, `notes (copy 2).txt`
+pub fn copy_name(name: &str, n: usize) -> String {
+    if n == 1 {
+        labelled_name(name, "copy")
+    } else {
+        labelled_name(name, &format!("copy {}", n))
+    }
+}
+
+fn labelled_name(name: &str, label: &str) -> String {
+    let path = Path::new(name);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) => format!(
+            "{} ({}).{}",
+            stem.to_string_lossy(),
+            label,
+            ext.to_string_lossy()
+        ),
+        _ => format!("{} ({})", name, label),
+    }
+}
+
+/// Remove a file or an entire directory tree
+pub fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() && !path.is_symlink() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Expand a leading `~` to the home directory, like a shell
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    match (path.strip_prefix('~'), home) {
+        (Some(""), Some(home)) => home,
+        (Some(rest), Some(home)) if rest.starts_with('/') => home.join(&rest[1..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Resolve `.` and `..` components without touching the filesystem, like `cd` does
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
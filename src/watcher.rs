@@ -0,0 +1,119 @@
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+/// How long the directory has to be quiet before it's refreshed
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Refresh at least this often while changes keep coming (during a build, say)
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Watches the directories on screen for changes made outside clexp (by builds, other
+/// terminals, ...), using inotify on Linux
+pub struct DirWatcher {
+    watcher: Option<RecommendedWatcher>, // `None` when watching isn't available
+    events: Receiver<notify::Result<Event>>,
+    watched: Vec<PathBuf>,
+    changed: Vec<PathBuf>, // watched directories with changes not yet picked up
+    first_change: Option<Instant>,
+    last_change: Option<Instant>,
+}
+
+impl Default for DirWatcher {
+    /// A watcher that never reports anything
+    fn default() -> Self {
+        let (_, events) = mpsc::channel();
+        Self {
+            watcher: None,
+            events,
+            watched: Vec::new(),
+            changed: Vec::new(),
+            first_change: None,
+            last_change: None,
+        }
+    }
+}
+
+impl DirWatcher {
+    pub fn new() -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        Ok(Self {
+            watcher: Some(notify::recommended_watcher(sender)?),
+            events,
+            ..Self::default()
+        })
+    }
+
+    /// Watch exactly `dirs` (not their subdirectories), if they aren't watched already
+    pub fn watch(&mut self, dirs: &[&Path]) {
+        if self
+            .watched
+            .iter()
+            .map(PathBuf::as_path)
+            .eq(dirs.iter().copied())
+        {
+            return;
+        }
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+
+        for dir in &self.watched {
+            if !dirs.contains(&dir.as_path()) {
+                let _ = watcher.unwatch(dir);
+            }
+        }
+        for dir in dirs {
+            // a directory that can't be watched (no permission, say) just isn't refreshed:
+            if !self.watched.contains(&dir.to_path_buf()) {
+                let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+            }
+        }
+        self.watched = dirs.iter().map(|dir| dir.to_path_buf()).collect();
+        self.changed.retain(|dir| self.watched.contains(dir));
+    }
+
+    /// Watched directories that have changed, once they've settled down
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            // reading files (for the preview, say) doesn't change anything:
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                let dir = self
+                    .watched
+                    .iter()
+                    .find(|dir| *dir == path || path.parent() == Some(dir.as_path()));
+                if let Some(dir) = dir
+                    && !self.changed.contains(dir)
+                {
+                    self.changed.push(dir.clone());
+                }
+            }
+            self.first_change.get_or_insert(now);
+            self.last_change = Some(now);
+        }
+
+        let settled = self
+            .last_change
+            .is_some_and(|last| now.duration_since(last) >= DEBOUNCE);
+        let overdue = self
+            .first_change
+            .is_some_and(|first| now.duration_since(first) >= MAX_DELAY);
+        if !settled && !overdue {
+            return Vec::new();
+        }
+        self.first_change = None;
+        self.last_change = None;
+        std::mem::take(&mut self.changed)
+    }
+}